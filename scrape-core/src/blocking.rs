@@ -0,0 +1,104 @@
+//! the async api run on a runtime of its own, for programs that aren't async
+//!
+//! Like `reqwest::blocking` this must not be used from within an async runtime, where blocking on
+//! a download would stall it.
+//!
+//! ```no_run
+//! # fn links() -> Result<(), scrape_core::Error> {
+//! let scraper = scrape_core::blocking::Scraper::new();
+//! let links = scraper
+//!     .fetch("https://news.ycombinator.com")
+//!     .select(".titleline a")
+//!     .attribute("href")
+//!     .extract()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use reqwest::Client;
+use tokio::runtime::{self, Runtime};
+
+use crate::{Error, Page};
+
+/// downloads pages with one client, so connections are reused
+#[derive(Debug)]
+pub struct Scraper {
+    scraper: crate::Scraper,
+    runtime: Runtime,
+}
+
+impl Scraper {
+    /// panics if the runtime can't be started, like `reqwest::blocking::Client::new` does
+    pub fn new() -> Self {
+        Scraper::with_client(Client::new())
+    }
+
+    /// a scraper sending its requests with this client, e.g. to set a proxy or user agent
+    pub fn with_client(client: Client) -> Self {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start a runtime for scrape_core::blocking");
+        Scraper {
+            scraper: crate::Scraper::with_client(client),
+            runtime,
+        }
+    }
+
+    /// what to download and extract from it, nothing is sent before `page` or `extract`
+    pub fn fetch(&self, url: &str) -> Fetch<'_> {
+        Fetch {
+            fetch: self.scraper.fetch(url),
+            runtime: &self.runtime,
+        }
+    }
+}
+
+impl Default for Scraper {
+    fn default() -> Self {
+        Scraper::new()
+    }
+}
+
+/// a download and what to extract from the page, built up like a request
+#[derive(Debug)]
+pub struct Fetch<'a> {
+    fetch: crate::Fetch<'a>,
+    runtime: &'a Runtime,
+}
+
+impl Fetch<'_> {
+    /// the elements to extract, the whole body if there is no selector
+    pub fn select(self, selector: &str) -> Self {
+        Fetch {
+            fetch: self.fetch.select(selector),
+            ..self
+        }
+    }
+
+    /// extract the value of this attribute instead of the html of elements that have it
+    pub fn attribute(self, attribute: &str) -> Self {
+        Fetch {
+            fetch: self.fetch.attribute(attribute),
+            ..self
+        }
+    }
+
+    /// keep only the matches of a regex in what was extracted, their first group if it has one
+    pub fn matching(self, regex: &str) -> Self {
+        Fetch {
+            fetch: self.fetch.matching(regex),
+            ..self
+        }
+    }
+
+    /// downloads the page
+    pub fn page(self) -> Result<Page, Error> {
+        self.runtime.block_on(self.fetch.page())
+    }
+
+    /// downloads the page and extracts from it
+    pub fn extract(self) -> Result<Vec<String>, Error> {
+        self.runtime.block_on(self.fetch.extract())
+    }
+}
@@ -0,0 +1,129 @@
+//! turning response bytes into text in whatever encoding the page declares
+
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use regex::bytes::Regex;
+
+/// the `charset` parameter of a content type like `text/html; charset=iso-8859-1`
+fn from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| Encoding::for_label(value.trim().trim_matches('"').as_bytes()))?
+    })
+}
+
+/// `<meta charset>` or its `http-equiv` form, looked for in the first kilobyte like browsers do
+fn from_meta(bytes: &[u8]) -> Option<&'static Encoding> {
+    let meta = Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?([\w.:-]+)"#).unwrap();
+    let head = &bytes[..bytes.len().min(1024)];
+    let label = meta.captures(head)?.get(1)?.as_bytes();
+    // a page that got as far as declaring utf-16 in ascii is not utf-16
+    Encoding::for_label(label).map(|encoding| encoding.output_encoding())
+}
+
+/// the encoding of a body, or of its first kilobyte while downloading: its byte order mark, the
+/// content type, a meta tag, or windows-1252 for anything that isn't valid utf-8
+pub fn detect(bytes: &[u8], content_type: Option<&str>) -> &'static Encoding {
+    Encoding::for_bom(bytes)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| content_type.and_then(from_content_type))
+        .or_else(|| from_meta(bytes))
+        .unwrap_or(match std::str::from_utf8(bytes) {
+            Ok(_) => UTF_8,
+            // cut off in the middle of a character
+            Err(error) if error.error_len().is_none() => UTF_8,
+            Err(_) => WINDOWS_1252,
+        })
+}
+
+/// decodes the body in the encoding `detect` finds
+pub fn decode(bytes: &[u8], content_type: Option<&str>) -> String {
+    let (text, _, _) = detect(bytes, content_type).decode(bytes);
+    text.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{ISO_8859_2, SHIFT_JIS, UTF_16LE, WINDOWS_1251};
+
+    const META: &[u8] =
+        b"<html><head><meta charset=\"windows-1251\"></head><body>\xcf\xf0\xe8</body>";
+
+    #[test]
+    fn byte_order_mark() {
+        assert_eq!(detect(b"\xef\xbb\xbfhi", None), UTF_8);
+        assert_eq!(detect(b"\xff\xfeh\0i\0", None), UTF_16LE);
+        assert_eq!(decode(b"\xef\xbb\xbfh\xc3\xa9", None), "hé");
+        assert_eq!(decode(b"\xff\xfeh\0\xe9\0", None), "hé");
+    }
+
+    #[test]
+    fn content_type() {
+        assert_eq!(
+            detect(b"abc", Some("text/html; charset=ISO-8859-2")),
+            ISO_8859_2
+        );
+        assert_eq!(
+            detect(b"abc", Some("text/html;charset=\"shift_jis\"")),
+            SHIFT_JIS
+        );
+        assert_eq!(
+            detect(b"abc", Some("text/html; Charset = latin1")),
+            WINDOWS_1252
+        );
+        assert_eq!(detect(b"abc", Some("text/html")), UTF_8);
+        assert_eq!(detect(b"abc", Some("text/html; charset=nonsense")), UTF_8);
+    }
+
+    #[test]
+    fn meta_tags() {
+        assert_eq!(detect(META, None), WINDOWS_1251);
+        assert_eq!(
+            decode(META, None),
+            "<html><head><meta charset=\"windows-1251\"></head><body>При</body>"
+        );
+        let http_equiv =
+            b"<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=iso-8859-2\">";
+        assert_eq!(detect(http_equiv, None), ISO_8859_2);
+        assert_eq!(detect(b"<meta charset='shift_jis'>", None), SHIFT_JIS);
+        // declaring utf-16 in an ascii document means utf-8
+        assert_eq!(detect(b"<meta charset=utf-16>", None), UTF_8);
+        // only the first kilobyte is looked at
+        let late = [
+            b" ".repeat(1024).as_slice(),
+            b"<meta charset=windows-1251>\xcf\xf0\xe8",
+        ]
+        .concat();
+        assert_eq!(detect(&late, None), WINDOWS_1252);
+    }
+
+    #[test]
+    fn fallback() {
+        assert_eq!(detect("héllo".as_bytes(), None), UTF_8);
+        assert_eq!(detect(b"h\xe9llo", None), WINDOWS_1252);
+        assert_eq!(decode(b"h\xe9llo", None), "héllo");
+        // a multi-byte character cut off by the end of the first kilobyte
+        assert_eq!(detect(b"abc\xc3", None), UTF_8);
+        assert_eq!(detect(b"", None), UTF_8);
+    }
+
+    #[test]
+    fn precedence() {
+        let latin2 = Some("text/html; charset=iso-8859-2");
+        // the byte order mark wins over the content type and the meta tag
+        let bom = [b"\xef\xbb\xbf".as_slice(), META].concat();
+        assert_eq!(detect(&bom, latin2), UTF_8);
+        // the content type wins over the meta tag
+        assert_eq!(detect(META, latin2), ISO_8859_2);
+        // a content type without a usable charset leaves it to the meta tag
+        assert_eq!(detect(META, Some("text/html")), WINDOWS_1251);
+        assert_eq!(detect(META, Some("text/html; charset=bogus")), WINDOWS_1251);
+        // the meta tag wins over guessing from the bytes
+        assert_eq!(
+            detect(b"<meta charset=iso-8859-2>caf\xc3\xa9", None),
+            ISO_8859_2
+        );
+    }
+}
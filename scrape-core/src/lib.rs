@@ -0,0 +1,258 @@
+//! downloading pages and extracting from them what the `scrape` command line tool does, for other
+//! programs to do the same
+//!
+//! ```no_run
+//! # async fn links() -> Result<(), scrape_core::Error> {
+//! let scraper = scrape_core::Scraper::new();
+//! let links = scraper
+//!     .fetch("https://news.ycombinator.com")
+//!     .select(".titleline a")
+//!     .attribute("href")
+//!     .extract()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The `blocking` feature adds the same api for programs that aren't async.
+
+use regex::Regex;
+use reqwest::{header::HeaderMap, Client, Response, StatusCode, Url};
+use scraper::{Html, Selector};
+use std::fmt;
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod charset;
+
+#[derive(Debug)]
+pub enum Error {
+    /// the request failed or its body couldn't be read
+    Request {
+        url: String,
+        source: reqwest::Error,
+    },
+    InvalidSelector(String),
+    InvalidRegex(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Request { url, .. } => write!(f, "Failed to GET from '{}'", url),
+            Error::InvalidSelector(selector) => write!(f, "Invalid selector '{}'", selector),
+            Error::InvalidRegex(regex) => write!(f, "Invalid regex '{}'", regex),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// a downloaded document
+#[derive(Debug, Clone)]
+pub struct Page {
+    /// where the document ended up after redirects
+    pub url: Url,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+    /// how many bytes the body was before it was decoded
+    pub size: usize,
+    /// what a script run in the page returned, as json, for pages a browser rendered
+    pub evaluated: Option<String>,
+}
+
+impl Page {
+    pub fn document(&self) -> Html {
+        Html::parse_document(&self.body)
+    }
+}
+
+/// downloads pages with one client, so connections are reused
+#[derive(Debug, Clone, Default)]
+pub struct Scraper {
+    client: Client,
+}
+
+impl Scraper {
+    pub fn new() -> Self {
+        Scraper::default()
+    }
+
+    /// a scraper sending its requests with this client, e.g. to set a proxy or user agent
+    pub fn with_client(client: Client) -> Self {
+        Scraper { client }
+    }
+
+    /// what to download and extract from it, nothing is sent before `page` or `extract`
+    pub fn fetch(&self, url: &str) -> Fetch<'_> {
+        Fetch {
+            client: &self.client,
+            url: url.to_string(),
+            selector: None,
+            attribute: None,
+            regex: None,
+        }
+    }
+}
+
+/// a download and what to extract from the page, built up like a request
+#[derive(Debug)]
+pub struct Fetch<'a> {
+    client: &'a Client,
+    url: String,
+    selector: Option<String>,
+    attribute: Option<String>,
+    regex: Option<String>,
+}
+
+impl Fetch<'_> {
+    /// the elements to extract, the whole body if there is no selector
+    pub fn select(self, selector: &str) -> Self {
+        Fetch {
+            selector: Some(selector.to_string()),
+            ..self
+        }
+    }
+
+    /// extract the value of this attribute instead of the html of elements that have it
+    pub fn attribute(self, attribute: &str) -> Self {
+        Fetch {
+            attribute: Some(attribute.to_string()),
+            ..self
+        }
+    }
+
+    /// keep only the matches of a regex in what was extracted, their first group if it has one
+    pub fn matching(self, regex: &str) -> Self {
+        Fetch {
+            regex: Some(regex.to_string()),
+            ..self
+        }
+    }
+
+    /// downloads the page
+    pub async fn page(self) -> Result<Page, Error> {
+        fetch(self.client, &self.url).await
+    }
+
+    /// downloads the page and extracts from it
+    pub async fn extract(self) -> Result<Vec<String>, Error> {
+        let selector = self.selector.as_deref().map(parse_selector).transpose()?;
+        let regex = match &self.regex {
+            Some(regex) => Some(Regex::new(regex).map_err(|_| Error::InvalidRegex(regex.clone()))?),
+            None => None,
+        };
+        let page = fetch(self.client, &self.url).await?;
+        let values = match &selector {
+            Some(selector) => extract(&page.document(), selector, self.attribute.as_deref()),
+            None => vec![page.body],
+        };
+        Ok(match regex {
+            Some(regex) => values
+                .iter()
+                .flat_map(|value| regex_matches(&regex, value))
+                .collect(),
+            None => values,
+        })
+    }
+}
+
+/// downloads the url and decodes the body in the encoding the page declares
+pub async fn fetch(client: &Client, url: &str) -> Result<Page, Error> {
+    let res = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|source| Error::Request {
+            url: url.to_string(),
+            source,
+        })?;
+    read(url, res, |_| {}).await
+}
+
+/// reads the response to the request for the url into a page, telling `progress` how many bytes
+/// of the body arrived so far, for programs that send their own requests or look at the response
+/// before the body
+pub async fn read(
+    url: &str,
+    mut res: Response,
+    mut progress: impl FnMut(u64),
+) -> Result<Page, Error> {
+    let error = |source| Error::Request {
+        url: url.to_string(),
+        source,
+    };
+    let mut bytes = Vec::with_capacity(res.content_length().unwrap_or_default() as usize);
+    while let Some(chunk) = res.chunk().await.map_err(error)? {
+        bytes.extend_from_slice(&chunk);
+        progress(bytes.len() as u64);
+    }
+    let content_type = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    Ok(Page {
+        body: charset::decode(&bytes, content_type),
+        url: res.url().clone(),
+        status: res.status(),
+        headers: res.headers().clone(),
+        size: bytes.len(),
+        evaluated: None,
+    })
+}
+
+/// splits `selector@attribute`, the attribute part is optional
+pub fn split_attribute(spec: &str) -> (&str, Option<&str>) {
+    match spec.rsplit_once('@') {
+        Some((selector, attribute))
+            if !attribute.is_empty()
+                && attribute.chars().all(|c| c.is_alphanumeric() || c == '-') =>
+        {
+            (selector, Some(attribute))
+        }
+        _ => (spec, None),
+    }
+}
+
+pub fn parse_selector(selector: &str) -> Result<Selector, Error> {
+    Selector::parse(selector).map_err(|_| Error::InvalidSelector(selector.to_string()))
+}
+
+/// matched nodes as text, or the value of `attribute` where present
+pub fn extract(document: &Html, selector: &Selector, attribute: Option<&str>) -> Vec<String> {
+    document
+        .select(selector)
+        .map(|node| {
+            if let Some(attribute) = attribute.and_then(|a| node.value().attr(a)) {
+                attribute.to_string()
+            } else {
+                node.inner_html().trim().to_string()
+            }
+        })
+        .collect()
+}
+
+/// the matches of the regex in the text, their first group if it has one
+pub fn regex_matches(regex: &Regex, text: &str) -> Vec<String> {
+    regex
+        .captures_iter(text)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(0)))
+        .map(|value| value.as_str().to_string())
+        .collect()
+}
+
+/// a value as printed, prefixed with the page url and a tab in batch runs
+pub fn line(value: &str, prefix: Option<&Url>) -> String {
+    match prefix {
+        Some(url) => format!("{}\t{}", url, value),
+        None => value.to_string(),
+    }
+}
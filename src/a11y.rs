@@ -0,0 +1,106 @@
+//! a handful of automatic accessibility checks, no substitute for a real audit
+
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashSet;
+
+use crate::json::Json;
+
+fn select(selector: &str) -> Selector {
+    Selector::parse(selector).unwrap()
+}
+
+/// the opening tag, shortened, to point at the element in the report
+fn snippet(element: ElementRef) -> String {
+    let mut tag = format!("<{}", element.value().name());
+    for (name, value) in element.value().attrs() {
+        tag.push_str(&format!(" {}=\"{}\"", name, value));
+    }
+    tag.push('>');
+    match tag.char_indices().nth(100) {
+        Some((at, _)) => format!("{}...", &tag[..at]),
+        None => tag,
+    }
+}
+
+fn has_label_attribute(element: ElementRef) -> bool {
+    ["aria-label", "aria-labelledby", "title"]
+        .iter()
+        .any(|name| {
+            element
+                .value()
+                .attr(name)
+                .is_some_and(|v| !v.trim().is_empty())
+        })
+}
+
+/// visible text, or the alt text of images inside
+fn accessible_name(element: ElementRef) -> bool {
+    let text: String = element.text().collect();
+    !text.trim().is_empty()
+        || has_label_attribute(element)
+        || element.select(&select("img[alt]")).any(|img| {
+            !img.value()
+                .attr("alt")
+                .unwrap_or_default()
+                .trim()
+                .is_empty()
+        })
+}
+
+/// every issue found on the page as `{"rule": ..., "element": ...}`
+pub fn audit(document: &Html, url: &Url) -> Json {
+    let mut issues = Vec::new();
+    let mut issue = |rule: &str, message: &str, element: Option<ElementRef>| {
+        issues.push(Json::object([
+            ("rule", rule.into()),
+            ("message", message.into()),
+            ("element", element.map(snippet).into()),
+        ]))
+    };
+
+    let lang = document
+        .select(&select("html"))
+        .next()
+        .and_then(|html| html.value().attr("lang"));
+    if lang.is_none_or(|lang| lang.trim().is_empty()) {
+        issue("html-lang", "the page does not declare its language", None);
+    }
+
+    for image in document.select(&select("img:not([alt]), input[type=image]:not([alt])")) {
+        if !has_label_attribute(image) {
+            issue("image-alt", "image without alt text", Some(image));
+        }
+    }
+
+    let labelled: HashSet<_> = document
+        .select(&select("label[for]"))
+        .filter_map(|label| label.value().attr("for"))
+        .collect();
+    let controls = select(
+        "input:not([type=hidden]):not([type=submit]):not([type=button]):not([type=reset]):not([type=image]), select, textarea",
+    );
+    for control in document.select(&controls) {
+        let by_id = control.value().id().is_some_and(|id| labelled.contains(id));
+        let wrapped = control
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| ancestor.value().name() == "label");
+        if !by_id && !wrapped && !has_label_attribute(control) {
+            issue("form-label", "form field without a label", Some(control));
+        }
+    }
+
+    for link in document.select(&select("a[href]")) {
+        if !accessible_name(link) {
+            issue("link-name", "link without text", Some(link));
+        }
+    }
+    for button in document.select(&select("button")) {
+        if !accessible_name(button) {
+            issue("button-name", "button without text", Some(button));
+        }
+    }
+
+    Json::object([("url", url.as_str().into()), ("issues", issues.into())])
+}
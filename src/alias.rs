@@ -0,0 +1,168 @@
+//! command lines saved under a name in `~/.config/scrape/aliases.toml`, run as `scrape NAME`
+
+use clap::CommandFactory;
+use regex::{Captures, Regex};
+use std::{ffi::OsString, fs, path::PathBuf};
+
+use crate::{curl, dirs, json::Json, toml, Args, Error};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct AliasArgs {
+    #[clap(subcommand)]
+    command: AliasCommand,
+}
+
+#[derive(clap::Subcommand, Clone, Debug)]
+enum AliasCommand {
+    /// save a command line, `{1}`, `{2}`... in it are filled in with the arguments of `scrape NAME`
+    Add(AddArgs),
+    /// list the saved aliases
+    List,
+    /// forget an alias
+    Remove(RemoveArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct AddArgs {
+    /// what to call it
+    name: String,
+
+    /// the arguments to run scrape with, quoted like in a shell
+    command: String,
+
+    /// replace an alias of the same name
+    #[clap(short, long)]
+    force: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct RemoveArgs {
+    name: String,
+}
+
+fn path() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("aliases.toml"))
+        .ok_or_else(|| "can't find the config directory".to_string())
+}
+
+/// the saved aliases, in the order they were added
+fn load() -> Result<Vec<(String, String)>, String> {
+    let path = path()?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    match toml::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))? {
+        Json::Object(fields) => fields
+            .into_iter()
+            .map(|(name, command)| match command {
+                Json::String(command) => Ok((name, command)),
+                _ => Err(format!("{}: '{}' is not a string", path.display(), name)),
+            })
+            .collect(),
+        _ => unreachable!(),
+    }
+}
+
+fn save(aliases: &[(String, String)]) -> Result<(), String> {
+    let path = path()?;
+    let text: String = aliases
+        .iter()
+        .map(|(name, command)| format!("{} = {}\n", name, Json::from(command.as_str())))
+        .collect();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create '{}': {}", dir.display(), e))?;
+    }
+    fs::write(&path, text).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))
+}
+
+fn is_subcommand(name: &str) -> bool {
+    Args::command()
+        .get_subcommands()
+        .any(|command| command.get_name() == name)
+}
+
+/// the words of the command with `{N}` replaced by the Nth argument, and the arguments no `{N}`
+/// asked for after them
+fn substitute(name: &str, command: &str, arguments: &[OsString]) -> Result<Vec<OsString>, String> {
+    let placeholder = Regex::new(r"\{(\d+)\}").unwrap();
+    let argument = |number: &str| {
+        let number: usize = number.parse().unwrap_or(0);
+        match number.checked_sub(1).and_then(|index| arguments.get(index)) {
+            Some(argument) => Ok((number, argument.to_string_lossy())),
+            None => Err(format!(
+                "alias '{}' needs an argument for {{{}}}",
+                name, number
+            )),
+        }
+    };
+    let mut used = 0;
+    let mut words = Vec::new();
+    for word in curl::split(command).map_err(|e| format!("alias '{}': {}", name, e))? {
+        for captures in placeholder.captures_iter(&word) {
+            used = used.max(argument(&captures[1])?.0);
+        }
+        let word = placeholder.replace_all(&word, |captures: &Captures| {
+            argument(&captures[1]).unwrap().1.into_owned()
+        });
+        words.push(OsString::from(word.into_owned()));
+    }
+    words.extend(arguments.iter().skip(used).cloned());
+    Ok(words)
+}
+
+/// the command line with the alias it starts with, if any, in place of its name
+pub fn expand(args: Vec<OsString>) -> Result<Vec<OsString>, String> {
+    let name = match args.get(1).and_then(|arg| arg.to_str()) {
+        Some(name) if !name.starts_with('-') && !is_subcommand(name) => name.to_string(),
+        _ => return Ok(args),
+    };
+    let command = match load()?.into_iter().find(|(n, _)| *n == name) {
+        Some((_, command)) => command,
+        None => return Ok(args),
+    };
+    let mut expanded = vec![args[0].clone()];
+    expanded.extend(substitute(&name, &command, &args[2..])?);
+    Ok(expanded)
+}
+
+pub fn run(args: AliasArgs) -> Result<(), Error> {
+    let mut aliases = load()?;
+    match args.command {
+        AliasCommand::Add(add) => {
+            let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+            if add.name.is_empty() || !add.name.chars().all(valid) {
+                return Err(format!("Invalid alias name '{}'", add.name).into());
+            }
+            if is_subcommand(&add.name) {
+                return Err(format!("'{}' is already a scrape command", add.name).into());
+            }
+            curl::split(&add.command)?;
+            match aliases.iter_mut().find(|(name, _)| *name == add.name) {
+                Some(_) if !add.force => {
+                    return Err(format!("Alias '{}' exists, --force replaces it", add.name).into())
+                }
+                Some((_, command)) => *command = add.command,
+                None => aliases.push((add.name, add.command)),
+            }
+            save(&aliases)?;
+        }
+        AliasCommand::List => {
+            for (name, command) in aliases {
+                println!("{}\t{}", name, command);
+            }
+        }
+        AliasCommand::Remove(remove) => {
+            let count = aliases.len();
+            aliases.retain(|(name, _)| *name != remove.name);
+            if aliases.len() == count {
+                return Err(format!("No alias '{}'", remove.name).into());
+            }
+            save(&aliases)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,404 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::Client;
+
+use crate::{
+    datetime, gzip, interrupt, json::Json, mirror::local_path, s3::Location, snapshot, Page,
+};
+
+struct Entry {
+    name: String,
+    data: Vec<u8>,
+    manifest: Json,
+}
+
+/// everything fetched and extracted during this run, written out at the end
+static ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+/// result lines, only kept once `collect_results` was called
+static RESULTS: Mutex<Option<String>> = Mutex::new(None);
+/// FNV-1a of every result line so far, which doesn't need to keep them
+static RESULTS_HASH: Mutex<u64> = Mutex::new(snapshot::FNV1A_EMPTY);
+
+pub fn add_page(page: &Page) {
+    let name = format!("pages/{}", local_path(&page.url, true).display());
+    let mut entries = ENTRIES.lock().unwrap();
+    if entries.iter().any(|entry| entry.name == name) {
+        return;
+    }
+    let content_type = page
+        .headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|t| t.to_str().ok());
+    let manifest = Json::object([
+        ("path", name.as_str().into()),
+        ("url", page.url.as_str().into()),
+        ("status", page.status.as_u16().into()),
+        ("content_type", content_type.into()),
+        ("size", page.body.len().into()),
+        ("fetched_at", datetime::now().into()),
+    ]);
+    entries.push(Entry {
+        name,
+        data: page.body.as_bytes().to_vec(),
+        manifest,
+    });
+}
+
+/// keeps the result lines from now on, for the `results.txt` of an archive
+pub fn collect_results() {
+    RESULTS.lock().unwrap().get_or_insert_with(String::new);
+}
+
+/// remembers a line of output for `results.txt` and the hash of all results
+pub fn add_result(line: &str) {
+    let mut hash = RESULTS_HASH.lock().unwrap();
+    *hash = snapshot::fnv1a_extend(snapshot::fnv1a_extend(*hash, line), "\n");
+    if let Some(results) = RESULTS.lock().unwrap().as_mut() {
+        results.push_str(line);
+        results.push('\n');
+    }
+}
+
+/// the FNV-1a of every result line printed so far
+pub fn results_hash() -> u64 {
+    *RESULTS_HASH.lock().unwrap()
+}
+
+/// the fetched pages with `manifest.json` and `results.txt`, emptying what was collected
+fn take() -> Vec<Entry> {
+    let mut entries = std::mem::take(&mut *ENTRIES.lock().unwrap());
+    let manifest = Json::Array(entries.iter().map(|e| e.manifest.clone()).collect());
+    let results = RESULTS.lock().unwrap().take().unwrap_or_default();
+    entries.push(Entry {
+        name: "manifest.json".into(),
+        data: format!("{}\n", manifest).into_bytes(),
+        manifest: Json::Null,
+    });
+    if !results.is_empty() {
+        entries.push(Entry {
+            name: "results.txt".into(),
+            data: results.into_bytes(),
+            manifest: Json::Null,
+        });
+    }
+    if interrupt::interrupted() {
+        entries.push(Entry {
+            name: "PARTIAL".into(),
+            data: format!("interrupted at {}\n", datetime::now()).into_bytes(),
+            manifest: Json::Null,
+        });
+    }
+    entries
+}
+
+/// uploads what `write` would bundle as separate objects under the location's prefix
+pub async fn upload(client: &Client, location: &Location) -> Result<(), String> {
+    for entry in take() {
+        let content_type = match entry.name.as_str() {
+            "manifest.json" => "application/json",
+            "results.txt" => "text/plain; charset=utf-8",
+            _ => entry
+                .manifest
+                .get("content_type")
+                .and_then(Json::as_str)
+                .unwrap_or("application/octet-stream"),
+        };
+        location
+            .put(client, &entry.name, entry.data, content_type)
+            .await?;
+    }
+    Ok(())
+}
+
+/// writes a `.zip`, `.tar.gz`/`.tgz` or `.tar` depending on the file name
+pub fn write(path: &Path) -> io::Result<()> {
+    let entries = take();
+    let name = path.to_string_lossy();
+    let archive = if name.ends_with(".zip") {
+        zip(&entries)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        gzip::compress(&tar(&entries))
+    } else {
+        tar(&entries)
+    };
+    fs::File::create(path)?.write_all(&archive)
+}
+
+fn octal(field: &mut [u8], value: u64) {
+    let width = field.len() - 1;
+    let digits = format!("{:0width$o}", value, width = width);
+    field[..width].copy_from_slice(&digits.as_bytes()[digits.len() - width..]);
+}
+
+/// a ustar header, `None` if the name fits neither the name field nor the prefix and name fields
+fn header(name: &[u8], size: u64, mtime: u64, kind: u8) -> Option<[u8; 512]> {
+    let mut header = [0u8; 512];
+    // ustar keeps up to 155 bytes of directories in a separate prefix field
+    let split = match name.len() {
+        0..=100 => None,
+        _ => Some(
+            name[..name.len().min(156)]
+                .iter()
+                .rposition(|b| *b == b'/')?,
+        ),
+    };
+    match split {
+        None => header[..name.len()].copy_from_slice(name),
+        Some(split) if name.len() - split - 1 <= 100 => {
+            header[345..345 + split].copy_from_slice(&name[..split]);
+            header[..name.len() - split - 1].copy_from_slice(&name[split + 1..]);
+        }
+        Some(_) => return None,
+    }
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], mtime);
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|b| *b as u64).sum();
+    octal(&mut header[148..155], checksum);
+    Some(header)
+}
+
+/// a pax extended header record, `<length> <key>=<value>\n` where the length counts itself
+fn pax_record(key: &str, value: &[u8]) -> Vec<u8> {
+    let rest = key.len() + value.len() + 3;
+    let mut length = rest + 1;
+    while length != rest + length.to_string().len() {
+        length = rest + length.to_string().len();
+    }
+    let mut record = format!("{} {}=", length, key).into_bytes();
+    record.extend_from_slice(value);
+    record.push(b'\n');
+    record
+}
+
+fn pad(out: &mut Vec<u8>) {
+    out.resize(out.len().div_ceil(512) * 512, 0);
+}
+
+fn tar(entries: &[Entry]) -> Vec<u8> {
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut out = Vec::new();
+    for entry in entries {
+        let name = entry.name.as_bytes();
+        let size = entry.data.len() as u64;
+        let header = match header(name, size, mtime, b'0') {
+            Some(header) => header,
+            None => {
+                // names too long for ustar go in a pax header that applies to the next entry,
+                // readers without pax support see the name cut to its last 100 bytes
+                let record = pax_record("path", name);
+                let pax_name = format!("PaxHeaders/{}", entry.name.len());
+                out.extend_from_slice(
+                    &header(pax_name.as_bytes(), record.len() as u64, mtime, b'x').unwrap(),
+                );
+                out.extend_from_slice(&record);
+                pad(&mut out);
+                header(&name[name.len() - 100..], size, mtime, b'0').unwrap()
+            }
+        };
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&entry.data);
+        pad(&mut out);
+    }
+    out.resize(out.len() + 1024, 0);
+    out
+}
+
+fn zip(entries: &[Entry]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for entry in entries {
+        let compressed = gzip::deflate(&entry.data);
+        let offset = out.len() as u32;
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0x0800u16.to_le_bytes());
+        common.extend_from_slice(&8u16.to_le_bytes());
+        common.extend_from_slice(&0u32.to_le_bytes());
+        common.extend_from_slice(&gzip::crc32(&entry.data).to_le_bytes());
+        common.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&common);
+        out.extend_from_slice(entry.name.as_bytes());
+        out.extend_from_slice(&compressed);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        central.extend_from_slice(&[0; 6]);
+        central.extend_from_slice(&0o100644u32.wrapping_shl(16).to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(entry.name.as_bytes());
+    }
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        let deep = format!("pages/{}/index.html", "directory/".repeat(20));
+        let long = format!("pages/{}.html", "n".repeat(150));
+        [
+            ("manifest.json", b"[]\n".to_vec()),
+            ("pages/example.com/index.html", b"<h1>hi</h1>".repeat(100)),
+            (deep.as_str(), b"deep".to_vec()),
+            (long.as_str(), b"long".to_vec()),
+            ("empty.txt", vec![]),
+        ]
+        .into_iter()
+        .map(|(name, data)| Entry {
+            name: name.to_string(),
+            data,
+            manifest: Json::Null,
+        })
+        .collect()
+    }
+
+    fn field(header: &[u8], range: std::ops::Range<usize>) -> &str {
+        let field = &header[range];
+        let end = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+        std::str::from_utf8(&field[..end]).unwrap()
+    }
+
+    fn number(header: &[u8], range: std::ops::Range<usize>) -> usize {
+        usize::from_str_radix(field(header, range).trim(), 8).unwrap()
+    }
+
+    /// names and contents of the files in a tar, checking every header's checksum
+    fn untar(mut tar: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut files = vec![];
+        let mut pax_path = None;
+        while tar[..512].iter().any(|b| *b != 0) {
+            let (header, rest) = tar.split_at(512);
+            let mut blank = header.to_vec();
+            blank[148..156].fill(b' ');
+            let sum: usize = blank.iter().map(|b| *b as usize).sum();
+            assert_eq!(number(header, 148..156), sum);
+            assert_eq!(&header[257..263], b"ustar\0");
+
+            let size = number(header, 124..136);
+            let data = &rest[..size];
+            match header[156] {
+                b'x' => {
+                    let record = std::str::from_utf8(data).unwrap();
+                    let (length, rest) = record.split_once(' ').unwrap();
+                    assert_eq!(length.parse::<usize>().unwrap(), record.len());
+                    let path = rest.strip_prefix("path=").unwrap();
+                    pax_path = Some(path.strip_suffix('\n').unwrap().to_string());
+                }
+                b'0' => {
+                    let name = match (field(header, 345..500), field(header, 0..100)) {
+                        ("", name) => name.to_string(),
+                        (prefix, name) => format!("{}/{}", prefix, name),
+                    };
+                    files.push((pax_path.take().unwrap_or(name), data.to_vec()));
+                }
+                kind => panic!("unexpected entry type {}", kind),
+            }
+            tar = &rest[size.div_ceil(512) * 512..];
+        }
+        assert_eq!(tar.len(), 1024);
+        files
+    }
+
+    fn u16_at(data: &[u8], at: usize) -> usize {
+        u16::from_le_bytes([data[at], data[at + 1]]) as usize
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    /// names and contents of the files in a zip, read through its central directory
+    fn unzip(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(zip, end), 0x0605_4b50);
+        let count = u16_at(zip, end + 10);
+        let mut at = u32_at(zip, end + 16) as usize;
+        let mut files = vec![];
+        for _ in 0..count {
+            assert_eq!(u32_at(zip, at), 0x0201_4b50);
+            let (crc, compressed, size) = (
+                u32_at(zip, at + 16),
+                u32_at(zip, at + 20),
+                u32_at(zip, at + 24),
+            );
+            let name_len = u16_at(zip, at + 28);
+            let name = std::str::from_utf8(&zip[at + 46..at + 46 + name_len]).unwrap();
+            let local = u32_at(zip, at + 42) as usize;
+
+            assert_eq!(u32_at(zip, local), 0x0403_4b50);
+            assert_eq!(u16_at(zip, local + 8), 8);
+            assert_eq!(u32_at(zip, local + 14), crc);
+            assert_eq!(&zip[local + 30..local + 30 + name_len], name.as_bytes());
+            let start = local + 30 + name_len + u16_at(zip, local + 28);
+            let deflated = &zip[start..start + compressed as usize];
+            let (data, used) = gzip::inflate(deflated).unwrap();
+            assert_eq!(used, deflated.len());
+            assert_eq!(data.len(), size as usize);
+            assert_eq!(gzip::crc32(&data), crc);
+            files.push((name.to_string(), data));
+            at += 46 + name_len + u16_at(zip, at + 30) + u16_at(zip, at + 32);
+        }
+        assert_eq!(at, end);
+        files
+    }
+
+    fn expected() -> Vec<(String, Vec<u8>)> {
+        entries().into_iter().map(|e| (e.name, e.data)).collect()
+    }
+
+    #[test]
+    fn tar_reads_back() {
+        assert_eq!(untar(&tar(&entries())), expected());
+    }
+
+    #[test]
+    fn long_names_get_a_pax_header() {
+        let tar = tar(&entries());
+        let long = format!("pages/{}.html", "n".repeat(150));
+        let pax = tar.chunks(512).find(|block| block[156] == b'x').unwrap();
+        assert_eq!(field(pax, 0..100), "PaxHeaders/161");
+        assert_eq!(pax_record("path", long.as_bytes()).len(), 171);
+        assert_eq!(pax_record("path", b"a"), b"9 path=a\n");
+        assert_eq!(
+            pax_record("path", &[b'a'; 90]),
+            [b"99 path=".as_slice(), &[b'a'; 90], b"\n"].concat()
+        );
+        assert_eq!(pax_record("path", &[b'a'; 91]).len(), 101);
+    }
+
+    #[test]
+    fn zip_reads_back() {
+        assert_eq!(unzip(&zip(&entries())), expected());
+    }
+}
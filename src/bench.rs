@@ -0,0 +1,162 @@
+use futures_util::{stream, StreamExt};
+use reqwest::{Client, StatusCode};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{json::Json, timings::millis, Error, Format};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct BenchArgs {
+    /// the url to request
+    url: String,
+
+    /// how many requests to make
+    #[clap(short = 'n', long, default_value_t = 100)]
+    requests: usize,
+
+    /// how many requests to have in flight at once
+    #[clap(short, long, default_value_t = 10)]
+    concurrency: usize,
+
+    /// `jsonl` prints the report as a single json object
+    #[clap(short, long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+/// how one request went
+enum Outcome {
+    Response {
+        status: StatusCode,
+        bytes: usize,
+        latency: Duration,
+    },
+    Failed,
+}
+
+async fn request(client: &Client, url: &str) -> Outcome {
+    let started = Instant::now();
+    let res = match client.get(url).send().await {
+        Ok(res) => res,
+        Err(_) => return Outcome::Failed,
+    };
+    let status = res.status();
+    match res.bytes().await {
+        Ok(body) => Outcome::Response {
+            status,
+            bytes: body.len(),
+            latency: started.elapsed(),
+        },
+        Err(_) => Outcome::Failed,
+    }
+}
+
+pub async fn run(client: &Client, args: BenchArgs) -> Result<(), Error> {
+    if args.requests == 0 || args.concurrency == 0 {
+        return Err("-n and -c need to be at least 1".into());
+    }
+    let started = Instant::now();
+    let outcomes: Vec<_> = stream::iter(0..args.requests)
+        .map(|_| request(client, &args.url))
+        .buffer_unordered(args.concurrency)
+        .collect()
+        .await;
+    let elapsed = started.elapsed();
+
+    let mut latencies = Vec::new();
+    let mut statuses = BTreeMap::new();
+    let (mut bytes, mut failed) = (0, 0);
+    for outcome in &outcomes {
+        match outcome {
+            Outcome::Response {
+                status,
+                bytes: size,
+                latency,
+            } => {
+                *statuses.entry(status.as_u16()).or_insert(0usize) += 1;
+                bytes += size;
+                latencies.push(*latency);
+            }
+            Outcome::Failed => failed += 1,
+        }
+    }
+    latencies.sort();
+    let errors = failed
+        + statuses
+            .iter()
+            .filter(|(status, _)| **status >= 400)
+            .map(|(_, count)| count)
+            .sum::<usize>();
+    let percentile = |p: f64| {
+        let index = ((latencies.len() as f64 - 1.0) * p).round() as usize;
+        latencies.get(index).copied().map(millis)
+    };
+    let mean = match latencies.is_empty() {
+        true => None,
+        false => Some(millis(
+            latencies.iter().sum::<Duration>() / latencies.len() as u32,
+        )),
+    };
+    let rate = args.requests as f64 / elapsed.as_secs_f64();
+    let latency = [
+        ("min", percentile(0.0)),
+        ("mean", mean),
+        ("p50", percentile(0.5)),
+        ("p90", percentile(0.9)),
+        ("p99", percentile(0.99)),
+        ("max", percentile(1.0)),
+    ];
+
+    match args.format {
+        Format::Text => {
+            println!(
+                "{} requests, {} at a time, in {:.2}s: {:.1} requests/s, {:.1} KiB/s",
+                args.requests,
+                args.concurrency,
+                elapsed.as_secs_f64(),
+                rate,
+                bytes as f64 / 1024.0 / elapsed.as_secs_f64()
+            );
+            let statuses: Vec<_> = statuses
+                .iter()
+                .map(|(status, count)| format!("{} x{}", status, count))
+                .collect();
+            if !statuses.is_empty() {
+                println!("status: {}", statuses.join(", "));
+            }
+            println!("errors: {} ({} without a response)", errors, failed);
+            if !latencies.is_empty() {
+                let latency: Vec<_> = latency
+                    .iter()
+                    .filter_map(|(name, ms)| ms.map(|ms| format!("{} {:.1}ms", name, ms)))
+                    .collect();
+                println!("latency: {}", latency.join("  "));
+            }
+        }
+        Format::Jsonl => {
+            let statuses = statuses
+                .iter()
+                .map(|(status, count)| (status.to_string(), (*count).into()))
+                .collect();
+            let latency = latency
+                .iter()
+                .map(|(name, ms)| (name.to_string(), (*ms).into()))
+                .collect();
+            let report = Json::object([
+                ("url", args.url.as_str().into()),
+                ("requests", args.requests.into()),
+                ("concurrency", args.concurrency.into()),
+                ("seconds", elapsed.as_secs_f64().into()),
+                ("requests_per_second", ((rate * 10.0).round() / 10.0).into()),
+                ("bytes", bytes.into()),
+                ("errors", errors.into()),
+                ("failed", failed.into()),
+                ("status", Json::Object(statuses)),
+                ("latency_ms", Json::Object(latency)),
+            ]);
+            println!("{}", report);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use crate::sha256;
+
+/// set membership with false positives but a fixed memory footprint
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// sized for `items` entries at the given false-positive rate
+    pub fn new(items: usize, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let items = items.max(1) as f64;
+        let bits =
+            (-items * false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5).ln() / (ln2 * ln2)).ceil();
+        let hashes = ((bits / items) * ln2).round().max(1.0) as u32;
+        BloomFilter {
+            bits: vec![0; (bits as usize).div_ceil(64)],
+            hashes,
+        }
+    }
+
+    pub fn from_parts(bits: Vec<u64>, hashes: u32) -> Self {
+        BloomFilter { bits, hashes }
+    }
+
+    pub fn parts(&self) -> (&[u64], u32) {
+        (&self.bits, self.hashes)
+    }
+
+    /// double hashing with halves of a sha256, which unlike the std hasher stays the same across
+    /// rust versions, so saved filters keep working
+    fn positions(&self, item: &str) -> impl Iterator<Item = usize> {
+        let digest = sha256::digest(item.as_bytes());
+        let half = |at: usize| u64::from_le_bytes(digest[at..at + 8].try_into().unwrap());
+        let (h1, h2) = (half(0), half(8));
+        let len = self.bits.len() as u64 * 64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    /// returns `false` if the item was (probably) already present
+    pub fn insert(&mut self, item: &str) -> bool {
+        let positions: Vec<_> = self.positions(item).collect();
+        let mut added = false;
+        for bit in positions {
+            let word = &mut self.bits[bit / 64];
+            added |= *word & (1 << (bit % 64)) == 0;
+            *word |= 1 << (bit % 64);
+        }
+        added
+    }
+}
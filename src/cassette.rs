@@ -0,0 +1,92 @@
+//! recording the pages of a run to replay them later without going online
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode, Url,
+};
+use std::{fs, io, path::Path, sync::Mutex};
+
+use crate::{json::Json, Page};
+
+/// pages downloaded so far when recording
+static RECORDED: Mutex<Vec<Json>> = Mutex::new(Vec::new());
+/// the recorded pages and whether they were replayed yet, none unless replaying
+static REPLAYING: Mutex<Option<Vec<(bool, Json)>>> = Mutex::new(None);
+
+pub fn record(url: &str, page: &Page) {
+    let headers = page.headers.iter().map(|(name, value)| {
+        let value = String::from_utf8_lossy(value.as_bytes());
+        Json::Array(vec![name.as_str().into(), value.as_ref().into()])
+    });
+    RECORDED.lock().unwrap().push(Json::object([
+        ("request", url.into()),
+        ("url", page.url.as_str().into()),
+        ("status", page.status.as_u16().into()),
+        ("headers", Json::Array(headers.collect())),
+        ("body", page.body.as_str().into()),
+    ]));
+}
+
+pub fn write(path: &Path) -> io::Result<()> {
+    let recorded = std::mem::take(&mut *RECORDED.lock().unwrap());
+    fs::write(path, Json::Array(recorded).to_string() + "\n")
+}
+
+pub fn load(path: &Path) -> Result<(), String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let recorded = match Json::parse(&text) {
+        Ok(Json::Array(recorded)) => recorded,
+        _ => return Err(format!("'{}' is not a cassette", path.display())),
+    };
+    *REPLAYING.lock().unwrap() = Some(recorded.into_iter().map(|r| (false, r)).collect());
+    Ok(())
+}
+
+fn page(recorded: &Json) -> Option<Page> {
+    let mut headers = HeaderMap::new();
+    if let Some(Json::Array(pairs)) = recorded.get("headers") {
+        for pair in pairs {
+            if let Json::Array(pair) = pair {
+                let name = pair.first().and_then(Json::as_str)?;
+                let value = pair.get(1).and_then(Json::as_str)?;
+                headers.append(
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(value).ok()?,
+                );
+            }
+        }
+    }
+    let status = match recorded.get("status") {
+        Some(Json::Number(status)) => StatusCode::from_u16(*status as u16).ok()?,
+        _ => return None,
+    };
+    let body = recorded.get("body")?.as_str()?.to_string();
+    Some(Page {
+        url: Url::parse(recorded.get("url")?.as_str()?).ok()?,
+        status,
+        headers,
+        size: body.len(),
+        body,
+        evaluated: None,
+    })
+}
+
+/// the next recording of the url, the last one again once all were replayed;
+/// none if not replaying
+pub fn replay(url: &str) -> Option<Result<Page, String>> {
+    let mut replaying = REPLAYING.lock().unwrap();
+    let recorded = replaying.as_mut()?;
+    let requested = |r: &Json| r.get("request").and_then(Json::as_str) == Some(url);
+    let index = recorded
+        .iter()
+        .position(|(replayed, r)| !replayed && requested(r))
+        .or_else(|| recorded.iter().rposition(|(_, r)| requested(r)));
+    Some(match index {
+        Some(index) => {
+            recorded[index].0 = true;
+            page(&recorded[index].1).ok_or_else(|| format!("Broken recording of '{}'", url))
+        }
+        None => Err(format!("No recording of '{}' in the cassette", url)),
+    })
+}
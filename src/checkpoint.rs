@@ -0,0 +1,105 @@
+use reqwest::Url;
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{bloom::BloomFilter, log};
+
+/// urls a crawl has already seen, either exactly or approximately
+#[derive(Debug)]
+pub enum Visited {
+    Exact(HashSet<Url>),
+    Approximate(BloomFilter),
+}
+
+impl Default for Visited {
+    fn default() -> Self {
+        Visited::Exact(HashSet::new())
+    }
+}
+
+impl Visited {
+    /// returns `false` if the url was seen before
+    pub fn insert(&mut self, url: &Url) -> bool {
+        match self {
+            Visited::Exact(urls) => urls.insert(url.clone()),
+            Visited::Approximate(filter) => filter.insert(url.as_str()),
+        }
+    }
+}
+
+/// what a crawl still has to do and what it has already seen
+#[derive(Debug, Default)]
+pub struct CrawlState {
+    pub visited: Visited,
+    pub frontier: VecDeque<(Url, usize)>,
+}
+
+impl CrawlState {
+    /// reads a state file written by [`CrawlState::save`]
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut state = CrawlState::default();
+        for line in BufReader::new(fs::File::open(path)?).lines() {
+            let line = line?;
+            let mut fields = line.splitn(3, '\t');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some("visited"), Some(url), None) => {
+                    if let Ok(url) = Url::parse(url) {
+                        state.visited.insert(&url);
+                    }
+                }
+                (Some("queued"), Some(depth), Some(url)) => {
+                    if let (Ok(depth), Ok(url)) = (depth.parse(), Url::parse(url)) {
+                        state.frontier.push_back((url, depth));
+                    }
+                }
+                (Some("bloom-sha256"), Some(hashes), Some(bits)) => {
+                    let bits = bits
+                        .split(',')
+                        .map(|word| u64::from_str_radix(word, 16))
+                        .collect::<Result<_, _>>();
+                    if let (Ok(hashes), Ok(bits)) = (hashes.parse(), bits) {
+                        state.visited = Visited::Approximate(BloomFilter::from_parts(bits, hashes));
+                    }
+                }
+                (Some("bloom"), _, _) => log::warn(format!(
+                    "'{}' was saved by an older version, its visited urls will be visited again",
+                    path.display()
+                )),
+                _ => {}
+            }
+        }
+        Ok(state)
+    }
+
+    /// writes visited and queued urls, replacing `path` only once everything is written
+    pub fn save<'a>(
+        path: &Path,
+        visited: &Visited,
+        queued: impl Iterator<Item = (&'a Url, usize)>,
+    ) -> io::Result<()> {
+        let partial = path.with_extension("partial");
+        let mut out = BufWriter::new(fs::File::create(&partial)?);
+        writeln!(out, "# scrape crawl state")?;
+        match visited {
+            Visited::Exact(urls) => {
+                for url in urls {
+                    writeln!(out, "visited\t{}", url)?;
+                }
+            }
+            Visited::Approximate(filter) => {
+                let (bits, hashes) = filter.parts();
+                let bits: Vec<_> = bits.iter().map(|word| format!("{:x}", word)).collect();
+                writeln!(out, "bloom-sha256\t{}\t{}", hashes, bits.join(","))?;
+            }
+        }
+        for (url, depth) in queued {
+            writeln!(out, "queued\t{}\t{}", depth, url)?;
+        }
+        out.into_inner()?.sync_all()?;
+        fs::rename(partial, path)
+    }
+}
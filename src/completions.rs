@@ -0,0 +1,413 @@
+//! shell completion scripts generated from the clap definitions
+
+use clap::{ArgEnum, CommandFactory};
+
+use crate::{config, Args};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct CompletionsArgs {
+    /// the shell to complete in
+    #[clap(arg_enum, required_unless_present = "profiles")]
+    shell: Option<Shell>,
+
+    /// list the profiles in the config file, which the scripts run to complete --profile
+    #[clap(long, hide = true)]
+    profiles: bool,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+struct Flag {
+    short: Option<char>,
+    long: Option<String>,
+    help: String,
+    takes_value: bool,
+    /// can be given more than once
+    repeated: bool,
+    /// what the value can be, if clap knows
+    values: Vec<String>,
+    /// the arguments to scrape that list what the value can be when completing, for values that
+    /// change after the script is generated
+    lister: Option<&'static str>,
+}
+
+/// a command or subcommand with everything that can follow it
+struct Node {
+    /// the subcommand names leading to it, empty for scrape itself
+    path: Vec<String>,
+    about: String,
+    flags: Vec<Flag>,
+    subcommands: Vec<String>,
+    /// what the positional arguments can be, if clap knows
+    values: Vec<String>,
+}
+
+/// the `--help` and `--version` clap adds, with `-h` and `-V` unless something else has them
+fn help_flags(root: bool, taken: &[char]) -> Vec<Flag> {
+    let flag = |short, long: &str, help: &str| Flag {
+        short: Some(short).filter(|short| !taken.contains(short)),
+        long: Some(long.to_string()),
+        help: help.to_string(),
+        takes_value: false,
+        repeated: false,
+        values: vec![],
+        lister: None,
+    };
+    let mut flags = vec![flag('h', "help", "Print help information")];
+    if root {
+        flags.push(flag('V', "version", "Print version information"));
+    }
+    flags
+}
+
+fn possible_values(arg: &clap::Arg) -> Vec<String> {
+    arg.get_possible_values()
+        .unwrap_or_default()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn nodes(command: &clap::Command, path: Vec<String>, nodes: &mut Vec<Node>) {
+    let mut flags: Vec<_> = command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        // clap only settles on these when it builds the command
+        .filter(|arg| !matches!(arg.get_id(), "help" | "version"))
+        .map(|arg| Flag {
+            short: arg.get_short(),
+            long: arg.get_long().map(str::to_string),
+            help: arg.get_help().unwrap_or_default().to_string(),
+            takes_value: arg.is_takes_value_set(),
+            repeated: arg.is_multiple_occurrences_set(),
+            values: possible_values(arg),
+            // the config file may have other profiles by the time it's completed
+            lister: (arg.get_id() == "profile").then_some("completions --profiles"),
+        })
+        .collect();
+    let taken: Vec<_> = flags.iter().filter_map(|flag| flag.short).collect();
+    flags.extend(help_flags(path.is_empty(), &taken));
+    let subcommands: Vec<_> = command
+        .get_subcommands()
+        .filter(|c| !c.is_hide_set())
+        .collect();
+    nodes.push(Node {
+        path: path.clone(),
+        about: command.get_about().unwrap_or_default().to_string(),
+        flags,
+        subcommands: subcommands
+            .iter()
+            .map(|c| c.get_name().to_string())
+            .collect(),
+        values: command
+            .get_positionals()
+            .flat_map(possible_values)
+            .collect(),
+    });
+    for subcommand in subcommands {
+        let mut path = path.clone();
+        path.push(subcommand.get_name().to_string());
+        self::nodes(subcommand, path, nodes);
+    }
+}
+
+impl Flag {
+    fn names(&self) -> Vec<String> {
+        let short = self.short.map(|short| format!("-{}", short));
+        let long = self.long.as_ref().map(|long| format!("--{}", long));
+        short.into_iter().chain(long).collect()
+    }
+}
+
+/// single quoted for the shells that all quote like that
+fn quote(text: &str, escaped_quote: &str) -> String {
+    format!("'{}'", text.replace('\'', escaped_quote))
+}
+
+fn bash(name: &str, nodes: &[Node]) -> String {
+    let key = |path: &[String]| {
+        [name]
+            .into_iter()
+            .chain(path.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join("__")
+    };
+    let mut script = format!(
+        "_{name}() {{\n    local cur prev command word i\n    COMPREPLY=()\n    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n    command=\"{name}\"\n\n    for ((i = 1; i < COMP_CWORD; i++)); do\n        word=\"${{COMP_WORDS[i]}}\"\n        case \"${{command}},${{word}}\" in\n",
+        name = name
+    );
+    for node in nodes.iter().filter(|node| !node.path.is_empty()) {
+        let parent = key(&node.path[..node.path.len() - 1]);
+        let last = node.path.last().unwrap();
+        script += &format!(
+            "            {},{}) command=\"{}\" ;;\n",
+            parent,
+            last,
+            key(&node.path)
+        );
+    }
+    script += "        esac\n    done\n\n    case \"${command}\" in\n";
+    for node in nodes {
+        script += &format!(
+            "        {})\n            case \"${{prev}}\" in\n",
+            key(&node.path)
+        );
+        for flag in node.flags.iter().filter(|flag| flag.takes_value) {
+            let reply = match (flag.lister, flag.values.is_empty()) {
+                (Some(lister), _) => format!(
+                    "COMPREPLY=($(compgen -W \"$({} {} 2>/dev/null)\" -- \"${{cur}}\")); return 0",
+                    name, lister
+                ),
+                // nothing to offer, so bash falls back to file names
+                (None, true) => "return 0".to_string(),
+                (None, false) => format!(
+                    "COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\")); return 0",
+                    flag.values.join(" ")
+                ),
+            };
+            script += &format!("                {}) {} ;;\n", flag.names().join("|"), reply);
+        }
+        let words: Vec<_> = node
+            .flags
+            .iter()
+            .flat_map(Flag::names)
+            .chain(node.subcommands.iter().cloned())
+            .chain(node.values.iter().cloned())
+            .collect();
+        script += &format!(
+            "            esac\n            COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))\n            ;;\n",
+            words.join(" ")
+        );
+    }
+    script += &format!(
+        "    esac\n}}\n\ncomplete -F _{name} -o bashdefault -o default {name}\n",
+        name = name
+    );
+    script
+}
+
+fn zsh(name: &str, nodes: &[Node]) -> String {
+    let function = |path: &[String]| {
+        [name]
+            .into_iter()
+            .chain(path.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join("__")
+    };
+    let escape = |text: &str| {
+        text.replace('\\', "\\\\")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+            .replace(':', "\\:")
+            .replace('\'', "'\\''")
+    };
+    let mut script = format!("#compdef {}\n", name);
+    for node in nodes {
+        script += &format!(
+            "\n_{}() {{\n    local context state state_descr line\n    typeset -A opt_args\n    _arguments -C \\\n",
+            function(&node.path)
+        );
+        for flag in &node.flags {
+            let names = flag.names();
+            let value = match (flag.takes_value, flag.lister, flag.values.is_empty()) {
+                (false, _, _) => String::new(),
+                (true, Some(lister), _) => {
+                    format!(": :{{compadd -- $({} {} 2>/dev/null)}}", name, lister)
+                }
+                (true, None, true) => ": :_files".to_string(),
+                (true, None, false) => format!(": :({})", flag.values.join(" ")),
+            };
+            let spec = match names.as_slice() {
+                [one] if flag.repeated => format!("'*{}[{}]{}'", one, escape(&flag.help), value),
+                [one] => format!("'{}[{}]{}'", one, escape(&flag.help), value),
+                _ => format!(
+                    "'{}'{{{}}}'[{}]{}'",
+                    match flag.repeated {
+                        true => "*".to_string(),
+                        false => format!("({})", names.join(" ")),
+                    },
+                    names.join(","),
+                    escape(&flag.help),
+                    value
+                ),
+            };
+            script += &format!("        {} \\\n", spec);
+        }
+        if node.subcommands.is_empty() {
+            script += &match node.values.is_empty() {
+                true => "        '*: :_files'\n}\n".to_string(),
+                false => format!("        '1: :({})'\n}}\n", node.values.join(" ")),
+            };
+            continue;
+        }
+        script += &format!(
+            "        '1: :_{}_commands' \\\n        '*:: :->args'\n    case $state in\n        args)\n            case $line[1] in\n",
+            function(&node.path)
+        );
+        for subcommand in &node.subcommands {
+            let mut path = node.path.clone();
+            path.push(subcommand.clone());
+            script += &format!("                {}) _{} ;;\n", subcommand, function(&path));
+        }
+        script += "            esac\n            ;;\n    esac\n}\n";
+
+        script += &format!(
+            "\n_{}_commands() {{\n    local commands; commands=(\n",
+            function(&node.path)
+        );
+        for subcommand in &node.subcommands {
+            let about = nodes
+                .iter()
+                .find(|n| n.path.len() == node.path.len() + 1 && n.path.last() == Some(subcommand))
+                .map(|n| n.about.as_str())
+                .unwrap_or_default();
+            script += &format!(
+                "        {}\n",
+                quote(&format!("{}:{}", subcommand, about), "'\\''")
+            );
+        }
+        script += &format!(
+            "    )\n    _describe -t commands '{} commands' commands \"$@\"\n}}\n",
+            name
+        );
+    }
+    script += &format!("\n_{} \"$@\"\n", name);
+    script
+}
+
+fn fish(name: &str, nodes: &[Node]) -> String {
+    let mut script = String::new();
+    for node in nodes {
+        let condition = match node.path.is_empty() {
+            true => "__fish_use_subcommand".to_string(),
+            false => node
+                .path
+                .iter()
+                .map(|word| format!("__fish_seen_subcommand_from {}", word))
+                .collect::<Vec<_>>()
+                .join("; and "),
+        };
+        for subcommand in &node.subcommands {
+            let about = nodes
+                .iter()
+                .find(|n| n.path.len() == node.path.len() + 1 && n.path.last() == Some(subcommand))
+                .map(|n| n.about.as_str())
+                .unwrap_or_default();
+            script += &format!(
+                "complete -c {} -n \"{}\" -f -a {} -d {}\n",
+                name,
+                condition,
+                subcommand,
+                quote(about, "\\'")
+            );
+        }
+        if !node.values.is_empty() {
+            script += &format!(
+                "complete -c {} -n \"{}\" -f -a \"{}\"\n",
+                name,
+                condition,
+                node.values.join(" ")
+            );
+        }
+        for flag in &node.flags {
+            let mut line = format!("complete -c {} -n \"{}\"", name, condition);
+            if let Some(short) = flag.short {
+                line += &format!(" -s {}", short);
+            }
+            if let Some(long) = &flag.long {
+                line += &format!(" -l {}", long);
+            }
+            line += &format!(" -d {}", quote(&flag.help, "\\'"));
+            if flag.takes_value {
+                line += " -r";
+            }
+            if let Some(lister) = flag.lister {
+                line += &format!(" -f -a \"({} {} 2>/dev/null)\"", name, lister);
+            } else if !flag.values.is_empty() {
+                line += &format!(" -f -a \"{}\"", flag.values.join(" "));
+            }
+            script += &line;
+            script.push('\n');
+        }
+    }
+    script
+}
+
+fn powershell(name: &str, nodes: &[Node]) -> String {
+    let key = |path: &[String]| {
+        [name]
+            .into_iter()
+            .chain(path.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(";")
+    };
+    let result = |text: &str, kind: &str, help: &str| {
+        format!(
+            "            [CompletionResult]::new({}, {}, [CompletionResultType]::{}, {})\n",
+            quote(text, "''"),
+            quote(text, "''"),
+            kind,
+            quote(if help.is_empty() { text } else { help }, "''")
+        )
+    };
+    let mut script = format!(
+        "using namespace System.Management.Automation\nusing namespace System.Management.Automation.Language\n\n\
+Register-ArgumentCompleter -Native -CommandName '{name}' -ScriptBlock {{\n    param($wordToComplete, $commandAst, $cursorPosition)\n\n    $commands = @{{\n",
+        name = name
+    );
+    for node in nodes {
+        script += &format!("        {} = $true\n", quote(&key(&node.path), "''"));
+    }
+    script += &format!(
+        "    }}\n    $command = '{}'\n    foreach ($element in $commandAst.CommandElements | Select-Object -Skip 1) {{\n        \
+if ($element -isnot [StringConstantExpressionAst] -or $element.Value -eq $wordToComplete) {{ continue }}\n        \
+$next = $command + ';' + $element.Value\n        if ($commands.ContainsKey($next)) {{ $command = $next }}\n    }}\n\n    \
+$completions = @(switch ($command) {{\n",
+        name
+    );
+    for node in nodes {
+        script += &format!("        {} {{\n", quote(&key(&node.path), "''"));
+        for flag in &node.flags {
+            for name in flag.names() {
+                script += &result(&name, "ParameterName", &flag.help);
+            }
+        }
+        for value in node.subcommands.iter().chain(&node.values) {
+            script += &result(value, "ParameterValue", value);
+        }
+        script += "            break\n        }\n";
+    }
+    script += "    })\n\n    $completions.Where{ $_.CompletionText -like \"$wordToComplete*\" } |\n        Sort-Object -Property ListItemText\n}\n";
+    script
+}
+
+pub fn run(args: CompletionsArgs) {
+    let shell = match (args.profiles, args.shell) {
+        (false, Some(shell)) => shell,
+        // clap asks for a shell unless it's --profiles
+        _ => {
+            for profile in config::profiles() {
+                println!("{}", profile);
+            }
+            return;
+        }
+    };
+    let command = Args::command();
+    let name = command.get_name().to_string();
+    let mut all = Vec::new();
+    nodes(&command, vec![], &mut all);
+    let script = match shell {
+        Shell::Bash => bash(&name, &all),
+        Shell::Zsh => zsh(&name, &all),
+        Shell::Fish => fish(&name, &all),
+        Shell::Powershell => powershell(&name, &all),
+    };
+    print!("{}", script);
+}
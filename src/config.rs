@@ -0,0 +1,239 @@
+//! `~/.config/scrape/config.toml`, with the settings every run uses and named profiles
+//! overriding them:
+//!
+//! ```toml
+//! user_agent = "scrape"
+//! timeout = "30s"
+//!
+//! [headers]
+//! Accept-Language = "en"
+//!
+//! [profile.work-proxy]
+//! proxy = "http://proxy.internal:3128"
+//! connect_timeout = "5s"
+//! ```
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    ClientBuilder, Proxy,
+};
+use std::{fs, path::PathBuf, time::Duration};
+
+use crate::{curl::Curl, dirs, json::Json, parse_duration, toml};
+
+#[derive(Debug, Default)]
+pub struct Profile {
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+}
+
+fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("config.toml"))
+}
+
+fn read() -> Result<Option<Json>, String> {
+    let path = match path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    toml::parse(&text)
+        .map(Some)
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// the names of the profiles in the config file
+pub fn profiles() -> Vec<String> {
+    match read() {
+        Ok(Some(config)) => match config.get("profile") {
+            Some(Json::Object(profiles)) => profiles.iter().map(|(name, _)| name.clone()).collect(),
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// the settings of the config file with those of the profile on top
+pub fn load(profile: Option<&str>) -> Result<Profile, String> {
+    let config = match (read()?, profile) {
+        (Some(config), _) => config,
+        (None, None) => return Ok(Profile::default()),
+        (None, Some(name)) => {
+            return Err(format!("No profile '{}', there is no config file", name))
+        }
+    };
+    let mut settings = Profile::default();
+    settings
+        .update(&config)
+        .map_err(|e| format!("config: {}", e))?;
+    if let Some(name) = profile {
+        let profile = config
+            .get("profile")
+            .and_then(|profiles| profiles.get(name))
+            .ok_or_else(|| format!("No profile '{}' in the config file", name))?;
+        settings
+            .update(profile)
+            .map_err(|e| format!("profile '{}': {}", name, e))?;
+    }
+    Ok(settings)
+}
+
+/// `30s` style strings or seconds
+fn duration(value: &Json) -> Result<Duration, String> {
+    match value {
+        Json::String(text) => parse_duration(text),
+        Json::Number(seconds) if *seconds >= 0.0 => Ok(Duration::from_secs_f64(*seconds)),
+        _ => Err(format!("invalid duration {}", value)),
+    }
+}
+
+fn string(value: &Json, key: &str) -> Result<String, String> {
+    value
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| format!("'{}' needs to be a string", key))
+}
+
+impl Profile {
+    /// takes over what the table sets, headers are added to the ones there are
+    fn update(&mut self, table: &Json) -> Result<(), String> {
+        if let Some(proxy) = table.get("proxy") {
+            self.proxy = Some(string(proxy, "proxy")?);
+        }
+        if let Some(user_agent) = table.get("user_agent") {
+            self.user_agent = Some(string(user_agent, "user_agent")?);
+        }
+        if let Some(timeout) = table.get("timeout") {
+            self.timeout = Some(duration(timeout)?);
+        }
+        if let Some(timeout) = table.get("connect_timeout") {
+            self.connect_timeout = Some(duration(timeout)?);
+        }
+        match table.get("headers") {
+            Some(Json::Object(headers)) => {
+                for (name, value) in headers {
+                    let value = string(value, name)?;
+                    self.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+                    self.headers.push((name.clone(), value));
+                }
+            }
+            Some(_) => return Err("'headers' needs to be a table".to_string()),
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// with what was given on the command line in place of what the config file says
+    pub fn with_flags(
+        self,
+        proxy: Option<String>,
+        user_agent: Option<String>,
+        timeout: Option<Duration>,
+    ) -> Profile {
+        Profile {
+            proxy: proxy.or(self.proxy),
+            user_agent: user_agent.or(self.user_agent),
+            timeout: timeout.or(self.timeout),
+            ..self
+        }
+    }
+
+    /// the proxy, user agent, headers and timeouts as options of the curl command for a request,
+    /// which leaves out what the client adds by default
+    pub fn add_to(&self, curl: &mut Curl) {
+        if let Some(proxy) = &self.proxy {
+            curl.proxy = Some(proxy.clone());
+        }
+        let user_agent = self.user_agent.iter().map(|agent| ("User-Agent", agent));
+        let headers = self.headers.iter().map(|(n, v)| (n.as_str(), v));
+        for (name, value) in user_agent.chain(headers) {
+            if !curl.has_header(name) {
+                curl.headers.push((name.to_string(), value.clone()));
+            }
+        }
+        curl.timeout = curl.timeout.or(self.timeout);
+        curl.connect_timeout = curl.connect_timeout.or(self.connect_timeout);
+    }
+
+    /// the builder with the proxy, user agent, headers and timeouts set
+    pub fn apply(&self, mut builder: ClientBuilder) -> Result<ClientBuilder, String> {
+        if let Some(proxy) = &self.proxy {
+            let proxy = Proxy::all(proxy).map_err(|_| format!("Invalid proxy '{}'", proxy))?;
+            builder = builder.proxy(proxy);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| format!("Invalid header name '{}'", name))?,
+                HeaderValue::from_str(value)
+                    .map_err(|_| format!("Invalid value for header '{}'", name))?,
+            );
+        }
+        builder = builder.default_headers(headers);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Client;
+
+    #[test]
+    fn as_curl_round_trips() {
+        let config = toml::parse(
+            "user_agent = \"from-config\"\nconnect_timeout = \"2s\"\n\
+             [headers]\nAccept-Language = \"en\"\nX-Token = \"it's\"\n",
+        )
+        .unwrap();
+        let mut profile = Profile::default();
+        profile.update(&config).unwrap();
+        let profile = profile.with_flags(
+            Some("http://proxy:3128".to_string()),
+            Some("scrape-test".to_string()),
+            Some(Duration::from_secs(30)),
+        );
+        let request = Client::new()
+            .get("https://example.com/a?b=c")
+            .build()
+            .unwrap();
+        let mut curl = Curl::from(&request);
+        profile.add_to(&mut curl);
+        let command = curl.to_string();
+        assert_eq!(
+            command,
+            "curl 'https://example.com/a?b=c' -A scrape-test -H 'Accept-Language: en' \
+             -H 'X-Token: it'\\''s' -x http://proxy:3128 -m 30 --connect-timeout 2"
+        );
+
+        let parsed: Curl = command.parse().unwrap();
+        assert_eq!(parsed.url, "https://example.com/a?b=c");
+        assert_eq!(parsed.method(), "GET");
+        assert_eq!(
+            parsed.headers,
+            [
+                ("User-Agent".to_string(), "scrape-test".to_string()),
+                ("Accept-Language".to_string(), "en".to_string()),
+                ("X-Token".to_string(), "it's".to_string()),
+            ]
+        );
+        assert_eq!(parsed.proxy.as_deref(), Some("http://proxy:3128"));
+        assert_eq!(parsed.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(parsed.connect_timeout, Some(Duration::from_secs(2)));
+        assert_eq!(parsed.to_string(), command);
+    }
+}
@@ -0,0 +1,391 @@
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use regex::Regex;
+use reqwest::{header::LAST_MODIFIED, Client, Url};
+use scraper::{Html, Selector};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    archive,
+    bloom::BloomFilter,
+    checkpoint::{CrawlState, Visited},
+    datetime, download, extract, interrupt,
+    json::Json,
+    language,
+    link_graph::LinkGraph,
+    log, nats, page_fields, parse_duration, parse_selector,
+    robots::{self, RobotsCache},
+    s3, sha256, sitemap,
+    throttle::Throttle,
+    Error, Format,
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct CrawlArgs {
+    /// where to start crawling
+    url: String,
+
+    /// select html from every visited page
+    selector: Option<String>,
+
+    #[clap(short, long)]
+    attribute: Option<String>,
+
+    /// `jsonl` prints one json object per page as soon as it is done
+    #[clap(short, long, arg_enum, default_value = "text")]
+    format: Format,
+
+    /// start from every page listed in the sitemap at the url instead
+    #[clap(long)]
+    from_sitemap: bool,
+
+    /// only start from sitemap entries modified on or after this date, e.g. `2022-01-31`
+    #[clap(long, parse(try_from_str = datetime::parse_w3c))]
+    modified_since: Option<i64>,
+
+    /// how many links away from the start page to follow
+    #[clap(short, long, default_value = "3")]
+    depth: usize,
+
+    /// only follow links to the host of the start page
+    #[clap(long)]
+    same_domain: bool,
+
+    /// only follow links matching this pattern
+    #[clap(long, parse(try_from_str = Regex::new))]
+    include_url: Vec<Regex>,
+
+    /// never follow links matching this pattern
+    #[clap(long, parse(try_from_str = Regex::new))]
+    exclude_url: Vec<Regex>,
+
+    /// how many pages to download at the same time
+    #[clap(short, long, default_value = "4")]
+    concurrency: usize,
+
+    /// how many pages to download from the same host at the same time
+    #[clap(long)]
+    per_host: Option<usize>,
+
+    /// pause between two requests to the same host, e.g. `500ms`
+    #[clap(long, parse(try_from_str = parse_duration))]
+    delay: Option<Duration>,
+
+    /// crawl pages even if robots.txt disallows it
+    #[clap(long)]
+    ignore_robots: bool,
+
+    /// skip `rel=nofollow` links and links on pages with a `nofollow` robots meta tag
+    #[clap(long)]
+    respect_nofollow: bool,
+
+    /// don't extract from pages with a `noindex` robots meta tag
+    #[clap(long)]
+    respect_noindex: bool,
+
+    /// continue the crawl saved in this file and keep saving progress to it
+    #[clap(long)]
+    resume: Option<PathBuf>,
+
+    /// how many pages to crawl between saves of the resume file
+    #[clap(long, default_value = "100")]
+    checkpoint_every: usize,
+
+    /// remember visited urls in a bloom filter sized for this many urls, may skip some pages
+    #[clap(long)]
+    approximate_visited: Option<usize>,
+
+    /// write the links between crawled pages to a `.dot` or `.graphml` file
+    #[clap(long)]
+    link_graph: Option<PathBuf>,
+
+    /// write a sitemap of all successfully crawled pages
+    #[clap(long)]
+    emit_sitemap: Option<PathBuf>,
+
+    /// include the detected language of every page (or of its matches)
+    #[clap(long)]
+    detect_language: bool,
+
+    /// chance of wrongly treating a new url as visited with --approximate-visited
+    #[clap(long, default_value = "0.001")]
+    false_positive_rate: f64,
+
+    /// skip pages whose body, or the matches of the selector, are the same as an earlier page's
+    #[clap(long, arg_enum, value_name = "CONTENT")]
+    dedup: Option<Dedup>,
+
+    /// upload crawled pages, results and a manifest to `s3://bucket/prefix/`
+    #[clap(long, value_name = "S3_URL")]
+    output: Option<s3::Location>,
+}
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Dedup {
+    Body,
+    Matches,
+}
+
+impl CrawlArgs {
+    fn should_follow(&self, start: &Url, link: &Url) -> bool {
+        if self.same_domain && link.host_str() != start.host_str() {
+            return false;
+        }
+        let link = link.as_str();
+        (self.include_url.is_empty() || self.include_url.iter().any(|r| r.is_match(link)))
+            && !self.exclude_url.iter().any(|r| r.is_match(link))
+    }
+}
+
+/// directives of `<meta name=robots>`, lowercased
+pub fn meta_robots(document: &Html) -> Vec<String> {
+    let meta = Selector::parse("meta[name][content]").unwrap();
+    document
+        .select(&meta)
+        .filter(|m| {
+            let name = m.value().attr("name").unwrap_or_default();
+            name.eq_ignore_ascii_case("robots") || name.eq_ignore_ascii_case(robots::AGENT)
+        })
+        .flat_map(|m| m.value().attr("content").unwrap_or_default().split(','))
+        .map(|directive| directive.trim().to_ascii_lowercase())
+        .flat_map(|directive| match directive.as_str() {
+            "none" => vec!["noindex".to_string(), "nofollow".to_string()],
+            _ => vec![directive],
+        })
+        .collect()
+}
+
+pub fn is_nofollow(rel: Option<&str>) -> bool {
+    rel.unwrap_or_default()
+        .split_ascii_whitespace()
+        .any(|r| r.eq_ignore_ascii_case("nofollow"))
+}
+
+/// absolute http(s) link without its fragment
+pub fn resolve(base: &Url, href: &str) -> Option<Url> {
+    let mut url = base.join(href).ok()?;
+    url.set_fragment(None);
+    matches!(url.scheme(), "http" | "https").then(|| url)
+}
+
+pub async fn crawl(client: &Client, args: Box<CrawlArgs>) -> Result<(), Error> {
+    let start = Url::parse(&args.url).map_err(|_| Error::InvalidUrl(args.url.clone()))?;
+    let selector = args.selector.as_deref().map(parse_selector).transpose()?;
+    if args.dedup == Some(Dedup::Matches) && selector.is_none() {
+        return Err("--dedup matches needs a selector".into());
+    }
+    let anchors = Selector::parse("a[href]").unwrap();
+
+    if args.output.is_some() {
+        archive::collect_results();
+    }
+    let throttle = Throttle::new(args.per_host, args.delay);
+    let robots = (!args.ignore_robots).then(RobotsCache::default);
+
+    let mut state = match &args.resume {
+        Some(path) if path.exists() => CrawlState::load(path)?,
+        _ => CrawlState::default(),
+    };
+    if let (Some(urls), Visited::Exact(seen)) = (args.approximate_visited, &state.visited) {
+        let mut filter = BloomFilter::new(urls, args.false_positive_rate);
+        for url in seen {
+            filter.insert(url.as_str());
+        }
+        state.visited = Visited::Approximate(filter);
+    }
+    let seeds = if args.from_sitemap {
+        let since = args.modified_since;
+        sitemap::urls(client, &start, since).await?
+    } else {
+        vec![start.clone()]
+    };
+    for seed in seeds {
+        if state.visited.insert(&seed) {
+            state.frontier.push_back((seed, 0));
+        }
+    }
+    let CrawlState {
+        mut visited,
+        mut frontier,
+    } = state;
+
+    let mut graph = args.link_graph.as_ref().map(|_| LinkGraph::default());
+    let mut crawled_pages = Vec::new();
+    let mut in_flight = FuturesUnordered::new();
+    let mut pending = HashMap::new();
+    // the first page with each content digest, for --dedup
+    let mut contents = HashMap::new();
+    let mut crawled = 0;
+
+    loop {
+        if let Some(path) = &args.resume {
+            if crawled % args.checkpoint_every.max(1) == 0 {
+                let queued = pending.iter().map(|(url, depth)| (url, *depth));
+                let queued = queued.chain(frontier.iter().map(|(url, depth)| (url, *depth)));
+                CrawlState::save(path, &visited, queued)?;
+            }
+        }
+
+        while in_flight.len() < args.concurrency.max(1) && !interrupt::interrupted() {
+            let (url, depth) = match frontier.pop_front() {
+                Some(next) => next,
+                None => break,
+            };
+            pending.insert(url.clone(), depth);
+            let throttle = &throttle;
+            let robots = robots.as_ref();
+            in_flight.push(async move {
+                if let Some(robots) = robots {
+                    let robots = match robots.get(client, &url).await {
+                        Ok(robots) => robots,
+                        Err(error) => {
+                            let skipped = format!(
+                                "Skipping '{}', failed to fetch robots.txt: {}",
+                                url, error
+                            );
+                            return (url, depth, Err(skipped.into()));
+                        }
+                    };
+                    if !robots.is_allowed(robots::AGENT, &robots::robots_path(&url)) {
+                        let skipped = format!("Skipping '{}', disallowed by robots.txt", url);
+                        return (url, depth, Err(skipped.into()));
+                    }
+                    if let Some(delay) = robots.crawl_delay(robots::AGENT) {
+                        throttle.slow_down(&url, delay);
+                    }
+                }
+                let _permit = throttle.acquire(&url).await;
+                let started = Instant::now();
+                let page = download(client, url.as_str()).await;
+                (url, depth, page.map(|page| (page, started.elapsed())))
+            });
+        }
+
+        let (url, depth, page) = match in_flight.next().await {
+            Some(done) => done,
+            None => break,
+        };
+        pending.remove(&url);
+        crawled += 1;
+        let (page, elapsed) = match page {
+            Ok(page) => page,
+            Err(error) => {
+                log::error(error);
+                continue;
+            }
+        };
+        let document = Html::parse_document(&page.body);
+        let directives = meta_robots(&document);
+        let noindex = args.respect_noindex && directives.iter().any(|d| d == "noindex");
+        let nofollow = args.respect_nofollow && directives.iter().any(|d| d == "nofollow");
+        let matches = selector
+            .as_ref()
+            .filter(|_| !noindex)
+            .map(|selector| extract(&document, selector, args.attribute.as_deref()));
+
+        if let Some(dedup) = args.dedup {
+            let digest = match (dedup, &matches) {
+                // pages without matches, like lists of links, are only duplicates by their body
+                (Dedup::Matches, Some(matches)) if !matches.is_empty() => {
+                    sha256::digest(matches.join("\n").as_bytes())
+                }
+                _ => sha256::digest(page.body.as_bytes()),
+            };
+            if let Some(original) = contents.get(&digest) {
+                log::info(format!(
+                    "Skipping '{}', same content as '{}'",
+                    url, original
+                ));
+                continue;
+            }
+            contents.insert(digest, url.clone());
+        }
+
+        if args.emit_sitemap.is_some() && page.status.is_success() && !noindex {
+            let lastmod = page.headers.get(LAST_MODIFIED);
+            let lastmod = lastmod.and_then(|date| datetime::rfc2822(date.to_str().ok()?));
+            crawled_pages.push((page.url.clone(), lastmod));
+        }
+        if args.output.is_some() {
+            archive::add_page(&page);
+        }
+
+        if !noindex {
+            let language = args.detect_language.then(|| match &matches {
+                Some(matches) => language::detect_html(&matches.join(" ")),
+                None => language::detect(&language::visible_text(&document)).unwrap_or("und"),
+            });
+            // the language goes right after the url in text output
+            let url_and_language = match language {
+                Some(language) => format!("{}\t{}", url, language),
+                None => url.to_string(),
+            };
+            let lines = match (args.format, matches) {
+                (Format::Jsonl, matches) => {
+                    let mut record = page_fields(url.as_str(), &page, elapsed);
+                    record.push(("depth", depth.into()));
+                    if let Some(language) = language {
+                        record.push(("language", language.into()));
+                    }
+                    record.push(("matches", matches.into()));
+                    vec![Json::object(record).to_string()]
+                }
+                (Format::Text, Some(matches)) => matches
+                    .into_iter()
+                    .map(|value| format!("{}\t{}", url_and_language, value))
+                    .collect(),
+                (Format::Text, None) => vec![url_and_language],
+            };
+            for line in lines {
+                archive::add_result(&line);
+                nats::publish(&line);
+                println!("{}", line);
+            }
+        }
+
+        let links: Vec<_> = document
+            .select(&anchors)
+            .filter(|a| !args.respect_nofollow || !is_nofollow(a.value().attr("rel")))
+            .filter_map(|a| a.value().attr("href"))
+            .filter_map(|href| resolve(&page.url, href))
+            .collect();
+
+        if let Some(graph) = &mut graph {
+            for link in &links {
+                graph.add_link(&url, link);
+            }
+        }
+
+        if depth >= args.depth || nofollow {
+            continue;
+        }
+
+        for link in links {
+            if args.should_follow(&start, &link) && visited.insert(&link) {
+                frontier.push_back((link, depth + 1));
+            }
+        }
+    }
+
+    if let Some(path) = &args.resume {
+        // the frontier is only left over when the crawl was interrupted
+        let queued = frontier.iter().map(|(url, depth)| (url, *depth));
+        CrawlState::save(path, &visited, queued)?;
+    }
+    if let (Some(path), Some(graph)) = (&args.link_graph, &graph) {
+        graph.write(path)?;
+    }
+    if let Some(path) = &args.emit_sitemap {
+        crawled_pages.sort();
+        crawled_pages.dedup_by(|a, b| a.0 == b.0);
+        sitemap::write(path, &crawled_pages)?;
+    }
+    if let Some(location) = &args.output {
+        archive::upload(client, location).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::datetime;
+
+/// a five field cron expression like `*/15 9-17 * * mon-fri`, in UTC
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// cron matches either field if both day of month and weekday are restricted
+    any_day: bool,
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// the values a field allows, as bits
+fn field(input: &str, min: u64, max: u64, names: &[&str]) -> Result<u64, String> {
+    let value = |part: &str| -> Result<u64, String> {
+        let lower = part.to_ascii_lowercase();
+        let value = match names.iter().position(|name| *name == lower) {
+            Some(index) => index as u64 + min,
+            None => part
+                .parse()
+                .map_err(|_| format!("Invalid cron field '{}'", input))?,
+        };
+        if value < min || value > max {
+            return Err(format!(
+                "'{}' is out of range in cron field '{}'",
+                part, input
+            ));
+        }
+        Ok(value)
+    };
+    let mut bits = 0;
+    for part in input.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().ok().filter(|step| *step > 0)),
+            None => (part, Some(1)),
+        };
+        let step = step.ok_or_else(|| format!("Invalid step in cron field '{}'", input))?;
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (value(from)?, value(to)?),
+            None if step > 1 => (value(range)?, max),
+            None => (value(range)?, value(range)?),
+        };
+        for value in (from..=to).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let expression = match input.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            expression => expression,
+        };
+        let fields: Vec<_> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Cron expression '{}' needs 5 fields", input));
+        }
+        Ok(Schedule {
+            minutes: field(fields[0], 0, 59, &[])?,
+            hours: field(fields[1], 0, 23, &[])?,
+            days: field(fields[2], 1, 31, &[])?,
+            months: field(fields[3], 1, 12, &MONTHS)?,
+            // 7 is sunday too
+            weekdays: match field(fields[4], 0, 7, &WEEKDAYS)? {
+                bits if bits & 1 << 7 != 0 => bits & !(1 << 7) | 1,
+                bits => bits,
+            },
+            any_day: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+        })
+    }
+}
+
+impl Schedule {
+    fn matches(&self, minute: u64) -> bool {
+        let days = minute / (24 * 60);
+        let (_, month, day) = datetime::civil_from_days(days as i64);
+        // 1970-01-01 was a thursday
+        let weekday = (days + 4) % 7;
+        let has = |bits: u64, value: u64| bits & (1 << value) != 0;
+        let day_matches = if self.any_day {
+            has(self.days, day.into()) || has(self.weekdays, weekday)
+        } else {
+            has(self.days, day.into()) && has(self.weekdays, weekday)
+        };
+        has(self.minutes, minute % 60)
+            && has(self.hours, minute / 60 % 24)
+            && has(self.months, month.into())
+            && day_matches
+    }
+
+    /// the first full minute after `time` the schedule fires at
+    pub fn next_after(&self, time: SystemTime) -> Option<SystemTime> {
+        let since = time.duration_since(UNIX_EPOCH).ok()?;
+        let start = since.as_secs() / 60 + 1;
+        // every schedule fires within a few years, even `0 0 29 2 *`
+        (start..start + 8 * 366 * 24 * 60)
+            .find(|minute| self.matches(*minute))
+            .map(|minute| UNIX_EPOCH + Duration::from_secs(minute * 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// thursday, 2022-04-07T12:34:56Z
+    const NOW: u64 = 1_649_334_896;
+
+    fn next(expression: &str) -> String {
+        let schedule: Schedule = expression.parse().unwrap();
+        let now = UNIX_EPOCH + Duration::from_secs(NOW);
+        datetime::rfc3339(schedule.next_after(now).unwrap())
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(next("* * * * *"), "2022-04-07T12:35:00Z");
+        assert_eq!(next("0 0 * * *"), "2022-04-08T00:00:00Z");
+        assert_eq!(next("@hourly"), "2022-04-07T13:00:00Z");
+        assert_eq!(next("0 0 1 jan *"), "2023-01-01T00:00:00Z");
+        assert_eq!(next("0 0 29 2 *"), "2024-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn ranges_steps_and_lists() {
+        assert_eq!(next("*/15 9-17 * * mon-fri"), "2022-04-07T12:45:00Z");
+        assert_eq!(next("10/20 * * * *"), "2022-04-07T12:50:00Z");
+        assert_eq!(next("0-30/10 * * * *"), "2022-04-07T13:00:00Z");
+        assert_eq!(next("5,10 * * * *"), "2022-04-07T13:05:00Z");
+        assert_eq!(next("0 9 * * sat,sun"), "2022-04-09T09:00:00Z");
+        assert_eq!(next("0 18-20/2 * * *"), "2022-04-07T18:00:00Z");
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(next("0 0 * * 7"), "2022-04-10T00:00:00Z");
+        assert_eq!(next("0 0 * * 0"), "2022-04-10T00:00:00Z");
+        // today's run has already passed
+        assert_eq!(next("0 12 * * thu"), "2022-04-14T12:00:00Z");
+    }
+
+    #[test]
+    fn day_of_month_or_weekday() {
+        assert_eq!(next("30 8 1 * *"), "2022-05-01T08:30:00Z");
+        assert_eq!(next("30 8 * * 1"), "2022-04-11T08:30:00Z");
+        // both restricted: either one fires
+        assert_eq!(next("30 8 1 * 1"), "2022-04-11T08:30:00Z");
+        assert_eq!(next("30 8 8 * 1"), "2022-04-08T08:30:00Z");
+    }
+
+    #[test]
+    fn strictly_after() {
+        let schedule: Schedule = "* * * * *".parse().unwrap();
+        let minute = UNIX_EPOCH + Duration::from_secs(NOW - 56 + 60);
+        let next = schedule.next_after(minute).unwrap();
+        assert_eq!(datetime::rfc3339(next), "2022-04-07T12:36:00Z");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |expression: &str| expression.parse::<Schedule>().unwrap_err();
+        assert_eq!(error("* * * *"), "Cron expression '* * * *' needs 5 fields");
+        assert_eq!(
+            error("60 * * * *"),
+            "'60' is out of range in cron field '60'"
+        );
+        assert_eq!(
+            error("0 0 32 * *"),
+            "'32' is out of range in cron field '32'"
+        );
+        assert_eq!(error("*/0 * * * *"), "Invalid step in cron field '*/0'");
+        assert_eq!(error("x * * * *"), "Invalid cron field 'x'");
+        assert_eq!(error("0 0 * foo *"), "Invalid cron field 'foo'");
+    }
+}
@@ -0,0 +1,585 @@
+//! curl command lines, as copied from browser devtools, turned into requests
+
+use reqwest::{Client, Method, Proxy, Request, RequestBuilder};
+use scraper::Html;
+use std::{env, fmt, str::FromStr, time::Duration};
+
+use crate::{extract, log, parse_selector, print_values, split_attribute, Error};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct FromCurlArgs {
+    /// the curl command, like `curl 'https://example.com' -H 'accept: text/html'`
+    command: Curl,
+
+    /// select html from the response, `selector@attribute` for an attribute
+    selector: Option<String>,
+
+    #[clap(short, long)]
+    attribute: Option<String>,
+}
+
+/// the parts of a curl invocation scrape knows how to send
+#[derive(Clone, Debug, Default)]
+pub struct Curl {
+    pub method: Option<String>,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// `-d` values, joined with `&` when sent
+    pub data: Vec<String>,
+    /// `user:password` for basic auth
+    pub user: Option<String>,
+    pub proxy: Option<String>,
+    pub insecure: bool,
+    /// `-G`, sends the data in the query string
+    pub get: bool,
+    /// `-I`, only asks for the headers
+    pub head: bool,
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+}
+
+/// options that change nothing about the request scrape sends
+const IGNORED: &[&str] = &[
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-L",
+    "--location",
+    "--compressed",
+    "-i",
+    "--include",
+    "-v",
+    "--verbose",
+    "-f",
+    "--fail",
+    "--http1.1",
+    "--http2",
+];
+
+/// splits like a POSIX shell, with `$'...'` strings as bash has them
+pub fn split(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err("trailing backslash".to_string()),
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated '".to_string()),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => word.push('\n'),
+                            Some('t') => word.push('\t'),
+                            Some('r') => word.push('\r'),
+                            Some(kind @ ('u' | 'x')) => {
+                                let digits = if kind == 'u' { 4 } else { 2 };
+                                let mut hex = String::new();
+                                while hex.len() < digits
+                                    && chars.peek().is_some_and(char::is_ascii_hexdigit)
+                                {
+                                    hex.extend(chars.next());
+                                }
+                                let code = u32::from_str_radix(&hex, 16).ok();
+                                word.extend(code.and_then(char::from_u32));
+                            }
+                            Some(c) => word.push(c),
+                            None => return Err("unterminated $'".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated $'".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated \"".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated \"".to_string()),
+                    }
+                }
+            }
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// `application/x-www-form-urlencoded` escaping for --data-urlencode
+fn urlencode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'*' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            byte => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+impl FromStr for Curl {
+    type Err = String;
+
+    fn from_str(command: &str) -> Result<Self, Self::Err> {
+        let words = split(command)?;
+        let mut words = words.into_iter();
+        match words.next() {
+            Some(curl) if curl == "curl" || curl.ends_with("/curl") || curl == "curl.exe" => {}
+            _ => return Err("not a curl command".to_string()),
+        }
+        let mut curl = Curl::default();
+        while let Some(word) = words.next() {
+            // `--header=value` and `-Hvalue` forms
+            let (option, inline) = match word.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => {
+                    (option.to_string(), Some(value.to_string()))
+                }
+                _ if word.len() > 2 && word.starts_with('-') && !word.starts_with("--") => {
+                    (word[..2].to_string(), Some(word[2..].to_string()))
+                }
+                _ => (word.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| words.next())
+                    .ok_or_else(|| format!("{} needs a value", option))
+            };
+            match option.as_str() {
+                "-X" | "--request" => curl.method = Some(value()?.to_uppercase()),
+                "-H" | "--header" => {
+                    let header = value()?;
+                    let (name, value) = header
+                        .split_once(':')
+                        .ok_or_else(|| format!("Invalid header '{}'", header))?;
+                    curl.headers
+                        .push((name.trim().to_string(), value.trim().to_string()));
+                }
+                "-d" | "--data" | "--data-raw" | "--data-binary" | "--data-ascii" => {
+                    curl.data.push(value()?)
+                }
+                "--data-urlencode" => {
+                    let data = value()?;
+                    curl.data.push(match data.split_once('=') {
+                        Some((name, value)) => format!("{}={}", name, urlencode(value)),
+                        None => urlencode(&data),
+                    });
+                }
+                "-b" | "--cookie" => curl.headers.push(("Cookie".to_string(), value()?)),
+                "-A" | "--user-agent" => curl.headers.push(("User-Agent".to_string(), value()?)),
+                "-e" | "--referer" => curl.headers.push(("Referer".to_string(), value()?)),
+                "-u" | "--user" => curl.user = Some(value()?),
+                "-x" | "--proxy" => curl.proxy = Some(value()?),
+                "-m" | "--max-time" => {
+                    let seconds = value()?;
+                    let seconds: f64 = seconds
+                        .parse()
+                        .map_err(|_| format!("Invalid --max-time '{}'", seconds))?;
+                    curl.timeout = Some(Duration::from_secs_f64(seconds));
+                }
+                "--connect-timeout" => {
+                    let seconds = value()?;
+                    let seconds: f64 = seconds
+                        .parse()
+                        .map_err(|_| format!("Invalid --connect-timeout '{}'", seconds))?;
+                    curl.connect_timeout = Some(Duration::from_secs_f64(seconds));
+                }
+                "--url" => curl.url = value()?,
+                "-k" | "--insecure" => curl.insecure = true,
+                "-G" | "--get" => curl.get = true,
+                "-I" | "--head" => curl.head = true,
+                option if IGNORED.contains(&option) => {}
+                option if option.starts_with('-') && option.len() > 1 => {
+                    return Err(format!("Unsupported curl option '{}'", option));
+                }
+                _ => curl.url = word,
+            }
+        }
+        if curl.url.is_empty() {
+            return Err("no url in the curl command".to_string());
+        }
+        Ok(curl)
+    }
+}
+
+impl Curl {
+    pub fn method(&self) -> String {
+        match (&self.method, self.head, self.get) {
+            (Some(method), _, _) => method.clone(),
+            (None, true, _) => "HEAD".to_string(),
+            (None, false, false) if !self.data.is_empty() => "POST".to_string(),
+            _ => "GET".to_string(),
+        }
+    }
+
+    pub fn has_header(&self, name: &str) -> bool {
+        self.headers
+            .iter()
+            .any(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+
+    /// a client for the proxy, connect timeout and certificate checks of the command
+    pub fn client(&self, client: &Client) -> Result<Client, String> {
+        if self.proxy.is_none() && !self.insecure && self.connect_timeout.is_none() {
+            return Ok(client.clone());
+        }
+        let mut builder = Client::builder().danger_accept_invalid_certs(self.insecure);
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy =
+                Proxy::all(proxy.as_str()).map_err(|_| format!("Invalid proxy '{}'", proxy))?;
+            builder = builder.proxy(proxy);
+        }
+        builder
+            .build()
+            .map_err(|e| format!("Failed to set up the client: {}", e))
+    }
+
+    pub fn request(&self, client: &Client) -> Result<RequestBuilder, String> {
+        let method = Method::from_bytes(self.method().as_bytes())
+            .map_err(|_| format!("Invalid method '{}'", self.method()))?;
+        let data = self.data.join("&");
+        let url = match (self.get, data.is_empty()) {
+            (true, false) if self.url.contains('?') => format!("{}&{}", self.url, data),
+            (true, false) => format!("{}?{}", self.url, data),
+            _ => self.url.clone(),
+        };
+        let mut request = client.request(method, url.as_str());
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some(user) = &self.user {
+            let (user, password) = match user.split_once(':') {
+                Some((user, password)) => (user, Some(password)),
+                None => (user.as_str(), None),
+            };
+            request = request.basic_auth(user, password);
+        }
+        if !self.get && !data.is_empty() {
+            if !self.has_header("content-type") {
+                request = request.header("Content-Type", "application/x-www-form-urlencoded");
+            }
+            request = request.body(data);
+        }
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        Ok(request)
+    }
+}
+
+/// quotes a word for a POSIX shell if it needs it
+pub fn quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// the proxy reqwest picks up from the environment for the url, like curl does
+fn env_proxy(url: &str) -> Option<String> {
+    let names: &[&str] = match url.starts_with("https:") {
+        true => &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"],
+        false => &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"],
+    };
+    names
+        .iter()
+        .find_map(|name| env::var(name).ok())
+        .filter(|proxy| !proxy.is_empty())
+}
+
+impl From<&Request> for Curl {
+    fn from(request: &Request) -> Self {
+        let headers = request.headers().iter().map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            (name.to_string(), value.into_owned())
+        });
+        let body = request.body().and_then(|body| body.as_bytes());
+        Curl {
+            method: Some(request.method().to_string()),
+            url: request.url().to_string(),
+            headers: headers.collect(),
+            data: body
+                .map(|body| String::from_utf8_lossy(body).into_owned())
+                .into_iter()
+                .collect(),
+            proxy: env_proxy(request.url().as_str()),
+            timeout: request.timeout().copied(),
+            ..Curl::default()
+        }
+    }
+}
+
+impl fmt::Display for Curl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "curl {}", quote(&self.url))?;
+        let method = self.method();
+        let implied = match (self.head, self.get || self.data.is_empty()) {
+            (true, _) => "HEAD",
+            (false, true) => "GET",
+            (false, false) => "POST",
+        };
+        match method.as_str() {
+            "HEAD" if method == implied => write!(f, " -I")?,
+            _ if method == implied => {}
+            method => write!(f, " -X {}", quote(method))?,
+        }
+        for (name, value) in &self.headers {
+            match name.eq_ignore_ascii_case("user-agent") {
+                true => write!(f, " -A {}", quote(value))?,
+                false => write!(f, " -H {}", quote(&format!("{}: {}", name, value)))?,
+            }
+        }
+        if self.get && !self.data.is_empty() {
+            write!(f, " -G")?;
+        }
+        for data in &self.data {
+            write!(f, " --data-raw {}", quote(data))?;
+        }
+        if let Some(user) = &self.user {
+            write!(f, " -u {}", quote(user))?;
+        }
+        if let Some(proxy) = &self.proxy {
+            write!(f, " -x {}", quote(proxy))?;
+        }
+        if self.insecure {
+            write!(f, " -k")?;
+        }
+        if let Some(timeout) = self.timeout {
+            write!(f, " -m {}", timeout.as_secs_f64())?;
+        }
+        if let Some(timeout) = self.connect_timeout {
+            write!(f, " --connect-timeout {}", timeout.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn run(client: &Client, args: FromCurlArgs) -> Result<(), Error> {
+    let curl = args.command;
+    let request = curl.request(&curl.client(client)?)?;
+    log::info(format!("{} {}", curl.method(), curl.url));
+    let res = request
+        .send()
+        .await
+        .map_err(|_| format!("Failed to {} '{}'", curl.method(), curl.url))?;
+    if !res.status().is_success() {
+        log::warn(format!("'{}' answered {}", res.url(), res.status()));
+    }
+    let content_type = res
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let bytes = res.bytes().await?;
+    let body = scrape_core::charset::decode(&bytes, content_type.as_deref());
+
+    let (selector, attribute) = match args.selector.as_deref().map(split_attribute) {
+        Some((selector, attribute)) => (Some(selector), attribute.or(args.attribute.as_deref())),
+        None => (None, args.attribute.as_deref()),
+    };
+    let values = match selector {
+        Some(selector) => extract(
+            &Html::parse_document(&body),
+            &parse_selector(selector)?,
+            attribute,
+        ),
+        None => vec![body],
+    };
+    print_values(values, None);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> Curl {
+        command.parse().unwrap()
+    }
+
+    fn request(command: &str) -> Request {
+        let curl = parse(command);
+        curl.request(&Client::new()).unwrap().build().unwrap()
+    }
+
+    #[test]
+    fn splits_like_a_shell() {
+        assert_eq!(
+            split(r#"curl 'a b' "c \"d\" \$e \x" f\ g $'h\'\n\x41é'"#).unwrap(),
+            ["curl", "a b", "c \"d\" $e \\x", "f g", "h'\nA\u{e9}"]
+        );
+        assert_eq!(
+            split("curl \\\n  'https://example.com' \\\r\n  -H 'a: b'").unwrap(),
+            ["curl", "https://example.com", "-H", "a: b"]
+        );
+        assert_eq!(split("curl 'x'y\"z\"").unwrap(), ["curl", "xyz"]);
+        assert_eq!(split("curl ''").unwrap(), ["curl", ""]);
+        assert_eq!(split("curl 'open").unwrap_err(), "unterminated '");
+        assert_eq!(split("curl \"open").unwrap_err(), "unterminated \"");
+        assert_eq!(split("curl $'open").unwrap_err(), "unterminated $'");
+        assert_eq!(split("curl \\").unwrap_err(), "trailing backslash");
+    }
+
+    #[test]
+    fn devtools_command() {
+        let curl = parse(
+            "curl 'https://example.com/api?q=1' \\\n  -H 'accept: application/json' \\\n  \
+             -H 'cookie: a=b; c=d' \\\n  --compressed",
+        );
+        assert_eq!(curl.url, "https://example.com/api?q=1");
+        assert_eq!(curl.method(), "GET");
+        assert_eq!(
+            curl.headers,
+            [
+                ("accept".to_string(), "application/json".to_string()),
+                ("cookie".to_string(), "a=b; c=d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn headers() {
+        let curl =
+            parse("curl -H'x-one:1' --header='x-two: 2' -A agent -e ref -b 'k=v' example.com");
+        let headers: Vec<_> = curl
+            .headers
+            .iter()
+            .map(|(n, v)| format!("{}={}", n, v))
+            .collect();
+        assert_eq!(
+            headers,
+            [
+                "x-one=1",
+                "x-two=2",
+                "User-Agent=agent",
+                "Referer=ref",
+                "Cookie=k=v"
+            ]
+        );
+        assert_eq!(
+            "curl example.com -H nocolon".parse::<Curl>().unwrap_err(),
+            "Invalid header 'nocolon'"
+        );
+    }
+
+    #[test]
+    fn methods() {
+        assert_eq!(parse("curl -X put example.com").method(), "PUT");
+        assert_eq!(
+            parse("curl --request=DELETE example.com").method(),
+            "DELETE"
+        );
+        assert_eq!(parse("curl -XPATCH example.com").method(), "PATCH");
+        assert_eq!(parse("curl -I example.com").method(), "HEAD");
+        assert_eq!(parse("curl -d x example.com").method(), "POST");
+        assert_eq!(parse("curl -G -d x example.com").method(), "GET");
+        assert_eq!(parse("curl -X GET -d x example.com").method(), "GET");
+    }
+
+    #[test]
+    fn data() {
+        let curl = parse(
+            "curl https://example.com -d a=1 --data-raw 'b=2&c' --data-binary=@file \
+             --data-urlencode 'q=a b&c' --data-urlencode 'é'",
+        );
+        assert_eq!(curl.data, ["a=1", "b=2&c", "@file", "q=a+b%26c", "%C3%A9"]);
+
+        let post = request("curl https://example.com -d a=1 -d b=2");
+        assert_eq!(post.method(), Method::POST);
+        assert_eq!(
+            post.body().and_then(|b| b.as_bytes()),
+            Some(&b"a=1&b=2"[..])
+        );
+        assert_eq!(
+            post.headers()["content-type"],
+            "application/x-www-form-urlencoded"
+        );
+
+        let json = request("curl https://example.com -H 'Content-Type: application/json' -d '{}'");
+        assert_eq!(json.headers()["content-type"], "application/json");
+
+        let get = request("curl -G 'https://example.com/?x=0' -d a=1 -d b=2");
+        assert_eq!(get.url().as_str(), "https://example.com/?x=0&a=1&b=2");
+        assert!(get.body().is_none());
+    }
+
+    #[test]
+    fn options() {
+        let curl =
+            parse("curl.exe -k -u user:secret -x http://proxy:3128 -m 2.5 -sSL --url example.com");
+        assert!(curl.insecure);
+        assert_eq!(curl.user.as_deref(), Some("user:secret"));
+        assert_eq!(curl.proxy.as_deref(), Some("http://proxy:3128"));
+        assert_eq!(curl.timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(curl.url, "example.com");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |command: &str| command.parse::<Curl>().unwrap_err();
+        assert_eq!(error("wget https://example.com"), "not a curl command");
+        assert_eq!(error("curl -H 'a: b'"), "no url in the curl command");
+        assert_eq!(error("curl https://example.com -H"), "-H needs a value");
+        assert_eq!(
+            error("curl https://example.com --retry 3"),
+            "Unsupported curl option '--retry'"
+        );
+        assert_eq!(
+            error("curl https://example.com -F a=b"),
+            "Unsupported curl option '-F'"
+        );
+        assert_eq!(
+            error("curl -m soon example.com"),
+            "Invalid --max-time 'soon'"
+        );
+    }
+
+    #[test]
+    fn display_parses_back() {
+        let command = "curl 'https://example.com/?a=1&b=2' -X PUT -H 'x-quote: it'\\''s' \
+                       --data-raw 'a b' -u u:p -k -m 1.5";
+        let curl = parse(command);
+        assert_eq!(curl.to_string(), command);
+        assert_eq!(parse(&curl.to_string()).to_string(), command);
+    }
+}
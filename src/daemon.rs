@@ -0,0 +1,228 @@
+use futures_util::future::join_all;
+use reqwest::{header::CONTENT_TYPE, Client, Url};
+use scraper::Selector;
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Instant, SystemTime},
+};
+
+use crate::{
+    cron::Schedule, datetime, json::Json, log, metrics, notify, parse_selector, s3, toml, watch,
+    Error,
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct DaemonArgs {
+    /// toml file with a `[[job]]` table for every page to scrape
+    #[clap(short, long)]
+    config: PathBuf,
+
+    /// serve per-job request, failure and change metrics for Prometheus on this address
+    #[clap(long)]
+    metrics: Option<SocketAddr>,
+}
+
+/// where a job's results go
+enum Sink {
+    Stdout,
+    /// appended to, one `timestamp\tvalue` line per result
+    File(PathBuf),
+    Webhook(Url),
+    /// one object per run, `prefix/job/timestamp.txt`
+    S3(s3::Location),
+}
+
+impl Sink {
+    fn parse(output: &str) -> Result<Self, String> {
+        Ok(match output {
+            "-" | "stdout" => Sink::Stdout,
+            location if location.starts_with("s3://") => Sink::S3(location.parse()?),
+            url if url.starts_with("http://") || url.starts_with("https://") => {
+                Sink::Webhook(Url::parse(url).map_err(|_| format!("Invalid URL '{}'", url))?)
+            }
+            path => Sink::File(PathBuf::from(path)),
+        })
+    }
+}
+
+/// a page scraped on a schedule, e.g.
+///
+/// ```toml
+/// [[job]]
+/// name = "price"
+/// url = "https://shop.example/item/42"
+/// selector = ".price"
+/// schedule = "*/15 * * * *"
+/// watch = true
+/// output = ["-", "prices.log", "https://ntfy.sh/my-prices", "s3://bucket/prices/"]
+/// ```
+struct Job {
+    name: String,
+    url: String,
+    selector: Option<Selector>,
+    attribute: Option<String>,
+    schedule: Schedule,
+    /// only output results when they changed since the last run
+    watch: bool,
+    notify: bool,
+    outputs: Vec<Sink>,
+}
+
+impl Job {
+    fn parse(job: &Json) -> Result<Self, Error> {
+        let string = |key| job.get(key).and_then(Json::as_str);
+        let url = string("url").ok_or("needs a url")?;
+        let outputs = match job.get("output") {
+            None => vec![Sink::Stdout],
+            Some(Json::String(output)) => vec![Sink::parse(output)?],
+            Some(Json::Array(outputs)) => outputs
+                .iter()
+                .map(|output| output.as_str().ok_or("`output` must be a list of strings"))
+                .map(|output| Sink::parse(output?))
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err("`output` must be a path, url or list of them".into()),
+        };
+        Ok(Job {
+            name: string("name").unwrap_or(url).to_string(),
+            url: url.to_string(),
+            selector: string("selector").map(parse_selector).transpose()?,
+            attribute: string("attribute").map(String::from),
+            schedule: string("schedule").ok_or("needs a schedule")?.parse()?,
+            watch: job.get("watch").and_then(Json::as_bool).unwrap_or(false),
+            notify: job.get("notify").and_then(Json::as_bool).unwrap_or(false),
+            outputs,
+        })
+    }
+
+    /// scrapes whenever the schedule says so, returns only if it never fires again
+    async fn run(&self, client: &Client) {
+        let mut previous: Option<String> = None;
+        while let Some(next) = self.schedule.next_after(SystemTime::now()) {
+            let wait = next.duration_since(SystemTime::now()).unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            log::info(format!("{}: running", self.name));
+            let selector = self.selector.as_ref();
+            let started = Instant::now();
+            let current =
+                watch::fetch(client, &self.url, selector, self.attribute.as_deref()).await;
+            metrics::request(&self.name, started.elapsed(), current.is_ok());
+            let current = match current {
+                Ok(current) => current,
+                Err(error) => {
+                    log::error(format!("{}: {}", self.name, error));
+                    continue;
+                }
+            };
+            if previous.as_ref() == Some(&current) {
+                if self.watch {
+                    continue;
+                }
+            } else if previous.is_some() {
+                metrics::changed(&self.name);
+            }
+            let changed = previous.as_ref().filter(|previous| **previous != current);
+            if let (true, Some(previous)) = (self.notify, changed) {
+                let title = format!("{} changed", self.name);
+                notify::send(&title, &watch::summary(previous, &current));
+            }
+            for output in &self.outputs {
+                if let Err(error) = self
+                    .write(client, output, previous.as_deref(), &current)
+                    .await
+                {
+                    log::error(format!("{}: {}", self.name, error));
+                }
+            }
+            previous = Some(current);
+        }
+        log::warn(format!("{}: schedule never fires again", self.name));
+    }
+
+    async fn write(
+        &self,
+        client: &Client,
+        output: &Sink,
+        previous: Option<&str>,
+        current: &str,
+    ) -> Result<(), Error> {
+        match output {
+            Sink::Stdout => {
+                for line in current.lines() {
+                    println!("{}\t{}", self.name, line);
+                }
+            }
+            Sink::File(path) => {
+                if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    fs::create_dir_all(dir)?;
+                }
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                let now = datetime::now();
+                for line in current.lines() {
+                    writeln!(file, "{}\t{}", now, line)?;
+                }
+            }
+            // watch jobs only call their webhooks with changes, like `scrape watch --webhook`
+            Sink::Webhook(hook) if self.watch => {
+                if let Some(previous) = previous {
+                    watch::webhook(client, hook, &self.url, previous, current).await;
+                }
+            }
+            Sink::Webhook(hook) => {
+                let payload = Json::object([
+                    ("job", self.name.as_str().into()),
+                    ("url", self.url.as_str().into()),
+                    ("values", current.lines().collect::<Vec<_>>().into()),
+                    ("timestamp", datetime::now().into()),
+                ]);
+                let res = client
+                    .post(hook.clone())
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(payload.to_string())
+                    .send()
+                    .await
+                    .map_err(|_| format!("Failed to POST to '{}'", hook))?;
+                if !res.status().is_success() {
+                    return Err(format!("Webhook '{}' answered {}", hook, res.status()).into());
+                }
+            }
+            Sink::S3(location) => {
+                let name = format!("{}/{}.txt", self.name, datetime::now());
+                let data = current.as_bytes().to_vec();
+                location
+                    .put(client, &name, data, "text/plain; charset=utf-8")
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub async fn run(client: &Client, args: DaemonArgs) -> Result<(), Error> {
+    let text = fs::read_to_string(&args.config)
+        .map_err(|e| format!("Failed to read '{}': {}", args.config.display(), e))?;
+    let config = toml::parse(&text).map_err(|e| format!("{}: {}", args.config.display(), e))?;
+    let jobs = match config.get("job") {
+        Some(Json::Array(jobs)) => jobs
+            .iter()
+            .enumerate()
+            .map(|(i, job)| Job::parse(job).map_err(|e| format!("job {}: {}", i + 1, e)))
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err(format!("{}: no [[job]] tables", args.config.display()).into()),
+    };
+
+    if let Some(address) = args.metrics {
+        metrics::serve(address).await?;
+    }
+    for job in &jobs {
+        metrics::register(&job.name);
+        let next = job.schedule.next_after(SystemTime::now());
+        let next = next.map_or("never".to_string(), datetime::rfc3339);
+        log::info(format!("{}: next run at {}", job.name, next));
+    }
+    join_all(jobs.iter().map(|job| job.run(client))).await;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// year, month and day for days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// days since 1970-01-01 for a year, month and day
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = i64::from((month + 9) % 12);
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// seconds since the epoch for the W3C datetimes sitemaps use, from `2022` and `2022-10-03` to
+/// `2022-10-03T10:00:00.5+02:00`, times without an offset count as UTC
+pub fn w3c(date: &str) -> Option<i64> {
+    let (date, time) = date.trim().split_once('T').unwrap_or((date.trim(), ""));
+    let number = |digits: &str, len: usize| match digits.len() == len {
+        true => digits.parse::<u32>().ok(),
+        false => None,
+    };
+    let mut parts = date.split('-');
+    let year = number(parts.next()?, 4)?;
+    let month = parts.next().map_or(Some(1), |m| number(m, 2))?;
+    let day = parts.next().map_or(Some(1), |d| number(d, 2))?;
+    if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year.into(), month, day);
+
+    let zone = time.find(['Z', 'z', '+', '-']).unwrap_or(time.len());
+    let (clock, zone) = time.split_at(zone);
+    let mut seconds = 0;
+    if !clock.is_empty() || !zone.is_empty() {
+        let clock = clock.split_once('.').map_or(clock, |(whole, _)| whole);
+        let mut fields = clock.split(':');
+        let hours = number(fields.next()?, 2)?;
+        let minutes = number(fields.next()?, 2)?;
+        let secs = fields.next().map_or(Some(0), |s| number(s, 2))?;
+        if fields.next().is_some() || hours > 24 || minutes > 59 || secs > 60 {
+            return None;
+        }
+        seconds = i64::from(hours * 3600 + minutes * 60 + secs);
+    }
+    let offset = match zone {
+        "" | "Z" | "z" => 0,
+        _ => {
+            let sign = if zone.starts_with('-') { -1 } else { 1 };
+            let zone = &zone[1..];
+            let (hours, minutes) = zone
+                .split_once(':')
+                .unwrap_or((zone.get(..2)?, zone.get(2..)?));
+            sign * i64::from(number(hours, 2)? * 3600 + number(minutes, 2)? * 60)
+        }
+    };
+    Some(days * 86_400 + seconds - offset)
+}
+
+/// `--modified-since`, checked to be a W3C datetime
+pub fn parse_w3c(date: &str) -> Result<i64, String> {
+    w3c(date).ok_or_else(|| {
+        format!(
+            "Invalid date '{}', e.g. `2022-01-31` or `2022-01-31T12:00:00+02:00`",
+            date
+        )
+    })
+}
+
+/// `2022-04-07T12:34:56Z`
+pub fn rfc3339(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    };
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time_of_day = seconds.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    )
+}
+
+pub fn now() -> String {
+    rfc3339(SystemTime::now())
+}
+
+/// `Mon, 03 Oct 2022 10:00:00 GMT` as used by http and RSS, in the W3C format
+/// sitemaps and Atom use, e.g. `2022-10-03T10:00:00+00:00`
+pub fn rfc2822(date: &str) -> Option<String> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let date = date.split_once(',').map_or(date, |(_, date)| date);
+    let mut parts = date.split_whitespace();
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month = parts.next()?.to_ascii_lowercase();
+    let month = MONTHS.iter().position(|m| month.starts_with(m))? + 1;
+    let year: u32 = match parts.next()?.parse().ok()? {
+        year @ 0..=49 => year + 2000,
+        year @ 50..=99 => year + 1900,
+        year => year,
+    };
+    let time = match parts.next()? {
+        time if time.matches(':').count() == 1 => format!("{}:00", time),
+        time => time.to_string(),
+    };
+    let offset = match parts.next().unwrap_or("GMT") {
+        "EDT" => "-04:00".to_string(),
+        "EST" | "CDT" => "-05:00".to_string(),
+        "CST" | "MDT" => "-06:00".to_string(),
+        "MST" | "PDT" => "-07:00".to_string(),
+        "PST" => "-08:00".to_string(),
+        offset if offset.len() == 5 && offset.starts_with(['+', '-']) => {
+            format!("{}:{}", &offset[..3], &offset[3..])
+        }
+        _ => "+00:00".to_string(),
+    };
+    Some(format!(
+        "{:04}-{:02}-{:02}T{}{}",
+        year, month, day, time, offset
+    ))
+}
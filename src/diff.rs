@@ -0,0 +1,358 @@
+//! line diffs in the unified format, found with the linear space variant of Myers' algorithm
+
+use std::io::{self, IsTerminal};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// part of the edit graph, lines `left..right` of the old text and `top..bottom` of the new one
+#[derive(Clone, Copy)]
+struct Box {
+    left: isize,
+    top: isize,
+    right: isize,
+    bottom: isize,
+}
+
+impl Box {
+    fn delta(&self) -> isize {
+        (self.right - self.left) - (self.bottom - self.top)
+    }
+
+    fn size(&self) -> isize {
+        (self.right - self.left) + (self.bottom - self.top)
+    }
+}
+
+/// a point on the edit graph, `x` lines into the old text and `y` into the new one
+type Point = (isize, isize);
+
+/// Myers' linear space search: the middle snake of the shortest path through the box, found by
+/// searching from both corners at once and keeping only the furthest point on each diagonal
+fn middle_snake(old: &[&str], new: &[&str], bounds: Box) -> Option<(Point, Point)> {
+    if bounds.size() == 0 {
+        return None;
+    }
+    let max = (bounds.size() + 1) / 2;
+    let offset = max + 1;
+    let at = |k: isize| (offset + k) as usize;
+    let mut forward = vec![0; 2 * max as usize + 3];
+    let mut backward = vec![0; 2 * max as usize + 3];
+    forward[at(1)] = bounds.left;
+    backward[at(1)] = bounds.bottom;
+    let delta = bounds.delta();
+
+    for d in 0..=max {
+        // furthest reaching paths from the top left, on diagonals `k = x - y` shifted to the box
+        for k in (0..=d).map(|i| d - 2 * i) {
+            let c = k - delta;
+            let (previous_x, mut x) =
+                if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                    (forward[at(k + 1)], forward[at(k + 1)])
+                } else {
+                    (forward[at(k - 1)], forward[at(k - 1)] + 1)
+                };
+            let mut y = bounds.top + (x - bounds.left) - k;
+            let previous_y = if d == 0 || x != previous_x { y } else { y - 1 };
+            while x < bounds.right && y < bounds.bottom && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+            if delta % 2 != 0 && (-(d - 1)..=d - 1).contains(&c) && y >= backward[at(c)] {
+                return Some(((previous_x, previous_y), (x, y)));
+            }
+        }
+        // and from the bottom right, on diagonals `c = k - delta`
+        for c in (0..=d).map(|i| d - 2 * i) {
+            let k = c + delta;
+            let (previous_y, mut y) =
+                if c == -d || (c != d && backward[at(c - 1)] > backward[at(c + 1)]) {
+                    (backward[at(c + 1)], backward[at(c + 1)])
+                } else {
+                    (backward[at(c - 1)], backward[at(c - 1)] - 1)
+                };
+            let mut x = bounds.left + (y - bounds.top) + k;
+            let previous_x = if d == 0 || y != previous_y { x } else { x + 1 };
+            while x > bounds.left && y > bounds.top && old[x as usize - 1] == new[y as usize - 1] {
+                x -= 1;
+                y -= 1;
+            }
+            backward[at(c)] = y;
+            if delta % 2 == 0 && (-d..=d).contains(&k) && x <= forward[at(k)] {
+                return Some(((x, y), (previous_x, previous_y)));
+            }
+        }
+    }
+    None
+}
+
+/// the points a shortest path through the box goes through, without keeping the search's history
+fn path(old: &[&str], new: &[&str], bounds: Box) -> Option<Vec<Point>> {
+    let (start, finish) = middle_snake(old, new, bounds)?;
+    let head = Box {
+        right: start.0,
+        bottom: start.1,
+        ..bounds
+    };
+    let tail = Box {
+        left: finish.0,
+        top: finish.1,
+        ..bounds
+    };
+    let mut points = path(old, new, head).unwrap_or_else(|| vec![start]);
+    points.extend(path(old, new, tail).unwrap_or_else(|| vec![finish]));
+    Some(points)
+}
+
+/// the shortest edit script turning `old` into `new`, in memory linear in their length
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let mut script = Vec::new();
+    let bounds = Box {
+        left: 0,
+        top: 0,
+        right: old.len() as isize,
+        bottom: new.len() as isize,
+    };
+    let points = path(old, new, bounds).unwrap_or_default();
+    // consecutive points are joined by diagonals and at most one deletion or insertion
+    for pair in points.windows(2) {
+        let ((mut x, mut y), (to_x, to_y)) = (pair[0], pair[1]);
+        while (x, y) != (to_x, to_y) {
+            if x < to_x && y < to_y && old[x as usize] == new[y as usize] {
+                script.push(Edit::Equal(x as usize, y as usize));
+                x += 1;
+                y += 1;
+            } else if to_x - x > to_y - y {
+                script.push(Edit::Delete(x as usize));
+                x += 1;
+            } else {
+                script.push(Edit::Insert(y as usize));
+                y += 1;
+            }
+        }
+    }
+    script
+}
+
+/// a unified diff with three lines of context, empty if nothing changed
+pub fn unified(old: &str, new: &str, old_name: &str, new_name: &str) -> Vec<String> {
+    const CONTEXT: usize = 3;
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    let script = edits(&old, &new);
+
+    let changes: Vec<usize> = script
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return vec![];
+    }
+
+    // ranges of the edit script to show, changes plus their context, merged when they touch
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for change in changes {
+        let start = change.saturating_sub(CONTEXT);
+        let end = (change + CONTEXT + 1).min(script.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut lines = vec![format!("--- {}", old_name), format!("+++ {}", new_name)];
+    for (start, end) in hunks {
+        let edits = &script[start..end];
+        // where the hunk starts in each file, counting the lines before it
+        let (mut old_start, mut new_start) = (0, 0);
+        for edit in &script[..start] {
+            match edit {
+                Edit::Equal(..) => {
+                    old_start += 1;
+                    new_start += 1;
+                }
+                Edit::Delete(_) => old_start += 1,
+                Edit::Insert(_) => new_start += 1,
+            }
+        }
+        let old_len = edits
+            .iter()
+            .filter(|e| !matches!(e, Edit::Insert(_)))
+            .count();
+        let new_len = edits
+            .iter()
+            .filter(|e| !matches!(e, Edit::Delete(_)))
+            .count();
+        lines.push(format!(
+            "@@ -{},{} +{},{} @@",
+            old_start + usize::from(old_len > 0),
+            old_len,
+            new_start + usize::from(new_len > 0),
+            new_len
+        ));
+        for edit in edits {
+            lines.push(match *edit {
+                Edit::Equal(i, _) => format!(" {}", old[i]),
+                Edit::Delete(i) => format!("-{}", old[i]),
+                Edit::Insert(j) => format!("+{}", new[j]),
+            });
+        }
+    }
+    lines
+}
+
+/// prints a diff, in color when stdout is a terminal
+pub fn print(lines: &[String]) {
+    let color = io::stdout().is_terminal();
+    for line in lines {
+        let code = match line.chars().next() {
+            _ if !color => None,
+            _ if line.starts_with("---") || line.starts_with("+++") => Some("1"),
+            Some('-') => Some("31"),
+            Some('+') => Some("32"),
+            Some('@') => Some("36"),
+            _ => None,
+        };
+        match code {
+            Some(code) => println!("\x1b[{}m{}\x1b[0m", code, line),
+            None => println!("{}", line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the new lines, rebuilt from the old ones and the script
+    fn apply(old: &[&str], new: &[&str], script: &[Edit]) -> Vec<String> {
+        let (mut x, mut y) = (0, 0);
+        let mut lines = vec![];
+        for edit in script {
+            match *edit {
+                Edit::Equal(i, j) => {
+                    assert_eq!((i, j), (x, y));
+                    lines.push(old[i].to_string());
+                    x += 1;
+                    y += 1;
+                }
+                Edit::Delete(i) => {
+                    assert_eq!(i, x);
+                    x += 1;
+                }
+                Edit::Insert(j) => {
+                    assert_eq!(j, y);
+                    lines.push(new[j].to_string());
+                    y += 1;
+                }
+            }
+        }
+        assert_eq!((x, y), (old.len(), new.len()));
+        lines
+    }
+
+    /// how many lines have to be deleted or inserted at least, from the longest common subsequence
+    fn distance(old: &[&str], new: &[&str]) -> usize {
+        let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+        for i in 0..old.len() {
+            for j in 0..new.len() {
+                lcs[i + 1][j + 1] = match old[i] == new[j] {
+                    true => lcs[i][j] + 1,
+                    false => lcs[i][j + 1].max(lcs[i + 1][j]),
+                };
+            }
+        }
+        old.len() + new.len() - 2 * lcs[old.len()][new.len()]
+    }
+
+    fn check(old: &[&str], new: &[&str]) {
+        let script = edits(old, new);
+        assert_eq!(apply(old, new, &script), new, "{:?} -> {:?}", old, new);
+        let changes = script
+            .iter()
+            .filter(|e| !matches!(e, Edit::Equal(..)))
+            .count();
+        assert_eq!(changes, distance(old, new), "{:?} -> {:?}", old, new);
+    }
+
+    #[test]
+    fn shortest_scripts() {
+        check(&[], &[]);
+        check(&[], &["a"]);
+        check(&["a"], &[]);
+        check(&["a", "b", "c"], &["a", "b", "c"]);
+        check(
+            &["a", "b", "c", "a", "b", "b", "a"],
+            &["c", "b", "a", "b", "a", "c"],
+        );
+        check(&["x", "a", "b"], &["a", "b", "y"]);
+        check(&["a", "b"], &["c", "d", "e"]);
+    }
+
+    #[test]
+    fn shortest_scripts_for_random_texts() {
+        // xorshift, to vary the inputs without a dependency
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = move |n: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % n
+        };
+        let words = ["a", "b", "c", "d"];
+        for _ in 0..500 {
+            let mut text =
+                |len| -> Vec<&str> { (0..next(len)).map(|_| words[next(4) as usize]).collect() };
+            let (old, new) = (text(12), text(12));
+            check(&old, &new);
+        }
+    }
+
+    #[test]
+    fn large_texts_that_differ_everywhere() {
+        let old: Vec<String> = (0..3_000).map(|i| format!("old {}", i)).collect();
+        let new: Vec<String> = (0..3_000).map(|i| format!("new {}", i)).collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        let script = edits(&old, &new);
+        assert_eq!(script.len(), 6_000);
+        assert_eq!(apply(&old, &new, &script), new);
+    }
+
+    #[test]
+    fn unified_hunks() {
+        assert!(unified("a\nb\n", "a\nb\n", "old", "new").is_empty());
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\neleven\n";
+        assert_eq!(
+            unified(old, new, "old", "new"),
+            [
+                "--- old",
+                "+++ new",
+                "@@ -2,9 +2,10 @@",
+                " 2",
+                " 3",
+                " 4",
+                "-5",
+                "+five",
+                " 6",
+                " 7",
+                " 8",
+                " 9",
+                " 10",
+                "+eleven"
+            ]
+        );
+        assert_eq!(
+            unified("", "a\n", "old", "new"),
+            ["--- old", "+++ new", "@@ -0,0 +1,1 @@", "+a"]
+        );
+    }
+}
@@ -0,0 +1,19 @@
+use std::{env, path::PathBuf};
+
+fn xdg(variable: &str, fallback: &str) -> Option<PathBuf> {
+    let base = env::var_os(variable)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))?;
+    Some(base.join("scrape"))
+}
+
+/// `~/.config/scrape`
+pub fn config_dir() -> Option<PathBuf> {
+    xdg("XDG_CONFIG_HOME", ".config")
+}
+
+/// `~/.local/share/scrape`
+pub fn data_dir() -> Option<PathBuf> {
+    xdg("XDG_DATA_HOME", ".local/share")
+}
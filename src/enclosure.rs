@@ -0,0 +1,128 @@
+//! saving feed enclosures to disk, resuming where an earlier run stopped
+
+use futures_util::StreamExt;
+use reqwest::{
+    header::{CONTENT_LENGTH, RANGE},
+    Client, StatusCode, Url,
+};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{feed::Item, progress, rate, Error};
+
+/// keeps a substituted value from adding directories or characters filesystems reject
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .trim_start_matches('.')
+        .to_string()
+}
+
+/// fills `{title}`, `{date}`, `{author}`, `{filename}`, `{stem}` and `{ext}` in the template
+pub fn file_name(template: &str, item: &Item, url: &Url) -> String {
+    let filename = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("enclosure");
+    let (stem, ext) = filename.rsplit_once('.').unwrap_or((filename, "bin"));
+    let date = item
+        .date
+        .as_deref()
+        .map_or("", |date| &date[..date.len().min(10)]);
+
+    let name = template
+        .replace(
+            "{title}",
+            &sanitize(item.title.as_deref().unwrap_or("untitled")),
+        )
+        .replace("{date}", &sanitize(date))
+        .replace(
+            "{author}",
+            &sanitize(item.author.as_deref().unwrap_or_default()),
+        )
+        .replace("{filename}", &sanitize(filename))
+        .replace("{stem}", &sanitize(stem))
+        .replace("{ext}", &sanitize(ext));
+    match sanitize(&name) {
+        name if name.is_empty() => sanitize(filename),
+        name => name,
+    }
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    PathBuf::from(partial)
+}
+
+/// downloads `url` to `path`, continuing a `.part` file left by an interrupted run,
+/// returns false if the file was already there
+pub async fn save(client: &Client, url: &Url, path: &Path) -> Result<bool, Error> {
+    if path.exists() {
+        return Ok(false);
+    }
+    let partial = partial_path(path);
+    let resume_from = fs::metadata(&partial).map_or(0, |metadata| metadata.len());
+
+    rate::wait(url).await;
+    let mut request = client.get(url.clone());
+    if resume_from > 0 {
+        request = request.header(RANGE, format!("bytes={}-", resume_from));
+    }
+    let res = request
+        .send()
+        .await
+        .map_err(|_| format!("Failed to GET from '{}'", url))?;
+
+    let append = match res.status() {
+        StatusCode::PARTIAL_CONTENT => true,
+        // the part file already holds everything
+        StatusCode::RANGE_NOT_SATISFIABLE if resume_from > 0 => {
+            fs::rename(&partial, path)?;
+            return Ok(true);
+        }
+        status if status.is_success() => false,
+        status => {
+            let url = url.to_string();
+            return Err(Error::Status { url, status });
+        }
+    };
+
+    let remaining = res
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse::<u64>().ok())
+        .unwrap_or(0);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&partial)
+        .map_err(|e| format!("Failed to write '{}': {}", partial.display(), e))?;
+
+    let offset = if append { resume_from } else { 0 };
+    let progress_bar = progress::download(offset + remaining, url.as_str());
+    progress_bar.set_position(offset);
+    let mut stream = res.bytes_stream();
+    while let Some(item) = stream.next().await {
+        let chunk = item.map_err(|_| "Error while downloading file")?;
+        file.write_all(&chunk)
+            .map_err(|_| "Error while writing to file")?;
+        progress_bar.inc(chunk.len() as u64);
+    }
+
+    fs::rename(&partial, path)?;
+    Ok(true)
+}
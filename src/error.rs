@@ -0,0 +1,199 @@
+//! what can go wrong, in kinds that `--error-format json` lets scripts tell apart
+
+use reqwest::StatusCode;
+use std::{fmt, io};
+
+use crate::{interrupt, json::Json};
+
+#[derive(Debug)]
+pub enum Error {
+    /// the host name of the url didn't resolve
+    Dns {
+        url: String,
+        source: reqwest::Error,
+    },
+    /// nothing accepted a connection to the host
+    Connect {
+        url: String,
+        source: reqwest::Error,
+    },
+    Timeout {
+        url: String,
+        source: reqwest::Error,
+    },
+    /// the redirects, or meta refreshes, went in circles or on for too long
+    Redirect {
+        url: String,
+        reason: String,
+    },
+    /// the request failed otherwise, or its body couldn't be read
+    Request {
+        url: String,
+        source: reqwest::Error,
+    },
+    /// the server answered, but with an error status
+    Status {
+        url: String,
+        status: StatusCode,
+    },
+    InvalidUrl(String),
+    InvalidSelector(String),
+    InvalidRegex(String),
+    /// Ctrl-C stopped the run before it was done
+    Interrupted,
+    /// everything else, as the message to show
+    Other(String),
+}
+
+impl Error {
+    /// the request for the url failed, by the reason reqwest gives
+    pub fn request(url: &str, source: reqwest::Error) -> Error {
+        let url = url.to_string();
+        if source.is_timeout() {
+            return Error::Timeout { url, source };
+        }
+        if source.is_builder() {
+            return Error::InvalidUrl(url);
+        }
+        if source.is_redirect() {
+            // the policy's own error, under the one reqwest adds
+            let mut reason = source.to_string();
+            let mut cause = std::error::Error::source(&source);
+            while let Some(error) = cause {
+                reason = error.to_string();
+                cause = error.source();
+            }
+            return Error::Redirect { url, reason };
+        }
+        if source.is_connect() {
+            // hyper only says so in the message of the error it wraps
+            let mut cause = std::error::Error::source(&source);
+            while let Some(error) = cause {
+                if error.to_string().starts_with("dns error") {
+                    return Error::Dns { url, source };
+                }
+                cause = error.source();
+            }
+            return Error::Connect { url, source };
+        }
+        Error::Request { url, source }
+    }
+
+    /// how `--error-format json` names the kind of error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Dns { .. } => "dns",
+            Error::Connect { .. } => "connect",
+            Error::Timeout { .. } => "timeout",
+            Error::Redirect { .. } => "redirect",
+            Error::Request { .. } => "request",
+            Error::Status { .. } => "status",
+            Error::InvalidUrl(_) => "invalid_url",
+            Error::InvalidSelector(_) => "invalid_selector",
+            Error::InvalidRegex(_) => "invalid_regex",
+            Error::Interrupted => "interrupted",
+            Error::Other(_) => "other",
+        }
+    }
+
+    /// the exit status of a run that failed with the error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Interrupted => interrupt::EXIT_CODE,
+            _ => 1,
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![
+            ("error", self.kind().into()),
+            ("message", self.to_string().into()),
+        ];
+        match self {
+            Error::Dns { url, .. }
+            | Error::Connect { url, .. }
+            | Error::Timeout { url, .. }
+            | Error::Request { url, .. }
+            | Error::InvalidUrl(url) => fields.push(("url", url.as_str().into())),
+            Error::Redirect { url, reason } => {
+                fields.push(("url", url.as_str().into()));
+                fields.push(("reason", reason.as_str().into()));
+            }
+            Error::Status { url, status } => {
+                fields.push(("url", url.as_str().into()));
+                fields.push(("status", status.as_u16().into()));
+            }
+            Error::InvalidSelector(selector) => fields.push(("selector", selector.as_str().into())),
+            Error::InvalidRegex(regex) => fields.push(("regex", regex.as_str().into())),
+            Error::Interrupted | Error::Other(_) => {}
+        }
+        Json::object(fields)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Dns { url, .. } => write!(f, "Failed to resolve the host of '{}'", url),
+            Error::Connect { url, .. } => write!(f, "Failed to connect to '{}'", url),
+            Error::Timeout { url, .. } => write!(f, "Timed out on '{}'", url),
+            Error::Redirect { url, reason } => {
+                write!(f, "Failed to follow the redirects of '{}': {}", url, reason)
+            }
+            Error::Request { url, .. } => write!(f, "Failed to GET from '{}'", url),
+            Error::Status { url, status } => write!(f, "'{}' answered {}", url, status),
+            Error::InvalidUrl(url) => write!(f, "Invalid URL '{}'", url),
+            Error::InvalidSelector(selector) => write!(f, "Invalid selector '{}'", selector),
+            Error::InvalidRegex(regex) => write!(f, "Invalid regex '{}'", regex),
+            Error::Interrupted => write!(f, "Interrupted, the results are partial"),
+            Error::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Dns { source, .. }
+            | Error::Connect { source, .. }
+            | Error::Timeout { source, .. }
+            | Error::Request { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<scrape_core::Error> for Error {
+    fn from(error: scrape_core::Error) -> Self {
+        match error {
+            scrape_core::Error::Request { url, source } => Error::request(&url, source),
+            scrape_core::Error::InvalidSelector(selector) => Error::InvalidSelector(selector),
+            scrape_core::Error::InvalidRegex(regex) => Error::InvalidRegex(regex),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        let url = error.url().map(|url| url.to_string()).unwrap_or_default();
+        Error::request(&url, error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Other(error.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Other(message.to_string())
+    }
+}
@@ -0,0 +1,357 @@
+use console::{style, Key, Term};
+use html5ever::tree_builder::TreeSink;
+use reqwest::Client;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashSet;
+
+use crate::{clipboard, download, extract, Error};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct ExploreArgs {
+    /// the page to explore
+    url: String,
+
+    /// a selector to start from
+    selector: Option<String>,
+}
+
+type NodeId = <Html as TreeSink>::Handle;
+
+/// how many matches to show the value of
+const PREVIEWS: usize = 3;
+/// lines below the tree: a rule, the selector, the match count and the previews
+const FOOTER: usize = 3 + PREVIEWS;
+
+/// a name that needs no escaping in a selector
+pub fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit() || c == '-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// a selector matching only this element, like `#main > ul.items > li:nth-of-type(3)`
+pub fn unique_selector(element: ElementRef) -> String {
+    let mut parts = Vec::new();
+    let mut current = Some(element);
+    while let Some(element) = current {
+        let value = element.value();
+        if let Some(id) = value.id().filter(|id| is_identifier(id)) {
+            parts.push(format!("#{}", id));
+            break;
+        }
+        let mut part = value.name().to_string();
+        for class in value.classes().filter(|class| is_identifier(class)) {
+            part.push('.');
+            part.push_str(class);
+        }
+        let parent = element.parent().and_then(ElementRef::wrap);
+        if let Some(parent) = parent {
+            let same_type: Vec<_> = parent
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|sibling| sibling.value().name() == value.name())
+                .collect();
+            if same_type.len() > 1 {
+                let position = same_type.iter().position(|s| *s == element).unwrap_or(0);
+                part.push_str(&format!(":nth-of-type({})", position + 1));
+            }
+        }
+        parts.push(part);
+        if matches!(value.name(), "body" | "html") {
+            break;
+        }
+        current = parent;
+    }
+    parts.reverse();
+    parts.join(" > ")
+}
+
+/// `tag#id.class` and the start of the element's own text
+fn label(element: ElementRef) -> (String, String) {
+    let value = element.value();
+    let mut name = value.name().to_string();
+    if let Some(id) = value.id() {
+        name.push('#');
+        name.push_str(id);
+    }
+    for class in value.classes() {
+        name.push('.');
+        name.push_str(class);
+    }
+    let text: String = element
+        .children()
+        .filter_map(|child| child.value().as_text())
+        .flat_map(|text| text.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (name, text)
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+struct Explorer<'a> {
+    document: &'a Html,
+    expanded: HashSet<NodeId>,
+    cursor: ElementRef<'a>,
+    /// the first row on screen
+    scroll: usize,
+    selector: String,
+    /// matches of the selector, in document order, none if it doesn't parse
+    matches: Option<Vec<ElementRef<'a>>>,
+}
+
+impl<'a> Explorer<'a> {
+    fn new(document: &'a Html, selector: String) -> Self {
+        let root = document.root_element();
+        let mut explorer = Explorer {
+            document,
+            expanded: HashSet::from([root.id()]),
+            cursor: root,
+            scroll: 0,
+            selector,
+            matches: None,
+        };
+        // open html, head and body
+        for child in root.children().filter_map(ElementRef::wrap) {
+            explorer.expanded.insert(child.id());
+        }
+        explorer.update_matches();
+        if let Some(first) = explorer.matches.as_ref().and_then(|m| m.first().copied()) {
+            explorer.reveal(first);
+        }
+        explorer
+    }
+
+    /// visible elements and their depth, in document order
+    fn rows(&self) -> Vec<(ElementRef<'a>, usize)> {
+        let mut rows = Vec::new();
+        let mut stack = vec![(self.document.root_element(), 0)];
+        while let Some((element, depth)) = stack.pop() {
+            rows.push((element, depth));
+            if self.expanded.contains(&element.id()) {
+                let children: Vec<_> = element.children().filter_map(ElementRef::wrap).collect();
+                stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+            }
+        }
+        rows
+    }
+
+    fn update_matches(&mut self) {
+        self.matches = match self.selector.trim() {
+            "" => Some(vec![]),
+            selector => Selector::parse(selector)
+                .ok()
+                .map(|selector| self.document.select(&selector).collect()),
+        };
+    }
+
+    /// moves the cursor to the element, opening its ancestors
+    fn reveal(&mut self, element: ElementRef<'a>) {
+        for ancestor in element.ancestors() {
+            self.expanded.insert(ancestor.id());
+        }
+        self.cursor = element;
+    }
+
+    /// jumps to the next (or previous) match after the cursor
+    fn next_match(&mut self, forward: bool) {
+        let matches = match &self.matches {
+            Some(matches) if !matches.is_empty() => matches,
+            _ => return,
+        };
+        let order: Vec<_> = self
+            .document
+            .root_element()
+            .descendants()
+            .map(|n| n.id())
+            .collect();
+        let index = |element: &ElementRef| order.iter().position(|id| *id == element.id());
+        let cursor = index(&self.cursor);
+        let next = if forward {
+            let after = matches.iter().find(|m| index(m) > cursor);
+            after.or_else(|| matches.first())
+        } else {
+            let before = matches.iter().rev().find(|m| index(m) < cursor);
+            before.or_else(|| matches.last())
+        };
+        if let Some(next) = next.copied() {
+            self.reveal(next);
+        }
+    }
+
+    /// handles a key, returns false once exploring is done
+    fn key(&mut self, key: Key, page: usize) -> bool {
+        let rows = self.rows();
+        let at = rows
+            .iter()
+            .position(|(element, _)| *element == self.cursor)
+            .unwrap_or(0);
+        let move_to = |index: usize| rows[index.min(rows.len() - 1)].0;
+        match key {
+            Key::Escape | Key::Char('\u{3}') | Key::Char('\u{4}') => return false,
+            Key::ArrowUp => self.cursor = move_to(at.saturating_sub(1)),
+            Key::ArrowDown => self.cursor = move_to(at + 1),
+            Key::PageUp => self.cursor = move_to(at.saturating_sub(page)),
+            Key::PageDown => self.cursor = move_to(at + page),
+            Key::Home => self.cursor = move_to(0),
+            Key::End => self.cursor = move_to(rows.len() - 1),
+            // opens the element, or goes to its first child if it already is
+            Key::ArrowRight => {
+                let opened = self.expanded.insert(self.cursor.id());
+                let first_child = self.cursor.children().find_map(ElementRef::wrap);
+                if let (false, Some(child)) = (opened, first_child) {
+                    self.cursor = child;
+                }
+            }
+            // closes the element, or goes to its parent if it already is
+            Key::ArrowLeft => {
+                let closed = self.expanded.remove(&self.cursor.id());
+                let parent = self.cursor.parent().and_then(ElementRef::wrap);
+                if let (false, Some(parent)) = (closed, parent) {
+                    self.cursor = parent;
+                }
+            }
+            Key::Tab => self.next_match(true),
+            Key::BackTab => self.next_match(false),
+            Key::Enter => {
+                self.selector = unique_selector(self.cursor);
+                self.update_matches();
+            }
+            Key::Backspace => {
+                self.selector.pop();
+                self.update_matches();
+            }
+            Key::Char(c) if !c.is_control() => {
+                self.selector.push(c);
+                self.update_matches();
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, term: &Term) -> std::io::Result<()> {
+        let (height, width) = term.size();
+        let (height, width) = (height as usize, width as usize);
+        let tree_height = height.saturating_sub(FOOTER).max(1);
+        let rows = self.rows();
+        let at = rows
+            .iter()
+            .position(|(element, _)| *element == self.cursor)
+            .unwrap_or(0);
+        if at < self.scroll {
+            self.scroll = at;
+        } else if at >= self.scroll + tree_height {
+            self.scroll = at + 1 - tree_height;
+        }
+        let matched: HashSet<_> = self.matches.iter().flatten().map(|m| m.id()).collect();
+
+        let mut lines = Vec::new();
+        for (element, depth) in rows.iter().skip(self.scroll).take(tree_height) {
+            let has_children = element.children().any(|child| child.value().is_element());
+            let toggle = match (has_children, self.expanded.contains(&element.id())) {
+                (false, _) => ' ',
+                (true, true) => '▾',
+                (true, false) => '▸',
+            };
+            let marker = if matched.contains(&element.id()) {
+                '●'
+            } else {
+                ' '
+            };
+            let (name, text) = label(*element);
+            let line = format!("{} {}{} {}", marker, "  ".repeat(*depth), toggle, name);
+            let line = match text.is_empty() {
+                true => line,
+                false => format!("{}  \"{}\"", line, text),
+            };
+            let line = truncate(&line, width.saturating_sub(1));
+            let line = match (*element == self.cursor, matched.contains(&element.id())) {
+                (true, _) => style(line).reverse().to_string(),
+                (false, true) => style(line).green().to_string(),
+                (false, false) => line,
+            };
+            lines.push(line);
+        }
+        lines.resize(tree_height, String::new());
+
+        lines.push("─".repeat(width.saturating_sub(1)));
+        let prompt = format!("selector> {}", self.selector);
+        lines.push(match self.matches {
+            Some(_) => prompt,
+            None => style(prompt).red().to_string(),
+        });
+        let summary = match &self.matches {
+            Some(matches) if matches.len() == 1 => "1 match".to_string(),
+            Some(matches) => format!("{} matches", matches.len()),
+            None => "invalid selector".to_string(),
+        };
+        lines.push(truncate(
+            &format!(
+                "{}   ↑↓ move  ←→ fold  tab next match  enter select node  esc done",
+                summary
+            ),
+            width.saturating_sub(1),
+        ));
+        if let Some(matches) = &self.matches {
+            let selector = Selector::parse(&self.selector).ok();
+            let previews = selector
+                .map(|selector| extract(self.document, &selector, None))
+                .unwrap_or_default();
+            let shown = match matches.len() > PREVIEWS {
+                true => PREVIEWS - 1,
+                false => PREVIEWS,
+            };
+            for preview in previews.iter().take(shown) {
+                let preview = preview.split_whitespace().collect::<Vec<_>>().join(" ");
+                let preview = truncate(&preview, width.saturating_sub(1));
+                lines.push(style(preview).dim().to_string());
+            }
+            if matches.len() > shown {
+                let more = format!("… {} more", matches.len() - shown);
+                lines.push(style(more).dim().to_string());
+            }
+        }
+
+        term.clear_screen()?;
+        term.write_str(&lines.join("\n"))
+    }
+}
+
+pub async fn run(client: &Client, args: ExploreArgs) -> Result<(), Error> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        return Err("explore needs a terminal".into());
+    }
+    let page = download(client, &args.url).await?;
+    let document = Html::parse_document(&page.body);
+    let mut explorer = Explorer::new(&document, args.selector.unwrap_or_default());
+
+    term.hide_cursor()?;
+    let result = (|| loop {
+        explorer.draw(&term)?;
+        let page = (term.size().0 as usize).saturating_sub(FOOTER).max(1);
+        if !explorer.key(term.read_key()?, page) {
+            return Ok::<_, std::io::Error>(());
+        }
+    })();
+    term.clear_screen()?;
+    term.show_cursor()?;
+    result?;
+
+    let selector = explorer.selector.trim();
+    if !selector.is_empty() {
+        clipboard::copy(selector);
+        println!("{}", selector);
+    }
+    Ok(())
+}
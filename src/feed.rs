@@ -0,0 +1,357 @@
+use clap::ArgEnum;
+use reqwest::{Client, Url};
+use scraper::Html;
+use std::{fs, path::PathBuf};
+
+use crate::{datetime, download, enclosure, json::Json, xml, Error, Format};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct FeedArgs {
+    /// the RSS, Atom or JSON Feed to read
+    #[clap(required_unless_present = "from-opml")]
+    url: Option<String>,
+
+    /// read every feed listed in an OPML file (or url) instead
+    #[clap(long, conflicts_with = "url")]
+    from_opml: Option<String>,
+
+    /// `jsonl` prints one json object per item
+    #[clap(short, long, arg_enum, default_value = "text")]
+    format: Format,
+
+    /// only print the first this many items
+    #[clap(short = 'n', long)]
+    limit: Option<usize>,
+
+    /// save audio, video and other enclosures of the items into this directory
+    #[clap(long)]
+    download_enclosures: Option<PathBuf>,
+
+    /// name for saved enclosures, from `{title}`, `{date}`, `{author}`, `{filename}`, `{stem}` and `{ext}`
+    #[clap(long, default_value = "{filename}", requires = "download-enclosures")]
+    enclosure_name: String,
+
+    /// which fields to print, separated by commas
+    #[clap(
+        long,
+        arg_enum,
+        use_value_delimiter = true,
+        default_value = "title,link,date"
+    )]
+    fields: Vec<Field>,
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Title,
+    Link,
+    Date,
+    Summary,
+    Author,
+    Id,
+    Enclosure,
+}
+
+/// one entry of a feed, whatever flavour it came in
+#[derive(Debug, Default)]
+pub struct Item {
+    pub title: Option<String>,
+    pub link: Option<String>,
+    /// as `2022-10-03T10:00:00+00:00`
+    pub date: Option<String>,
+    /// plain text, markup stripped
+    pub summary: Option<String>,
+    pub author: Option<String>,
+    pub id: Option<String>,
+    /// urls of attached audio, video or other media
+    pub enclosures: Vec<String>,
+}
+
+impl Item {
+    pub fn field(&self, field: Field) -> Option<&str> {
+        match field {
+            Field::Title => self.title.as_deref(),
+            Field::Link => self.link.as_deref(),
+            Field::Date => self.date.as_deref(),
+            Field::Summary => self.summary.as_deref(),
+            Field::Author => self.author.as_deref(),
+            Field::Id => self.id.as_deref(),
+            Field::Enclosure => self.enclosures.first().map(String::as_str),
+        }
+    }
+}
+
+fn field_name(field: Field) -> &'static str {
+    field
+        .to_possible_value()
+        .map_or("", |value| value.get_name())
+}
+
+/// collapses markup and whitespace down to readable text
+fn plain_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let text: String = fragment.root_element().text().collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn non_empty(text: Option<String>) -> Option<String> {
+    text.filter(|text| !text.is_empty())
+}
+
+fn attribute(element: &xml::Element, name: &str) -> Option<String> {
+    element
+        .attributes
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone())
+}
+
+fn rss_item(item: &xml::Element) -> Item {
+    let enclosures = item
+        .elements()
+        .filter(|e| e.local_name() == "enclosure" || e.name == "media:content")
+        .filter_map(|e| attribute(e, "url"))
+        .collect();
+    Item {
+        title: non_empty(item.child_text("title")),
+        link: non_empty(item.child_text("link")),
+        date: item
+            .child_text("pubDate")
+            .or_else(|| item.child_text("date"))
+            .map(|date| datetime::rfc2822(&date).unwrap_or(date)),
+        summary: non_empty(
+            item.child_text("description")
+                .or_else(|| item.child_text("encoded"))
+                .map(|summary| plain_text(&summary)),
+        ),
+        author: non_empty(
+            item.child_text("creator")
+                .or_else(|| item.child_text("author")),
+        ),
+        id: non_empty(item.child_text("guid")),
+        enclosures,
+    }
+}
+
+fn atom_entry(entry: &xml::Element) -> Item {
+    let links = || entry.elements().filter(|e| e.local_name() == "link");
+    let link = links()
+        .find(|link| attribute(link, "rel").is_none_or(|rel| rel == "alternate"))
+        .and_then(|link| attribute(link, "href"));
+    let enclosures = links()
+        .filter(|link| attribute(link, "rel").as_deref() == Some("enclosure"))
+        .filter_map(|link| attribute(link, "href"))
+        .collect();
+    Item {
+        title: non_empty(entry.child_text("title").map(|title| plain_text(&title))),
+        link,
+        date: non_empty(
+            entry
+                .child_text("published")
+                .or_else(|| entry.child_text("updated")),
+        ),
+        summary: non_empty(
+            entry
+                .child_text("summary")
+                .or_else(|| entry.child_text("content"))
+                .map(|summary| plain_text(&summary)),
+        ),
+        author: non_empty(
+            entry
+                .child("author")
+                .and_then(|author| author.child_text("name")),
+        ),
+        id: non_empty(entry.child_text("id")),
+        enclosures,
+    }
+}
+
+fn json_feed_item(item: &Json) -> Item {
+    let text = |key: &str| non_empty(item.get(key).and_then(Json::as_str).map(String::from));
+    let author = item
+        .get("authors")
+        .and_then(|authors| match authors {
+            Json::Array(authors) => authors.first(),
+            _ => None,
+        })
+        .or_else(|| item.get("author"))
+        .and_then(|author| author.get("name"))
+        .and_then(Json::as_str)
+        .map(String::from);
+    let enclosures = match item.get("attachments") {
+        Some(Json::Array(attachments)) => attachments
+            .iter()
+            .filter_map(|attachment| attachment.get("url").and_then(Json::as_str))
+            .map(String::from)
+            .collect(),
+        _ => vec![],
+    };
+    Item {
+        title: text("title"),
+        link: text("url").or_else(|| text("external_url")),
+        date: text("date_published").or_else(|| text("date_modified")),
+        summary: text("summary")
+            .or_else(|| text("content_text"))
+            .or_else(|| text("content_html").map(|html| plain_text(&html))),
+        author: non_empty(author),
+        id: text("id"),
+        enclosures,
+    }
+}
+
+/// the items of a JSON Feed (https://jsonfeed.org)
+fn parse_json_feed(body: &str) -> Result<Vec<Item>, String> {
+    let feed = Json::parse(body)?;
+    let is_json_feed = feed
+        .get("version")
+        .and_then(Json::as_str)
+        .is_some_and(|version| version.contains("jsonfeed.org"));
+    match feed.get("items") {
+        Some(Json::Array(items)) if is_json_feed => Ok(items.iter().map(json_feed_item).collect()),
+        _ => Err("not a JSON Feed".into()),
+    }
+}
+
+/// the items of an RSS 2.0, RSS 1.0 (RDF), Atom or JSON Feed
+pub fn parse(body: &str) -> Result<Vec<Item>, String> {
+    if body.trim_start().starts_with('{') {
+        return parse_json_feed(body);
+    }
+    let root = xml::parse(body)?;
+    match root.local_name() {
+        "rss" => Ok(root
+            .child("channel")
+            .into_iter()
+            .flat_map(|channel| channel.elements())
+            .filter(|e| e.local_name() == "item")
+            .map(rss_item)
+            .collect()),
+        "RDF" => Ok(root
+            .elements()
+            .filter(|e| e.local_name() == "item")
+            .map(rss_item)
+            .collect()),
+        "feed" => Ok(root
+            .elements()
+            .filter(|e| e.local_name() == "entry")
+            .map(atom_entry)
+            .collect()),
+        other => Err(format!("not a feed, found <{}>", other)),
+    }
+}
+
+/// prints the items, prefixed with the feed they came from in batch runs
+pub fn print(items: &[Item], format: Format, fields: &[Field], feed: Option<&str>) {
+    for item in items {
+        match format {
+            Format::Jsonl => {
+                let values = fields
+                    .iter()
+                    .map(|field| (field_name(*field), item.field(*field).into()));
+                let feed = feed.map(|feed| ("feed", feed.into()));
+                println!("{}", Json::object(feed.into_iter().chain(values)));
+            }
+            Format::Text => {
+                let values: Vec<_> = feed
+                    .map(String::from)
+                    .into_iter()
+                    .chain(fields.iter().map(|field| {
+                        item.field(*field)
+                            .unwrap_or_default()
+                            .replace(['\t', '\n'], " ")
+                    }))
+                    .collect();
+                println!("{}", values.join("\t"));
+            }
+        }
+    }
+}
+
+/// the feed urls of every outline in an OPML document, nested ones included
+pub fn opml_feeds(body: &str) -> Result<Vec<String>, String> {
+    fn outlines(element: &xml::Element, feeds: &mut Vec<String>) {
+        for outline in element.elements().filter(|e| e.local_name() == "outline") {
+            let url = outline
+                .attributes
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case("xmlUrl"));
+            if let Some((_, url)) = url {
+                feeds.push(url.clone());
+            }
+            outlines(outline, feeds);
+        }
+    }
+
+    let root = xml::parse(body)?;
+    if root.local_name() != "opml" {
+        return Err(format!("not an OPML file, found <{}>", root.local_name()));
+    }
+    let mut feeds = Vec::new();
+    if let Some(body) = root.child("body") {
+        outlines(body, &mut feeds);
+    }
+    Ok(feeds)
+}
+
+/// prints the first `--limit` items and saves their enclosures if asked to
+async fn handle(client: &Client, args: &FeedArgs, mut items: Vec<Item>, feed: Option<&str>) {
+    items.truncate(args.limit.unwrap_or(usize::MAX));
+    print(&items, args.format, &args.fields, feed);
+
+    let directory = match &args.download_enclosures {
+        Some(directory) => directory,
+        None => return,
+    };
+    if let Err(error) = fs::create_dir_all(directory) {
+        eprintln!("Failed to create '{}': {}", directory.display(), error);
+        return;
+    }
+    for item in &items {
+        for url in &item.enclosures {
+            let url = match Url::parse(url) {
+                Ok(url) => url,
+                Err(_) => {
+                    eprintln!("Invalid enclosure URL '{}'", url);
+                    continue;
+                }
+            };
+            let path = directory.join(enclosure::file_name(&args.enclosure_name, item, &url));
+            match enclosure::save(client, &url, &path).await {
+                Ok(true) => eprintln!("Saved {}", path.display()),
+                Ok(false) => eprintln!("Already have {}", path.display()),
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+    }
+}
+
+pub async fn run(client: &Client, args: FeedArgs) -> Result<(), Error> {
+    let opml = match &args.from_opml {
+        Some(opml) if opml.starts_with("http://") || opml.starts_with("https://") => {
+            download(client, opml).await?.body
+        }
+        Some(opml) => {
+            fs::read_to_string(opml).map_err(|e| format!("Failed to read '{}': {}", opml, e))?
+        }
+        None => {
+            let url = args.url.as_deref().unwrap_or_default();
+            let page = download(client, url).await?;
+            let items = parse(&page.body).map_err(|e| format!("{}: {}", page.url, e))?;
+            handle(client, &args, items, None).await;
+            return Ok(());
+        }
+    };
+
+    for feed in opml_feeds(&opml)? {
+        let items = match download(client, &feed).await {
+            Ok(page) if !page.status.is_success() => Err(page.status.to_string()),
+            Ok(page) => parse(&page.body),
+            Err(error) => Err(error.to_string()),
+        };
+        match items {
+            Ok(items) => handle(client, &args, items, Some(&feed)).await,
+            Err(error) => eprintln!("{}: {}", feed, error),
+        }
+    }
+    Ok(())
+}
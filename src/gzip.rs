@@ -0,0 +1,472 @@
+//! gzip for sitemaps and archives, which are often compressed
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// decompresses all gzip members in `data` into one buffer
+pub fn decompress(mut data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    while is_gzip(data) {
+        let (member, used) = decompress_member(data)?;
+        out.extend_from_slice(&member);
+        data = &data[used..];
+    }
+    if out.is_empty() && !data.is_empty() {
+        return Err("not gzip data".into());
+    }
+    Ok(out)
+}
+
+/// decompresses the first gzip member, returns it and how many bytes it took up
+pub fn decompress_member(data: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    const TRUNCATED: &str = "truncated gzip header";
+    if data.len() < 18 || !is_gzip(data) || data[2] != 8 {
+        return Err("not gzip data".into());
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & 0x04 != 0 {
+        let extra = u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+        pos += 2 + extra;
+    }
+    for flag in [0x08, 0x10] {
+        if flags & flag != 0 {
+            let end = data
+                .get(pos..)
+                .ok_or(TRUNCATED)?
+                .iter()
+                .position(|b| *b == 0);
+            pos += end.ok_or(TRUNCATED)? + 1;
+        }
+    }
+    if flags & 0x02 != 0 {
+        pos += 2;
+    }
+
+    let (out, used) = inflate(data.get(pos..).ok_or(TRUNCATED)?)?;
+    pos += used;
+    let trailer = data.get(pos..pos + 8).ok_or("truncated gzip trailer")?;
+    if u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != crc32(&out) {
+        return Err("gzip checksum mismatch".into());
+    }
+    Ok((out, pos + 8))
+}
+
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+}
+
+impl Bits<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self.data.get(self.pos).ok_or("truncated deflate stream")?;
+            value |= ((byte as u32 >> self.bit) & 1) << i;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.pos += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        if self.bit > 0 {
+            self.bit = 0;
+            self.pos += 1;
+        }
+    }
+}
+
+/// canonical huffman code, decoded bit by bit
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for length in 1..16 {
+            offsets[length] = offsets[length - 1] + counts[length - 1];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length > 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= bits.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid huffman code".into())
+    }
+}
+
+/// raw deflate, returns the data and how many input bytes were consumed
+pub fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    let mut bits = Bits {
+        data,
+        pos: 0,
+        bit: 0,
+    };
+    let mut out = Vec::new();
+
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.align();
+                let header = data
+                    .get(bits.pos..bits.pos + 4)
+                    .ok_or("truncated stored block")?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let start = bits.pos + 4;
+                let block = data
+                    .get(start..start + len)
+                    .ok_or("truncated stored block")?;
+                out.extend_from_slice(block);
+                bits.pos = start + len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(&mut bits, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut bits)?;
+                inflate_block(&mut bits, &mut out, &literals, &distances)?;
+            }
+            _ => return Err("invalid deflate block type".into()),
+        }
+        if last {
+            bits.align();
+            return Ok((out, bits.pos));
+        }
+    }
+}
+
+fn dynamic_tables(bits: &mut Bits) -> Result<(Huffman, Huffman), String> {
+    let literal_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for i in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*i] = bits.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths.last().ok_or("repeat without previous length")?,
+                3 + bits.bits(2)?,
+            ),
+            17 => (0, 3 + bits.bits(3)?),
+            _ => (0, 11 + bits.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("too many code lengths".into());
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), String> {
+    loop {
+        match literals.decode(bits)? {
+            symbol @ 0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            symbol => {
+                let index = (symbol - 257) as usize;
+                if index >= LENGTH_BASE.len() {
+                    return Err("invalid length symbol".into());
+                }
+                let length =
+                    LENGTH_BASE[index] as usize + bits.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                let index = distances.decode(bits)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err("invalid distance symbol".into());
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + bits.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+                if distance > out.len() {
+                    return Err("distance too far back".into());
+                }
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    /// writes the lowest `count` bits, least significant first
+    fn bits(&mut self, value: u32, count: u32) {
+        for i in 0..count {
+            self.bits |= ((value >> i) & 1) << self.count;
+            self.count += 1;
+            if self.count == 8 {
+                self.out.push(self.bits as u8);
+                self.bits = 0;
+                self.count = 0;
+            }
+        }
+    }
+
+    /// huffman codes go most significant bit first
+    fn code(&mut self, code: u32, length: u32) {
+        self.bits(code.reverse_bits() >> (32 - length), length);
+    }
+
+    fn literal(&mut self, symbol: u32) {
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+/// raw deflate using the fixed huffman code and a simple lz77 match search
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    const WINDOW: usize = 32 * 1024;
+    let mut writer = BitWriter {
+        out: Vec::with_capacity(data.len() / 2),
+        bits: 0,
+        count: 0,
+    };
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    let mut heads = vec![usize::MAX; 1 << 15];
+    let hash = |at: usize| {
+        let key = (data[at] as usize) << 16 | (data[at + 1] as usize) << 8 | data[at + 2] as usize;
+        (key.wrapping_mul(2_654_435_761) >> 7) & ((1 << 15) - 1)
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut length = 0;
+        let mut distance = 0;
+        if pos + 3 <= data.len() {
+            let h = hash(pos);
+            let candidate = heads[h];
+            heads[h] = pos;
+            if candidate != usize::MAX && pos - candidate <= WINDOW {
+                let max = (data.len() - pos).min(258);
+                while length < max && data[candidate + length] == data[pos + length] {
+                    length += 1;
+                }
+                distance = pos - candidate;
+            }
+        }
+
+        if length >= 3 {
+            let index = LENGTH_BASE
+                .iter()
+                .rposition(|base| *base as usize <= length)
+                .unwrap();
+            writer.literal(257 + index as u32);
+            writer.bits(
+                (length - LENGTH_BASE[index] as usize) as u32,
+                LENGTH_EXTRA[index] as u32,
+            );
+            let index = DISTANCE_BASE
+                .iter()
+                .rposition(|base| *base as usize <= distance)
+                .unwrap();
+            writer.code(index as u32, 5);
+            writer.bits(
+                (distance - DISTANCE_BASE[index] as usize) as u32,
+                DISTANCE_EXTRA[index] as u32,
+            );
+            for at in pos + 1..(pos + length).min(data.len().saturating_sub(2)) {
+                heads[hash(at)] = at;
+            }
+            pos += length;
+        } else {
+            writer.literal(data[pos] as u32);
+            pos += 1;
+        }
+    }
+
+    writer.literal(256);
+    writer.finish()
+}
+
+/// a complete gzip member
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+    out.extend_from_slice(&deflate(data));
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn crc32_known_answers() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn inflate_stored_block() {
+        let data = unhex("010c00f3ff68656c6c6f2c20776f726c64");
+        assert_eq!(
+            inflate(&data).unwrap(),
+            (b"hello, world".to_vec(), data.len())
+        );
+    }
+
+    #[test]
+    fn inflate_fixed_block() {
+        let data = unhex("cb48cdc9c957c8402701");
+        let expected = b"hello hello hello hello".to_vec();
+        assert_eq!(inflate(&data).unwrap(), (expected, data.len()));
+    }
+
+    #[test]
+    fn inflate_dynamic_block() {
+        let data = unhex(concat!(
+            "b5cbc91180201044d1543a027302655360d811a377ca1c3c76fdd7cd2ae4eef60bb2d08cd074e3ec21",
+            "55d050058db317cfc24166fbd63f3809766141329aae59683714a7474578973b15fe9afa02",
+        ));
+        let mut expected = b"the quick brown fox jumps over the lazy dog. ".repeat(3);
+        expected.extend_from_slice(b"pack my box with five dozen liquor jugs");
+        assert_eq!(inflate(&data).unwrap(), (expected, data.len()));
+    }
+
+    #[test]
+    fn gzip_with_file_name() {
+        let data = unhex("1f8b08080000000002ff612e786d6c00b3292dca294e2dd1b70300dbce72fc09000000");
+        assert_eq!(decompress(&data).unwrap(), b"<urlset/>");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut data = b"<url><loc>https://example.com/</loc></url>\n".repeat(500);
+        data.extend((0..=255).cycle().take(5000));
+        let compressed = compress(&data);
+        assert!(compressed.len() < data.len() / 2);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        assert_eq!(decompress(&compress(b"")).unwrap(), b"");
+    }
+
+    #[test]
+    fn concatenated_members() {
+        let mut data = compress(b"first ");
+        data.extend(compress(b"second"));
+        assert_eq!(decompress(&data).unwrap(), b"first second");
+    }
+
+    #[test]
+    fn rejects_corruption() {
+        let mut data = compress(b"checksummed");
+        let at = data.len() - 8;
+        data[at] ^= 1;
+        assert_eq!(decompress(&data).unwrap_err(), "gzip checksum mismatch");
+        assert!(decompress(b"plain text").is_err());
+    }
+}
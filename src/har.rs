@@ -0,0 +1,144 @@
+//! an HTTP Archive of the run's traffic, for browser devtools and HAR viewers
+
+use reqwest::{header::HeaderMap, Request, Response, Version};
+use std::{
+    fs, io,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    datetime,
+    json::Json,
+    timings::{millis, Timing},
+    Page,
+};
+
+/// every exchange of the run, written out at the end
+static ENTRIES: Mutex<Vec<Json>> = Mutex::new(Vec::new());
+
+fn headers(headers: &HeaderMap) -> Json {
+    let headers = headers.iter().map(|(name, value)| {
+        Json::object([
+            ("name", name.as_str().into()),
+            (
+                "value",
+                String::from_utf8_lossy(value.as_bytes()).as_ref().into(),
+            ),
+        ])
+    });
+    Json::Array(headers.collect())
+}
+
+fn version(version: Version) -> Json {
+    format!("{:?}", version).into()
+}
+
+/// HAR says -1 for phases that don't apply, like TLS on plain http
+fn phase(duration: Option<Duration>) -> f64 {
+    duration.map_or(-1.0, millis)
+}
+
+pub fn request(request: &Request) -> Json {
+    let query = request.url().query_pairs().map(|(name, value)| {
+        Json::object([
+            ("name", name.as_ref().into()),
+            ("value", value.as_ref().into()),
+        ])
+    });
+    Json::object([
+        ("method", request.method().as_str().into()),
+        ("url", request.url().as_str().into()),
+        ("httpVersion", version(request.version())),
+        ("cookies", Json::Array(vec![])),
+        ("headers", headers(request.headers())),
+        ("queryString", Json::Array(query.collect())),
+        ("headersSize", (-1.0).into()),
+        ("bodySize", 0u64.into()),
+    ])
+}
+
+/// the status line and headers of the final response, the content comes with `add`
+pub fn response(response: &Response) -> Json {
+    let status = response.status();
+    Json::object([
+        ("status", status.as_u16().into()),
+        ("statusText", status.canonical_reason().unwrap_or("").into()),
+        ("httpVersion", version(response.version())),
+        ("cookies", Json::Array(vec![])),
+        ("headers", headers(response.headers())),
+        // reqwest followed any redirects already
+        ("redirectURL", "".into()),
+        ("headersSize", (-1.0).into()),
+    ])
+}
+
+pub fn add(started: SystemTime, request: Json, response: Json, page: &Page, timing: &Timing) {
+    let content_type = page
+        .headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let content = Json::object([
+        ("size", page.size.into()),
+        ("mimeType", content_type.unwrap_or("").into()),
+        ("text", page.body.as_str().into()),
+    ]);
+    let response = match response {
+        Json::Object(mut fields) => {
+            fields.push(("content".to_string(), content));
+            fields.push(("bodySize".to_string(), page.size.into()));
+            Json::Object(fields)
+        }
+        response => response,
+    };
+    let wait = timing.ttfb;
+    let receive = timing.total.saturating_sub(timing.ttfb);
+    let phases = [
+        timing.dns,
+        timing.connect,
+        timing.tls,
+        Some(wait),
+        Some(receive),
+    ];
+    let time: f64 = phases.iter().flatten().map(|d| millis(*d)).sum();
+    let entry = Json::object([
+        ("startedDateTime", datetime::rfc3339(started).into()),
+        ("time", ((time * 1e3).round() / 1e3).into()),
+        ("request", request),
+        ("response", response),
+        ("cache", Json::object([])),
+        (
+            "timings",
+            Json::object([
+                ("blocked", (-1.0).into()),
+                ("dns", phase(timing.dns).into()),
+                ("connect", phase(timing.connect).into()),
+                ("ssl", phase(timing.tls).into()),
+                ("send", 0.0.into()),
+                ("wait", millis(wait).into()),
+                ("receive", millis(receive).into()),
+            ]),
+        ),
+    ]);
+    ENTRIES.lock().unwrap().push(entry);
+}
+
+pub fn write(path: &Path) -> io::Result<()> {
+    let entries = std::mem::take(&mut *ENTRIES.lock().unwrap());
+    let har = Json::object([(
+        "log",
+        Json::object([
+            ("version", "1.2".into()),
+            (
+                "creator",
+                Json::object([
+                    ("name", env!("CARGO_PKG_NAME").into()),
+                    ("version", env!("CARGO_PKG_VERSION").into()),
+                ]),
+            ),
+            ("entries", Json::Array(entries)),
+        ]),
+    )]);
+    fs::write(path, har.to_string() + "\n")
+}
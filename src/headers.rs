@@ -0,0 +1,74 @@
+//! the response headers `--headers` prints, for people or for scripts
+
+use clap::ArgEnum;
+use reqwest::header::HeaderMap;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use crate::json::Json;
+
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub enum HeadersFormat {
+    /// `name: value` lines and an empty line after every response, like `curl -D`
+    Plain,
+    /// one json object per response, with lowercase names and a list for repeated headers
+    Json,
+}
+
+/// the --headers-file, created on the first response
+static FILE: Mutex<Option<File>> = Mutex::new(None);
+
+pub fn to_json(headers: &HeaderMap) -> Json {
+    let mut fields: Vec<(String, Json)> = Vec::new();
+    for (name, value) in headers {
+        let value = Json::from(String::from_utf8_lossy(value.as_bytes()).into_owned());
+        match fields.iter_mut().find(|(key, _)| key == name.as_str()) {
+            Some((_, Json::Array(values))) => values.push(value),
+            Some((_, existing)) => {
+                let first = std::mem::replace(existing, Json::Null);
+                *existing = Json::Array(vec![first, value]);
+            }
+            None => fields.push((name.as_str().to_string(), value)),
+        }
+    }
+    Json::Object(fields)
+}
+
+fn format(headers: &HeaderMap, format: HeadersFormat) -> String {
+    match format {
+        HeadersFormat::Plain => {
+            let mut text = String::new();
+            for (name, value) in headers {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                text.push_str(&format!("{}: {}\n", name, value));
+            }
+            text + "\n"
+        }
+        HeadersFormat::Json => format!("{}\n", to_json(headers)),
+    }
+}
+
+/// writes the headers of a response to stdout, or to the file
+pub fn print(
+    headers: &HeaderMap,
+    headers_format: HeadersFormat,
+    path: Option<&Path>,
+) -> io::Result<()> {
+    let text = format(headers, headers_format);
+    let path = match path {
+        Some(path) => path,
+        None => {
+            print!("{}", text);
+            return Ok(());
+        }
+    };
+    let mut file = FILE.lock().unwrap();
+    if file.is_none() {
+        *file = Some(File::create(path)?);
+    }
+    file.as_mut().unwrap().write_all(text.as_bytes())
+}
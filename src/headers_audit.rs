@@ -0,0 +1,222 @@
+use reqwest::{header::HeaderMap, Client};
+
+use crate::{download, json::Json, Error, Format};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct HeadersAuditArgs {
+    /// the page whose response headers to check
+    url: String,
+
+    /// `jsonl` prints the report as a single json object
+    #[clap(short, long, arg_enum, default_value = "text")]
+    format: Format,
+
+    /// exit with status 1 if the grade is worse than this, e.g. `B`
+    #[clap(long)]
+    min_grade: Option<char>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Outcome {
+    Pass,
+    Partial,
+    Fail,
+    /// like HSTS over plain http
+    Skipped,
+}
+
+struct Check {
+    header: &'static str,
+    outcome: Outcome,
+    note: String,
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn check(headers: &HeaderMap, https: bool) -> Vec<Check> {
+    let csp = header(headers, "content-security-policy");
+    let mut checks = Vec::new();
+    let mut add = |header, outcome, note: &str| {
+        checks.push(Check {
+            header,
+            outcome,
+            note: note.to_string(),
+        })
+    };
+
+    match csp {
+        None => add("content-security-policy", Outcome::Fail, "missing"),
+        Some(policy) if policy.contains("'unsafe-inline'") || policy.contains("'unsafe-eval'") => {
+            add(
+                "content-security-policy",
+                Outcome::Partial,
+                "allows 'unsafe-inline' or 'unsafe-eval'",
+            )
+        }
+        Some(_) => add("content-security-policy", Outcome::Pass, "present"),
+    }
+
+    let max_age = header(headers, "strict-transport-security").map(|hsts| {
+        hsts.split(';')
+            .find_map(|directive| directive.trim().strip_prefix("max-age="))
+            .and_then(|age| age.trim_matches('"').parse::<u64>().ok())
+            .unwrap_or(0)
+    });
+    match (https, max_age) {
+        (false, _) => add(
+            "strict-transport-security",
+            Outcome::Skipped,
+            "not served over https",
+        ),
+        (true, None) => add("strict-transport-security", Outcome::Fail, "missing"),
+        (true, Some(age)) if age < 15_552_000 => add(
+            "strict-transport-security",
+            Outcome::Partial,
+            "max-age below 180 days",
+        ),
+        (true, Some(_)) => add("strict-transport-security", Outcome::Pass, "present"),
+    }
+
+    let frame_ancestors = csp.is_some_and(|policy| policy.contains("frame-ancestors"));
+    match header(headers, "x-frame-options").map(str::to_ascii_uppercase) {
+        Some(value) if value == "DENY" || value == "SAMEORIGIN" => {
+            add("x-frame-options", Outcome::Pass, &value)
+        }
+        _ if frame_ancestors => add(
+            "x-frame-options",
+            Outcome::Pass,
+            "covered by CSP frame-ancestors",
+        ),
+        Some(value) => add(
+            "x-frame-options",
+            Outcome::Partial,
+            &format!("unusual value {}", value),
+        ),
+        None => add("x-frame-options", Outcome::Fail, "missing"),
+    }
+
+    match header(headers, "x-content-type-options") {
+        Some(value) if value.trim().eq_ignore_ascii_case("nosniff") => {
+            add("x-content-type-options", Outcome::Pass, "nosniff")
+        }
+        Some(value) => add(
+            "x-content-type-options",
+            Outcome::Fail,
+            &format!("unknown value {}", value),
+        ),
+        None => add("x-content-type-options", Outcome::Fail, "missing"),
+    }
+
+    match header(headers, "referrer-policy").map(str::to_ascii_lowercase) {
+        None => add("referrer-policy", Outcome::Fail, "missing"),
+        Some(policy)
+            if policy.contains("unsafe-url") || policy.contains("no-referrer-when-downgrade") =>
+        {
+            add(
+                "referrer-policy",
+                Outcome::Partial,
+                &format!("leaky policy {}", policy),
+            )
+        }
+        Some(policy) => add("referrer-policy", Outcome::Pass, &policy),
+    }
+    checks
+}
+
+fn grade(checks: &[Check]) -> char {
+    let scored: Vec<_> = checks
+        .iter()
+        .filter(|c| c.outcome != Outcome::Skipped)
+        .collect();
+    let points: usize = scored
+        .iter()
+        .map(|c| match c.outcome {
+            Outcome::Pass => 2,
+            Outcome::Partial => 1,
+            _ => 0,
+        })
+        .sum();
+    match points * 100 / (scored.len() * 2).max(1) {
+        90.. => 'A',
+        75..=89 => 'B',
+        60..=74 => 'C',
+        40..=59 => 'D',
+        _ => 'F',
+    }
+}
+
+/// headers that give away more about the server than needed
+fn leaks(headers: &HeaderMap) -> Vec<String> {
+    let mut leaks = Vec::new();
+    if let Some(server) =
+        header(headers, "server").filter(|s| s.chars().any(|c| c.is_ascii_digit()))
+    {
+        leaks.push(format!("server reveals its version: {}", server));
+    }
+    for name in ["x-powered-by", "x-aspnet-version", "x-aspnetmvc-version"] {
+        if let Some(value) = header(headers, name) {
+            leaks.push(format!("{} reveals {}", name, value));
+        }
+    }
+    leaks
+}
+
+pub async fn run(client: &Client, args: HeadersAuditArgs) -> Result<(), Error> {
+    let page = download(client, &args.url).await?;
+    let checks = check(&page.headers, page.url.scheme() == "https");
+    let grade = grade(&checks);
+    let leaks = leaks(&page.headers);
+
+    match args.format {
+        Format::Jsonl => {
+            let checks: Vec<_> = checks
+                .iter()
+                .map(|c| {
+                    let outcome = match c.outcome {
+                        Outcome::Pass => "pass",
+                        Outcome::Partial => "partial",
+                        Outcome::Fail => "fail",
+                        Outcome::Skipped => "skipped",
+                    };
+                    Json::object([
+                        ("header", c.header.into()),
+                        ("outcome", outcome.into()),
+                        ("note", c.note.as_str().into()),
+                    ])
+                })
+                .collect();
+            let report = Json::object([
+                ("url", page.url.as_str().into()),
+                ("grade", grade.to_string().into()),
+                ("checks", checks.into()),
+                ("leaks", leaks.into()),
+            ]);
+            println!("{}", report);
+        }
+        Format::Text => {
+            println!("{}  grade {}", page.url, grade);
+            for c in &checks {
+                let mark = match c.outcome {
+                    Outcome::Pass => "ok  ",
+                    Outcome::Partial => "weak",
+                    Outcome::Fail => "FAIL",
+                    Outcome::Skipped => "n/a ",
+                };
+                println!("  {} {:<26} {}", mark, c.header, c.note);
+            }
+            for leak in &leaks {
+                println!("  note {}", leak);
+            }
+        }
+    }
+
+    // grades sort alphabetically, so a later letter is a worse grade
+    if let Some(min_grade) = args.min_grade {
+        if grade > min_grade.to_ascii_uppercase() {
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
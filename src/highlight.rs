@@ -0,0 +1,117 @@
+//! the whole document with the matches of a selector colored in
+
+use console::style;
+use html5ever::{
+    serialize::{AttrRef, HtmlSerializer, Serialize, SerializeOpts, Serializer, TraversalScope},
+    QualName,
+};
+use scraper::{ElementRef, Html, Selector};
+use std::{collections::HashSet, io};
+
+const START: char = '\u{2}';
+const END: char = '\u{3}';
+
+/// serializes like html5ever, putting START and END around the matched elements
+struct Marking {
+    inner: HtmlSerializer<Vec<u8>>,
+    /// positions of the matched elements in document order
+    matched: HashSet<usize>,
+    /// elements started so far
+    count: usize,
+    /// for every open element, whether it is a match that opened a highlight
+    stack: Vec<bool>,
+}
+
+impl Serializer for Marking {
+    fn start_elem<'a, AttrIter>(&mut self, name: QualName, attrs: AttrIter) -> io::Result<()>
+    where
+        AttrIter: Iterator<Item = AttrRef<'a>>,
+    {
+        // matches inside a match are already highlighted
+        let opens = self.matched.contains(&self.count) && !self.stack.contains(&true);
+        if opens {
+            self.inner.writer.push(START as u8);
+        }
+        self.stack.push(opens);
+        self.count += 1;
+        self.inner.start_elem(name, attrs)
+    }
+
+    fn end_elem(&mut self, name: QualName) -> io::Result<()> {
+        self.inner.end_elem(name)?;
+        if self.stack.pop() == Some(true) {
+            self.inner.writer.push(END as u8);
+        }
+        Ok(())
+    }
+
+    fn write_text(&mut self, text: &str) -> io::Result<()> {
+        self.inner.write_text(text)
+    }
+
+    fn write_comment(&mut self, text: &str) -> io::Result<()> {
+        self.inner.write_comment(text)
+    }
+
+    fn write_doctype(&mut self, name: &str) -> io::Result<()> {
+        self.inner.write_doctype(name)
+    }
+
+    fn write_processing_instruction(&mut self, target: &str, data: &str) -> io::Result<()> {
+        self.inner.write_processing_instruction(target, data)
+    }
+}
+
+/// the document's html with its matches highlighted line by line, and how many matches there are
+pub fn render(document: &Html, selector: &Selector, line_numbers: bool) -> (String, usize) {
+    let root = document.root_element();
+    let matches: HashSet<_> = document.select(selector).map(|m| m.id()).collect();
+    let matched = root
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .enumerate()
+        .filter(|(_, element)| matches.contains(&element.id()))
+        .map(|(index, _)| index)
+        .collect();
+    let opts = SerializeOpts {
+        traversal_scope: TraversalScope::IncludeNode,
+        ..SerializeOpts::default()
+    };
+    let mut marking = Marking {
+        inner: HtmlSerializer::new(Vec::new(), opts),
+        matched,
+        count: 0,
+        stack: Vec::new(),
+    };
+    root.serialize(&mut marking, TraversalScope::IncludeNode)
+        .expect("writing to memory");
+    let html = String::from_utf8_lossy(&marking.inner.writer).into_owned();
+
+    let lines: Vec<_> = html.lines().collect();
+    let width = lines.len().to_string().len();
+    let mut highlighted = false;
+    let mut output = Vec::new();
+    for (number, line) in lines.iter().enumerate() {
+        // styled a piece at a time so that every line resets its colors
+        let mut rendered = String::new();
+        for (index, piece) in line.split([START, END]).enumerate() {
+            if index > 0 {
+                highlighted = !highlighted;
+            }
+            match highlighted {
+                _ if piece.is_empty() => {}
+                true => rendered.push_str(&style(piece).black().on_yellow().to_string()),
+                false => rendered.push_str(piece),
+            }
+        }
+        output.push(match line_numbers {
+            true => format!(
+                "{}  {}",
+                style(format!("{:>width$}", number + 1)).dim(),
+                rendered
+            ),
+            false => rendered,
+        });
+    }
+    (output.join("\n"), matches.len())
+}
@@ -0,0 +1,78 @@
+//! the command lines of successful runs, to look them up and run them again
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    process,
+};
+
+use crate::{curl, datetime, dirs, Error};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct RerunArgs {
+    /// the number `scrape history` lists the command with, the last one if left out
+    id: Option<usize>,
+}
+
+fn path() -> io::Result<PathBuf> {
+    let dir = dirs::data_dir().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "no home directory for the history")
+    })?;
+    Ok(dir.join("history"))
+}
+
+/// adds the arguments scrape ran with, quoted like in a shell
+pub fn record(args: &[OsString]) -> io::Result<()> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let command: Vec<_> = args
+        .iter()
+        .map(|arg| curl::quote(&arg.to_string_lossy()))
+        .collect();
+    let mut history = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(history, "{}\t{}", datetime::now(), command.join(" "))
+}
+
+/// when each command ran and its arguments, oldest first
+fn load() -> Vec<(String, String)> {
+    let history = match path() {
+        Ok(path) => fs::read_to_string(path).unwrap_or_default(),
+        Err(_) => return vec![],
+    };
+    history
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(time, command)| (time.to_string(), command.to_string()))
+        .collect()
+}
+
+pub fn list() -> Result<(), Error> {
+    for (id, (time, command)) in load().iter().enumerate() {
+        println!("{}\t{}\tscrape {}", id + 1, time, command);
+    }
+    Ok(())
+}
+
+pub fn rerun(args: RerunArgs) -> Result<(), Error> {
+    let history = load();
+    let (_, command) = match args.id {
+        Some(id) => id
+            .checked_sub(1)
+            .and_then(|index| history.get(index))
+            .ok_or_else(|| format!("No command {} in the history", id))?,
+        None => history.last().ok_or("No commands in the history yet")?,
+    };
+    eprintln!("scrape {}", command);
+    let status = process::Command::new(env::current_exe()?)
+        .args(curl::split(command)?)
+        .status()?;
+    if !status.success() {
+        process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+//! Ctrl-C in runs that can stop early: the first one stops new requests so the run ends with the
+//! results it has, a second one quits right away
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+use crate::log;
+
+/// the exit status of an interrupted run, like shells report a SIGINT
+pub const EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static NOTIFY: Notify = Notify::const_new();
+
+/// handles Ctrl-C from now on instead of dying with it
+pub fn listen() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if INTERRUPTED.swap(true, Ordering::SeqCst) {
+                std::process::exit(EXIT_CODE);
+            }
+            log::warn("Interrupted, finishing with the results so far, Ctrl-C again to quit");
+            NOTIFY.notify_waiters();
+        }
+    });
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// waits for Ctrl-C, to cut short sleeping between requests
+pub async fn wait() {
+    let notified = NOTIFY.notified();
+    if !interrupted() {
+        notified.await;
+    }
+}
@@ -0,0 +1,786 @@
+//! a jq-like filter language for json responses, covering paths, iteration, pipes, array and
+//! object construction, `keys`, `length`, `select`, `map`, `add`, `not`, `+`, `//` and a few string
+//! functions, other jq syntax is rejected rather than misread
+
+use regex::Regex;
+use std::str::FromStr;
+
+use crate::json::Json;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Identity,
+    Key(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Iterate,
+    Pipe(Box<Filter>, Box<Filter>),
+    Comma(Box<Filter>, Box<Filter>),
+    Keys,
+    Length,
+    Select(Box<Filter>),
+    Compare(Box<Filter>, Comparison, Box<Filter>),
+    Literal(Json),
+    Add(Box<Filter>, Box<Filter>),
+    /// `a // b`, the truthy outputs of a or else those of b
+    Alternative(Box<Filter>, Box<Filter>),
+    Not,
+    ToString,
+    Downcase,
+    Upcase,
+    Trim,
+    Split(Box<Filter>),
+    Join(Box<Filter>),
+    Test(Box<Filter>),
+    /// the regex, the replacement and whether to replace all matches
+    Sub(Box<Filter>, Box<Filter>, bool),
+    /// `[f]`, all outputs of f collected into an array
+    Array(Option<Box<Filter>>),
+    /// `{key: f}`, an object for every combination of the values' outputs
+    Object(Vec<(String, Filter)>),
+    /// `add`, the elements of an array added together
+    Sum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            input: filter,
+            at: 0,
+        };
+        let parsed = parser.pipe()?;
+        parser.whitespace();
+        if parser.at < filter.len() {
+            return Err(unexpected(&filter[parser.at..]));
+        }
+        Ok(parsed)
+    }
+}
+
+/// jq syntax this subset doesn't have
+const UNSUPPORTED: [(&str, &str); 17] = [
+    ("..", "recursive descent '..'"),
+    ("?", "the '?' operator"),
+    ("$", "variables"),
+    ("as ", "variables"),
+    ("-", "'-'"),
+    ("*", "'*'"),
+    ("/", "'/'"),
+    ("%", "'%'"),
+    ("and", "'and'"),
+    ("or", "'or'"),
+    ("if", "'if'"),
+    ("try", "'try'"),
+    ("reduce", "'reduce'"),
+    ("foreach", "'foreach'"),
+    ("def", "function definitions"),
+    ("@", "formats like '@csv'"),
+    ("\\(", "string interpolation"),
+];
+
+/// the error for what's left of a filter that couldn't be parsed
+fn unexpected(rest: &str) -> String {
+    let rest = rest.trim_start();
+    let unsupported = UNSUPPORTED
+        .iter()
+        .find(|(start, _)| match start.as_bytes()[0] {
+            // words, not the start of a longer name
+            b'a'..=b'z' => rest
+                .strip_prefix(start)
+                .is_some_and(|after| !after.starts_with(|c: char| c.is_alphanumeric() || c == '_')),
+            _ => rest.starts_with(start),
+        });
+    match unsupported {
+        Some((_, construct)) => format!("filters don't support {}", construct),
+        None => format!("unexpected '{}' in filter", rest),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    at: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.at..]
+    }
+
+    fn whitespace(&mut self) {
+        self.at = self.input.len() - self.rest().trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.whitespace();
+        if self.rest().starts_with(token) {
+            self.at += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Filter, String> {
+        let mut filter = self.comma()?;
+        while self.eat("|") {
+            filter = Filter::Pipe(Box::new(filter), Box::new(self.comma()?));
+        }
+        Ok(filter)
+    }
+
+    fn comma(&mut self) -> Result<Filter, String> {
+        let mut filter = self.alternative()?;
+        while self.eat(",") {
+            filter = Filter::Comma(Box::new(filter), Box::new(self.alternative()?));
+        }
+        Ok(filter)
+    }
+
+    fn alternative(&mut self) -> Result<Filter, String> {
+        let mut filter = self.comparison()?;
+        while self.eat("//") {
+            filter = Filter::Alternative(Box::new(filter), Box::new(self.comparison()?));
+        }
+        Ok(filter)
+    }
+
+    fn comparison(&mut self) -> Result<Filter, String> {
+        let left = self.addition()?;
+        let operators = [
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        for (token, comparison) in operators {
+            if self.eat(token) {
+                let right = self.addition()?;
+                return Ok(Filter::Compare(Box::new(left), comparison, Box::new(right)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn addition(&mut self) -> Result<Filter, String> {
+        let mut filter = self.term()?;
+        while self.eat("+") {
+            filter = Filter::Add(Box::new(filter), Box::new(self.term()?));
+        }
+        Ok(filter)
+    }
+
+    /// the `(a; b)` after a function name
+    fn arguments(&mut self, function: &str, count: usize) -> Result<Vec<Filter>, String> {
+        if !self.eat("(") {
+            return Err(format!("expected '(' after {}", function));
+        }
+        let mut arguments = vec![self.pipe()?];
+        while arguments.len() < count && self.eat(";") {
+            arguments.push(self.pipe()?);
+        }
+        if arguments.len() < count || !self.eat(")") {
+            return Err(format!("{} takes {} arguments", function, count));
+        }
+        Ok(arguments)
+    }
+
+    fn identifier(&mut self) -> String {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .unwrap_or(rest.len());
+        self.at += end;
+        rest[..end].to_string()
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let rest = self.rest();
+        let mut escaped = false;
+        let end = rest[1..]
+            .find(|c| {
+                let end = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                end
+            })
+            .ok_or("unterminated string in filter")?;
+        let literal = &rest[..end + 2];
+        if literal.contains("\\(") {
+            return Err(unexpected("\\("));
+        }
+        self.at += end + 2;
+        match Json::parse(literal)? {
+            Json::String(s) => Ok(s),
+            _ => Err("invalid string in filter".into()),
+        }
+    }
+
+    fn term(&mut self) -> Result<Filter, String> {
+        self.whitespace();
+        let rest = self.rest();
+        let mut filter = if rest.starts_with("..") {
+            return Err(unexpected(rest));
+        } else if rest.starts_with('.') {
+            self.at += 1;
+            match self.rest().chars().next() {
+                Some(c) if c.is_alphabetic() || c == '_' => Filter::Key(self.identifier()),
+                Some('"') => Filter::Key(self.string()?),
+                _ => Filter::Identity,
+            }
+        } else if rest.starts_with('(') {
+            self.at += 1;
+            let inner = self.pipe()?;
+            if !self.eat(")") {
+                return Err("missing ')' in filter".into());
+            }
+            inner
+        } else if rest.starts_with('"') {
+            Filter::Literal(Json::String(self.string()?))
+        } else if rest.starts_with('[') {
+            self.at += 1;
+            if self.eat("]") {
+                Filter::Array(None)
+            } else {
+                let inner = self.pipe()?;
+                if !self.eat("]") {
+                    return Err("missing ']' in filter".into());
+                }
+                Filter::Array(Some(Box::new(inner)))
+            }
+        } else if rest.starts_with('{') {
+            self.at += 1;
+            self.object()?
+        } else if rest.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            let end = rest[1..]
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .map_or(rest.len(), |end| end + 1);
+            let number = rest[..end]
+                .parse()
+                .map_err(|_| "invalid number in filter")?;
+            self.at += end;
+            Filter::Literal(Json::Number(number))
+        } else {
+            match self.identifier().as_str() {
+                "keys" => Filter::Keys,
+                "length" => Filter::Length,
+                "true" => Filter::Literal(Json::Bool(true)),
+                "false" => Filter::Literal(Json::Bool(false)),
+                "null" => Filter::Literal(Json::Null),
+                "select" => {
+                    if !self.eat("(") {
+                        return Err("expected '(' after select".into());
+                    }
+                    let condition = self.pipe()?;
+                    if !self.eat(")") {
+                        return Err("missing ')' in select".into());
+                    }
+                    Filter::Select(Box::new(condition))
+                }
+                "not" => Filter::Not,
+                "add" => Filter::Sum,
+                "map" => {
+                    let each = self.arguments("map", 1)?.remove(0);
+                    let each = Filter::Pipe(Box::new(Filter::Iterate), Box::new(each));
+                    Filter::Array(Some(Box::new(each)))
+                }
+                "tostring" => Filter::ToString,
+                "ascii_downcase" => Filter::Downcase,
+                "ascii_upcase" => Filter::Upcase,
+                "trim" => Filter::Trim,
+                function @ ("split" | "join" | "test") => {
+                    let argument = Box::new(self.arguments(function, 1)?.remove(0));
+                    match function {
+                        "split" => Filter::Split(argument),
+                        "join" => Filter::Join(argument),
+                        _ => Filter::Test(argument),
+                    }
+                }
+                function @ ("sub" | "gsub") => {
+                    let mut arguments = self.arguments(function, 2)?.into_iter().map(Box::new);
+                    let (regex, replacement) =
+                        (arguments.next().unwrap(), arguments.next().unwrap());
+                    Filter::Sub(regex, replacement, function == "gsub")
+                }
+                "" => return Err(unexpected(rest)),
+                other if other.starts_with('$') || UNSUPPORTED.iter().any(|(w, _)| w == &other) => {
+                    return Err(unexpected(rest))
+                }
+                other => return Err(format!("unknown function '{}'", other)),
+            }
+        };
+
+        loop {
+            let suffix = if self.rest().starts_with('[') {
+                self.at += 1;
+                self.brackets()?
+            } else if self.rest().starts_with('.') && self.rest().len() > 1 {
+                self.at += 1;
+                match self.rest().chars().next() {
+                    Some('"') => Filter::Key(self.string()?),
+                    Some('[') => continue,
+                    _ => Filter::Key(self.identifier()),
+                }
+            } else {
+                return Ok(filter);
+            };
+            filter = match filter {
+                Filter::Identity => suffix,
+                filter => Filter::Pipe(Box::new(filter), Box::new(suffix)),
+            };
+        }
+    }
+
+    /// inside `{...}`: `key: f`, `"key": f` or `key` for `key: .key`, separated by commas
+    fn object(&mut self) -> Result<Filter, String> {
+        let mut fields = Vec::new();
+        if self.eat("}") {
+            return Ok(Filter::Object(fields));
+        }
+        loop {
+            self.whitespace();
+            let key = match self.rest().chars().next() {
+                Some('"') => self.string()?,
+                Some(c) if c.is_alphabetic() || c == '_' => self.identifier(),
+                Some('(') => return Err("filters don't support computed object keys".into()),
+                _ => return Err(unexpected(self.rest())),
+            };
+            let value = match self.eat(":") {
+                true => self.alternative()?,
+                false => Filter::Key(key.clone()),
+            };
+            fields.push((key, value));
+            if self.eat("}") {
+                return Ok(Filter::Object(fields));
+            }
+            if !self.eat(",") {
+                return Err("expected ',' or '}' in object".into());
+            }
+        }
+    }
+
+    /// inside `[...]`: nothing, an index, a slice or a quoted key
+    fn brackets(&mut self) -> Result<Filter, String> {
+        self.whitespace();
+        if self.eat("]") {
+            return Ok(Filter::Iterate);
+        }
+        if self.rest().starts_with('"') {
+            let key = self.string()?;
+            return match self.eat("]") {
+                true => Ok(Filter::Key(key)),
+                false => Err("missing ']' in filter".into()),
+            };
+        }
+        let end = self.rest().find(']').ok_or("missing ']' in filter")?;
+        let inside = self.rest()[..end].trim().to_string();
+        self.at += end + 1;
+        let number = |n: &str| -> Result<Option<i64>, String> {
+            match n.trim() {
+                "" => Ok(None),
+                n => n
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("invalid index '{}'", n)),
+            }
+        };
+        match inside.split_once(':') {
+            Some((from, to)) => Ok(Filter::Slice(number(from)?, number(to)?)),
+            None => Ok(Filter::Index(number(&inside)?.unwrap_or(0))),
+        }
+    }
+}
+
+fn truthy(value: &Json) -> bool {
+    !matches!(value, Json::Null | Json::Bool(false))
+}
+
+fn compare(left: &Json, comparison: Comparison, right: &Json) -> bool {
+    let ordering = match (left, right) {
+        (Json::Number(a), Json::Number(b)) => a.partial_cmp(b),
+        (Json::String(a), Json::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    match comparison {
+        Comparison::Equal => left == right,
+        Comparison::NotEqual => left != right,
+        Comparison::Less => ordering == Some(std::cmp::Ordering::Less),
+        Comparison::LessOrEqual => ordering.is_some_and(|o| o.is_le()),
+        Comparison::Greater => ordering == Some(std::cmp::Ordering::Greater),
+        Comparison::GreaterOrEqual => ordering.is_some_and(|o| o.is_ge()),
+    }
+}
+
+fn add(left: &Json, right: &Json) -> Result<Json, String> {
+    Ok(match (left, right) {
+        (Json::Null, value) | (value, Json::Null) => value.clone(),
+        (Json::Number(a), Json::Number(b)) => Json::Number(a + b),
+        (Json::String(a), Json::String(b)) => Json::String(format!("{}{}", a, b)),
+        (Json::Array(a), Json::Array(b)) => Json::Array(a.iter().chain(b).cloned().collect()),
+        (Json::Object(a), Json::Object(b)) => {
+            let mut fields = a.clone();
+            for (key, value) in b {
+                match fields.iter_mut().find(|(k, _)| k == key) {
+                    Some((_, old)) => *old = value.clone(),
+                    None => fields.push((key.clone(), value.clone())),
+                }
+            }
+            Json::Object(fields)
+        }
+        _ => return Err(format!("cannot add {} and {}", kind(left), kind(right))),
+    })
+}
+
+fn string<'a>(value: &'a Json, function: &str) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("{} needs a string, not {}", function, kind(value)))
+}
+
+fn regex(value: &Json) -> Result<Regex, String> {
+    let pattern = string(value, "a regex")?;
+    Regex::new(pattern).map_err(|_| format!("Invalid regex '{}'", pattern))
+}
+
+fn index(len: usize, i: i64) -> usize {
+    if i < 0 {
+        len.saturating_sub(i.unsigned_abs() as usize)
+    } else {
+        (i as usize).min(len)
+    }
+}
+
+impl Filter {
+    /// every output the filter produces for `input`
+    pub fn apply(&self, input: &Json) -> Result<Vec<Json>, String> {
+        Ok(match self {
+            Filter::Identity => vec![input.clone()],
+            Filter::Literal(value) => vec![value.clone()],
+            Filter::Key(key) => match input {
+                Json::Object(_) => vec![input.get(key).cloned().unwrap_or(Json::Null)],
+                Json::Null => vec![Json::Null],
+                _ => return Err(format!("cannot index {} with \"{}\"", kind(input), key)),
+            },
+            Filter::Index(i) => match input {
+                Json::Array(values) => {
+                    let at = if *i < 0 { values.len() as i64 + i } else { *i };
+                    vec![values.get(at as usize).cloned().unwrap_or(Json::Null)]
+                }
+                Json::Null => vec![Json::Null],
+                _ => return Err(format!("cannot index {} with a number", kind(input))),
+            },
+            Filter::Slice(from, to) => match input {
+                Json::Array(values) => {
+                    let from = index(values.len(), from.unwrap_or(0));
+                    let to = index(values.len(), to.unwrap_or(values.len() as i64));
+                    vec![Json::Array(values[from..to.max(from)].to_vec())]
+                }
+                Json::String(s) => {
+                    let chars: Vec<char> = s.chars().collect();
+                    let from = index(chars.len(), from.unwrap_or(0));
+                    let to = index(chars.len(), to.unwrap_or(chars.len() as i64));
+                    vec![Json::String(chars[from..to.max(from)].iter().collect())]
+                }
+                _ => return Err(format!("cannot slice {}", kind(input))),
+            },
+            Filter::Iterate => match input {
+                Json::Array(values) => values.clone(),
+                Json::Object(fields) => fields.iter().map(|(_, v)| v.clone()).collect(),
+                _ => return Err(format!("cannot iterate over {}", kind(input))),
+            },
+            Filter::Pipe(first, second) => {
+                let mut out = Vec::new();
+                for value in first.apply(input)? {
+                    out.extend(second.apply(&value)?);
+                }
+                out
+            }
+            Filter::Comma(first, second) => {
+                let mut out = first.apply(input)?;
+                out.extend(second.apply(input)?);
+                out
+            }
+            Filter::Keys => match input {
+                Json::Object(fields) => {
+                    let mut keys: Vec<_> = fields.iter().map(|(k, _)| k.clone()).collect();
+                    keys.sort();
+                    vec![keys.into()]
+                }
+                Json::Array(values) => {
+                    vec![(0..values.len()).map(Json::from).collect::<Vec<_>>().into()]
+                }
+                _ => return Err(format!("{} has no keys", kind(input))),
+            },
+            Filter::Length => vec![Json::from(match input {
+                Json::Array(values) => values.len(),
+                Json::Object(fields) => fields.len(),
+                Json::String(s) => s.chars().count(),
+                Json::Null => 0,
+                _ => return Err(format!("{} has no length", kind(input))),
+            })],
+            Filter::Select(condition) => {
+                let keep = condition.apply(input)?.iter().any(truthy);
+                if keep {
+                    vec![input.clone()]
+                } else {
+                    vec![]
+                }
+            }
+            Filter::Compare(left, comparison, right) => {
+                let mut out = Vec::new();
+                for r in right.apply(input)? {
+                    for l in left.apply(input)? {
+                        out.push(Json::Bool(compare(&l, *comparison, &r)));
+                    }
+                }
+                out
+            }
+            Filter::Add(left, right) => {
+                let mut out = Vec::new();
+                for r in right.apply(input)? {
+                    for l in left.apply(input)? {
+                        out.push(add(&l, &r)?);
+                    }
+                }
+                out
+            }
+            Filter::Alternative(first, second) => {
+                let values: Vec<_> = first
+                    .apply(input)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(truthy)
+                    .collect();
+                match values.is_empty() {
+                    true => second.apply(input)?,
+                    false => values,
+                }
+            }
+            Filter::Not => vec![Json::Bool(!truthy(input))],
+            Filter::ToString => vec![Json::String(to_line(input))],
+            Filter::Downcase => vec![string(input, "ascii_downcase")?.to_ascii_lowercase().into()],
+            Filter::Upcase => vec![string(input, "ascii_upcase")?.to_ascii_uppercase().into()],
+            Filter::Trim => vec![string(input, "trim")?.trim().into()],
+            Filter::Split(separator) => {
+                let text = string(input, "split")?;
+                let mut out = Vec::new();
+                for separator in separator.apply(input)? {
+                    let parts = text.split(string(&separator, "split")?).map(Json::from);
+                    out.push(Json::Array(parts.collect()));
+                }
+                out
+            }
+            Filter::Join(separator) => {
+                let values = match input {
+                    Json::Array(values) => values,
+                    _ => return Err(format!("cannot join {}", kind(input))),
+                };
+                let parts: Vec<_> = values
+                    .iter()
+                    .map(|value| match value {
+                        Json::Null => String::new(),
+                        value => to_line(value),
+                    })
+                    .collect();
+                let mut out = Vec::new();
+                for separator in separator.apply(input)? {
+                    out.push(parts.join(string(&separator, "join")?).into());
+                }
+                out
+            }
+            Filter::Test(pattern) => {
+                let text = string(input, "test")?;
+                let mut out = Vec::new();
+                for pattern in pattern.apply(input)? {
+                    out.push(Json::Bool(regex(&pattern)?.is_match(text)));
+                }
+                out
+            }
+            Filter::Sub(pattern, replacement, global) => {
+                let text = string(input, "sub")?;
+                let mut out = Vec::new();
+                for pattern in pattern.apply(input)? {
+                    let regex = regex(&pattern)?;
+                    for replacement in replacement.apply(input)? {
+                        let replacement = string(&replacement, "sub")?;
+                        out.push(match global {
+                            true => regex.replace_all(text, replacement).into_owned().into(),
+                            false => regex.replace(text, replacement).into_owned().into(),
+                        });
+                    }
+                }
+                out
+            }
+            Filter::Array(None) => vec![Json::Array(vec![])],
+            Filter::Array(Some(inner)) => vec![Json::Array(inner.apply(input)?)],
+            Filter::Object(fields) => {
+                let mut objects = vec![Vec::new()];
+                for (key, value) in fields {
+                    let values = value.apply(input)?;
+                    objects = objects
+                        .into_iter()
+                        .flat_map(|fields: Vec<(String, Json)>| {
+                            values.iter().map(move |value| {
+                                let mut fields = fields.clone();
+                                fields.push((key.clone(), value.clone()));
+                                fields
+                            })
+                        })
+                        .collect();
+                }
+                objects.into_iter().map(Json::Object).collect()
+            }
+            Filter::Sum => match input {
+                Json::Array(values) => {
+                    vec![values
+                        .iter()
+                        .try_fold(Json::Null, |sum, value| add(&sum, value))?]
+                }
+                _ => return Err(format!("cannot add up {}", kind(input))),
+            },
+        })
+    }
+}
+
+fn kind(value: &Json) -> &'static str {
+    match value {
+        Json::Null => "null",
+        Json::Bool(_) => "boolean",
+        Json::Number(_) => "number",
+        Json::String(_) => "string",
+        Json::Array(_) => "array",
+        Json::Object(_) => "object",
+    }
+}
+
+/// strings as they are, everything else as compact json
+pub fn to_line(value: &Json) -> String {
+    match value {
+        Json::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(filter: &str, input: &str) -> Vec<String> {
+        let filter: Filter = filter.parse().unwrap();
+        let input = Json::parse(input).unwrap();
+        filter
+            .apply(&input)
+            .unwrap()
+            .iter()
+            .map(Json::to_string)
+            .collect()
+    }
+
+    const STORE: &str = r#"{"name":"shop","items":[{"id":1,"price":9.5,"tags":["a","b"]},
+        {"id":2,"price":20,"tags":[]},{"id":3,"price":null,"tags":["c"]}]}"#;
+
+    #[test]
+    fn paths() {
+        assert_eq!(run(".name", STORE), [r#""shop""#]);
+        assert_eq!(run(r#".["name"]"#, STORE), [r#""shop""#]);
+        assert_eq!(run(".items[1].id", STORE), ["2"]);
+        assert_eq!(run(".items[-1].id", STORE), ["3"]);
+        assert_eq!(run(".items[1:].[0].id", STORE), ["2"]);
+        assert_eq!(run(".items[].id", STORE), ["1", "2", "3"]);
+        assert_eq!(run(".missing.deeper", STORE), ["null"]);
+        assert_eq!(run(".name[0:2]", STORE), [r#""sh""#]);
+    }
+
+    #[test]
+    fn pipes_and_functions() {
+        assert_eq!(run(".items | length", STORE), ["3"]);
+        assert_eq!(run("keys", STORE), [r#"["items","name"]"#]);
+        assert_eq!(run(".items[] | select(.price > 10) | .id", STORE), ["2"]);
+        assert_eq!(run(".items[] | .price // 0", STORE), ["9.5", "20", "0"]);
+        assert_eq!(run(".name, .items[0].id", STORE), [r#""shop""#, "1"]);
+        assert_eq!(
+            run(r#".name | ascii_upcase | sub("O"; "0")"#, STORE),
+            [r#""SH0P""#]
+        );
+        assert_eq!(run(r#".items[0].tags | join("-")"#, STORE), [r#""a-b""#]);
+        assert_eq!(run(r#""a\"b" | length"#, "null"), ["3"]);
+    }
+
+    #[test]
+    fn construction() {
+        assert_eq!(run("[.items[].id]", STORE), ["[1,2,3]"]);
+        assert_eq!(run("[]", STORE), ["[]"]);
+        assert_eq!(
+            run(
+                r#".items[0] | {id, "first tag": .tags[0], cheap: .price < 10}"#,
+                STORE
+            ),
+            [r#"{"id":1,"first tag":"a","cheap":true}"#]
+        );
+        assert_eq!(
+            run("{id: .items[].id}", STORE),
+            [r#"{"id":1}"#, r#"{"id":2}"#, r#"{"id":3}"#]
+        );
+        assert_eq!(run("{}", STORE), ["{}"]);
+    }
+
+    #[test]
+    fn map_and_add() {
+        assert_eq!(run(".items | map(.id)", STORE), ["[1,2,3]"]);
+        assert_eq!(run(".items | map(.id) | add", STORE), ["6"]);
+        assert_eq!(run("[.items[].tags] | add", STORE), [r#"["a","b","c"]"#]);
+        assert_eq!(run("[] | add", STORE), ["null"]);
+        assert_eq!(run(r#"["a", "b"] | add"#, "null"), [r#""ab""#]);
+    }
+
+    #[test]
+    fn rejects_unsupported_syntax() {
+        let error = |filter: &str| filter.parse::<Filter>().unwrap_err();
+        assert_eq!(error(".."), "filters don't support recursive descent '..'");
+        assert_eq!(error(".a?"), "filters don't support the '?' operator");
+        assert_eq!(error(".a as $x | $x"), "filters don't support variables");
+        assert_eq!(error(".a - 1"), "filters don't support '-'");
+        assert_eq!(error(".a and .b"), "filters don't support 'and'");
+        assert_eq!(
+            error("if .a then 1 else 2 end"),
+            "filters don't support 'if'"
+        );
+        assert_eq!(error("@csv"), "filters don't support formats like '@csv'");
+        assert_eq!(
+            error(r#""\(.a)""#),
+            "filters don't support string interpolation"
+        );
+        assert_eq!(
+            error("{(.a): 1}"),
+            "filters don't support computed object keys"
+        );
+        assert_eq!(error("to_entries"), "unknown function 'to_entries'");
+        assert_eq!(error(".a |"), "unexpected '' in filter");
+    }
+
+    #[test]
+    fn type_errors() {
+        let filter: Filter = ".a".parse().unwrap();
+        assert_eq!(
+            filter.apply(&Json::from(1.0)).unwrap_err(),
+            r#"cannot index number with "a""#
+        );
+        let filter: Filter = "add".parse().unwrap();
+        assert_eq!(
+            filter.apply(&Json::from("x")).unwrap_err(),
+            "cannot add up string"
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use std::{collections::HashMap, fmt};
+
+/// how deeply arrays and objects can nest, deeper documents are rejected instead of running out of
+/// stack
+const MAX_DEPTH: usize = 128;
+
+/// just enough JSON to read api responses and write structured output
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// field of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn object<'a>(fields: impl IntoIterator<Item = (&'a str, Json)>) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Self {
+        Json::Number(n)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(n: u64) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<u16> for Json {
+    fn from(n: u16) -> Self {
+        Json::Number(n.into())
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Self {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Json {
+    /// compact, single line
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if !n.is_finite() => f.write_str("null"),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+impl Json {
+    pub fn parse(input: &str) -> Result<Json, String> {
+        let mut reader = Reader {
+            input: input.as_bytes(),
+            at: 0,
+            depth: 0,
+        };
+        let value = reader.value()?;
+        reader.whitespace();
+        if reader.at < input.len() {
+            return Err(reader.error("trailing characters"));
+        }
+        Ok(value)
+    }
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    at: usize,
+    /// how many arrays and objects the reader is inside of
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("invalid json at byte {}: {}", self.at, message)
+    }
+
+    fn whitespace(&mut self) {
+        while self.input.get(self.at).is_some_and(u8::is_ascii_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str, value: Json) -> Result<Json, String> {
+        if self.input[self.at..].starts_with(literal.as_bytes()) {
+            self.at += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("unexpected character"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.whitespace();
+        match self.input.get(self.at) {
+            Some(b'{' | b'[') if self.depth == MAX_DEPTH => Err(self.error("nested too deeply")),
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.expect("true", Json::Bool(true)),
+            Some(b'f') => self.expect("false", Json::Bool(false)),
+            Some(b'n') => self.expect("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.at;
+        while self
+            .input
+            .get(self.at)
+            .is_some_and(|b| b.is_ascii_digit() || b"+-.eE".contains(b))
+        {
+            self.at += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.at])
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .map(Json::Number)
+            .ok_or_else(|| self.error("invalid number"))
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let digits = self
+            .input
+            .get(self.at..self.at + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.at += 4;
+        Ok(digits)
+    }
+
+    /// the `\uDC00`-`\uDFFF` escape that completes a surrogate pair, if it comes next
+    fn low_surrogate(&self) -> Option<u32> {
+        let escape = self.input.get(self.at..self.at + 6)?.strip_prefix(b"\\u")?;
+        let low = u32::from_str_radix(std::str::from_utf8(escape).ok()?, 16).ok()?;
+        (0xdc00..0xe000).contains(&low).then_some(low)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.at += 1;
+        let mut out = Vec::new();
+        loop {
+            match self.input.get(self.at) {
+                Some(b'"') => {
+                    self.at += 1;
+                    return String::from_utf8(out).map_err(|_| self.error("invalid utf-8"));
+                }
+                Some(b'\\') => {
+                    self.at += 1;
+                    let escaped = *self
+                        .input
+                        .get(self.at)
+                        .ok_or_else(|| self.error("unexpected end"))?;
+                    self.at += 1;
+                    let c = match escaped {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'"' | b'\\' | b'/' => escaped as char,
+                        b'u' => {
+                            let mut code = self.hex()?;
+                            if (0xd800..0xdc00).contains(&code) {
+                                if let Some(low) = self.low_surrogate() {
+                                    self.at += 6;
+                                    code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                                }
+                            }
+                            // lone surrogates become replacement characters
+                            char::from_u32(code).unwrap_or('\u{fffd}')
+                        }
+                        _ => {
+                            self.at -= 1;
+                            return Err(self.error("invalid escape"));
+                        }
+                    };
+                    let mut buffer = [0; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+                Some(byte) => {
+                    out.push(*byte);
+                    self.at += 1;
+                }
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.at += 1;
+        let mut values = Vec::new();
+        self.whitespace();
+        if self.input.get(self.at) == Some(&b']') {
+            self.at += 1;
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+            match self.input.get(self.at) {
+                Some(b',') => self.at += 1,
+                Some(b']') => {
+                    self.at += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.at += 1;
+        let mut fields: Vec<(String, Json)> = Vec::new();
+        // where each key is, a repeated key replaces the value like javascript does
+        let mut keys: HashMap<String, usize> = HashMap::new();
+        self.whitespace();
+        if self.input.get(self.at) == Some(&b'}') {
+            self.at += 1;
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.whitespace();
+            if self.input.get(self.at) != Some(&b'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            self.whitespace();
+            if self.input.get(self.at) != Some(&b':') {
+                return Err(self.error("expected ':'"));
+            }
+            self.at += 1;
+            let value = self.value()?;
+            match keys.get(&key) {
+                Some(&i) => fields[i].1 = value,
+                None => {
+                    keys.insert(key.clone(), fields.len());
+                    fields.push((key, value));
+                }
+            }
+            self.whitespace();
+            match self.input.get(self.at) {
+                Some(b',') => self.at += 1,
+                Some(b'}') => {
+                    self.at += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(json: &str) -> String {
+        Json::parse(json).unwrap().as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(string(r#""a\"b\\c\/d""#), "a\"b\\c/d");
+        assert_eq!(string(r#""\n\t\r\b\f""#), "\n\t\r\u{8}\u{c}");
+        assert_eq!(string(r#""\u00e9\u0041""#), "éA");
+        assert_eq!(string("\"é ✓\""), "é ✓");
+        assert!(Json::parse(r#""\x""#).is_err());
+        assert!(Json::parse(r#""\u12""#).is_err());
+        assert!(Json::parse(r#""open"#).is_err());
+    }
+
+    #[test]
+    fn surrogate_pairs() {
+        assert_eq!(string(r#""\ud83d\ude00""#), "😀");
+        assert_eq!(string(r#""\uD834\uDD1E""#), "𝄞");
+        assert_eq!(string(r#""\ud83d""#), "\u{fffd}");
+        assert_eq!(string(r#""\ud83dx""#), "\u{fffd}x");
+        assert_eq!(string(r#""\ud83d\u0041""#), "\u{fffd}A");
+        assert_eq!(string(r#""\ude00\ud83d""#), "\u{fffd}\u{fffd}");
+    }
+
+    #[test]
+    fn numbers() {
+        for (json, number) in [
+            ("0", 0.0),
+            ("-0", 0.0),
+            ("42", 42.0),
+            ("-3.25", -3.25),
+            ("1e3", 1000.0),
+            ("2.5E-2", 0.025),
+            ("1e+2", 100.0),
+        ] {
+            assert_eq!(Json::parse(json), Ok(Json::Number(number)), "{}", json);
+        }
+        for json in ["-", "1e", "1.2.3", "--1", "+1", ".5"] {
+            assert!(Json::parse(json).is_err(), "{}", json);
+        }
+        assert_eq!(Json::Number(3.0).to_string(), "3");
+        assert_eq!(Json::Number(0.5).to_string(), "0.5");
+        assert_eq!(Json::Number(f64::NAN).to_string(), "null");
+    }
+
+    #[test]
+    fn duplicate_keys_keep_the_last_value() {
+        let json = Json::parse(r#"{"a": 1, "b": 2, "a": 3}"#).unwrap();
+        assert_eq!(json.get("a"), Some(&Json::Number(3.0)));
+        assert_eq!(json.to_string(), r#"{"a":3,"b":2}"#);
+    }
+
+    #[test]
+    fn round_trips() {
+        let json =
+            r#"{"s":"line\nbreak \"quoted\"","n":[1,2.5,-3],"t":true,"f":false,"z":null,"o":{}}"#;
+        assert_eq!(Json::parse(json).unwrap().to_string(), json);
+    }
+
+    #[test]
+    fn error_positions() {
+        let error = |json: &str| Json::parse(json).unwrap_err();
+        assert_eq!(
+            error("[1,]"),
+            "invalid json at byte 3: unexpected character"
+        );
+        assert_eq!(error(r#"{"a" 1}"#), "invalid json at byte 5: expected ':'");
+        assert_eq!(
+            error("[1 2]"),
+            "invalid json at byte 3: expected ',' or ']'"
+        );
+        assert_eq!(error("{1: 2}"), "invalid json at byte 1: expected a key");
+        assert_eq!(error("[] x"), "invalid json at byte 3: trailing characters");
+        assert_eq!(error(r#""a\qb""#), "invalid json at byte 3: invalid escape");
+        assert_eq!(error("tru"), "invalid json at byte 0: unexpected character");
+        assert_eq!(error(""), "invalid json at byte 0: unexpected end");
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            Json::parse(&nested(MAX_DEPTH + 1)).unwrap_err(),
+            format!("invalid json at byte {}: nested too deeply", MAX_DEPTH)
+        );
+        assert!(Json::parse(&"[{\"a\":".repeat(100_000)).is_err());
+    }
+}
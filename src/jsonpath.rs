@@ -0,0 +1,348 @@
+//! JSONPath queries like `$.store.book[*].title`, filter expressions such as
+//! `[?(@.price < 10)]` are evaluated as jq conditions
+
+use std::str::FromStr;
+
+use crate::jq;
+use crate::json::Json;
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Filter(jq::Filter),
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    /// `..`, applies the selectors to the node and all its descendants
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+/// the characters of a bracketed selector with whether they're outside of quotes and parentheses
+fn top_level(inside: &str) -> impl Iterator<Item = (usize, char, bool)> + '_ {
+    let mut quote = None;
+    let mut escaped = false;
+    let mut depth = 0usize;
+    inside.char_indices().map(move |(i, c)| {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        (i, c, quote.is_none() && depth == 0)
+    })
+}
+
+fn split_top_level(inside: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (i, c, top) in top_level(inside) {
+        if top && c == ',' {
+            parts.push(&inside[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&inside[start..]);
+    parts
+}
+
+/// the filter as a jq condition: `@` becomes `.` and single quoted strings double quoted ones,
+/// leaving what's inside of strings alone
+fn condition(filter: &str) -> Result<String, String> {
+    let mut condition = String::new();
+    let mut chars = filter.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '@' => {
+                condition.push('.');
+                chars.next_if_eq(&'.');
+            }
+            '"' | '\'' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ ('\'' | '"' | '\\' | '/')) => string.push(escaped),
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some(other) => {
+                                return Err(format!("invalid escape '\\{}' in jsonpath", other))
+                            }
+                            None => break,
+                        },
+                        Some(other) => string.push(other),
+                        None => {
+                            return Err(format!(
+                                "unterminated string in jsonpath filter '{}'",
+                                filter
+                            ))
+                        }
+                    }
+                }
+                condition.push_str(&Json::String(string).to_string());
+            }
+            c => condition.push(c),
+        }
+    }
+    Ok(condition)
+}
+
+fn parse_selector(part: &str) -> Result<Selector, String> {
+    let part = part.trim();
+    let number = |n: &str| -> Result<Option<i64>, String> {
+        match n.trim() {
+            "" => Ok(None),
+            n => n
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid index '{}' in jsonpath", n)),
+        }
+    };
+    let quoted = |quote| part.len() >= 2 && part.starts_with(quote) && part.ends_with(quote);
+    if part == "*" {
+        Ok(Selector::Wildcard)
+    } else if quoted('\'') || quoted('"') {
+        Ok(Selector::Name(part[1..part.len() - 1].to_string()))
+    } else if let Some(filter) = part.strip_prefix('?') {
+        let filter = filter.trim();
+        let filter = filter
+            .strip_prefix('(')
+            .and_then(|c| c.strip_suffix(')'))
+            .unwrap_or(filter);
+        Ok(Selector::Filter(condition(filter)?.parse()?))
+    } else if let Some((from, to)) = part.split_once(':') {
+        Ok(Selector::Slice(number(from)?, number(to)?))
+    } else {
+        number(part)?
+            .map(Selector::Index)
+            .ok_or_else(|| "empty selector in jsonpath".to_string())
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut rest = path.trim().strip_prefix('$').unwrap_or(path.trim());
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            let descendant = rest.starts_with("..");
+            if descendant {
+                rest = &rest[2..];
+            } else if let Some(after) = rest.strip_prefix('.') {
+                rest = after;
+            } else if !rest.starts_with('[') {
+                return Err(format!("unexpected '{}' in jsonpath", rest));
+            }
+
+            let selectors = if let Some(inside) = rest.strip_prefix('[') {
+                let (end, _, _) = top_level(inside)
+                    .find(|(_, c, top)| *top && *c == ']')
+                    .ok_or("missing ']' in jsonpath")?;
+                rest = &inside[end + 1..];
+                split_top_level(&inside[..end])
+                    .into_iter()
+                    .map(parse_selector)
+                    .collect::<Result<_, _>>()?
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                let name = &rest[..end];
+                rest = &rest[end..];
+                match name {
+                    "" => return Err("empty name in jsonpath".into()),
+                    "*" => vec![Selector::Wildcard],
+                    name => vec![Selector::Name(name.to_string())],
+                }
+            };
+            segments.push(Segment {
+                descendant,
+                selectors,
+            });
+        }
+        Ok(JsonPath { segments })
+    }
+}
+
+fn descendants<'a>(value: &'a Json, out: &mut Vec<&'a Json>) {
+    out.push(value);
+    match value {
+        Json::Array(values) => values.iter().for_each(|v| descendants(v, out)),
+        Json::Object(fields) => fields.iter().for_each(|(_, v)| descendants(v, out)),
+        _ => {}
+    }
+}
+
+fn children(value: &Json) -> Vec<&Json> {
+    match value {
+        Json::Array(values) => values.iter().collect(),
+        Json::Object(fields) => fields.iter().map(|(_, v)| v).collect(),
+        _ => vec![],
+    }
+}
+
+fn position(len: usize, i: i64) -> usize {
+    if i < 0 {
+        len.saturating_sub(i.unsigned_abs() as usize)
+    } else {
+        (i as usize).min(len)
+    }
+}
+
+impl Selector {
+    fn select<'a>(&self, value: &'a Json, out: &mut Vec<&'a Json>) {
+        match (self, value) {
+            (Selector::Name(name), Json::Object(_)) => out.extend(value.get(name)),
+            (Selector::Wildcard, _) => out.extend(children(value)),
+            (Selector::Index(i), Json::Array(values)) => {
+                let at = if *i < 0 { values.len() as i64 + i } else { *i };
+                out.extend(values.get(at as usize));
+            }
+            (Selector::Slice(from, to), Json::Array(values)) => {
+                let from = position(values.len(), from.unwrap_or(0));
+                let to = position(values.len(), to.unwrap_or(values.len() as i64));
+                out.extend(values[from..to.max(from)].iter());
+            }
+            (Selector::Filter(condition), _) => {
+                for child in children(value) {
+                    // a child without the field queried for is just not a match
+                    let matched = condition.apply(child).unwrap_or_default();
+                    if matched
+                        .iter()
+                        .any(|m| !matches!(m, Json::Null | Json::Bool(false)))
+                    {
+                        out.push(child);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl JsonPath {
+    /// every node the path matches, in document order
+    pub fn apply(&self, input: &Json) -> Vec<Json> {
+        let mut nodes = vec![input];
+        for segment in &self.segments {
+            let mut candidates = Vec::new();
+            if segment.descendant {
+                nodes
+                    .iter()
+                    .for_each(|node| descendants(node, &mut candidates));
+            } else {
+                candidates = nodes;
+            }
+            nodes = Vec::new();
+            for candidate in candidates {
+                for selector in &segment.selectors {
+                    selector.select(candidate, &mut nodes);
+                }
+            }
+        }
+        nodes.into_iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(path: &str, json: &str) -> Vec<String> {
+        let path: JsonPath = path.parse().unwrap();
+        path.apply(&Json::parse(json).unwrap())
+            .iter()
+            .map(Json::to_string)
+            .collect()
+    }
+
+    const STORE: &str = r#"{"store": {"book": [
+        {"title": "A", "price": 8, "email": "a@b.c", "tags": ["x", "y"]},
+        {"title": "It's", "price": 12, "email": "d@e.f", "tags": ["y"]},
+        {"title": "C, ]) D", "price": 5}
+    ], "bicycle": {"price": 20}}}"#;
+
+    #[test]
+    fn names_indexes_and_slices() {
+        assert_eq!(query("$.store.book[0].title", STORE), [r#""A""#]);
+        assert_eq!(query("$['store']['bicycle'].price", STORE), ["20"]);
+        assert_eq!(query("$.store.book[-1].price", STORE), ["5"]);
+        assert_eq!(query("$.store.book[0,2].price", STORE), ["8", "5"]);
+        assert_eq!(query("$.store.book[1:].price", STORE), ["12", "5"]);
+        assert_eq!(query("$.store.book[:1].price", STORE), ["8"]);
+        assert_eq!(query("$.store.book[*].price", STORE), ["8", "12", "5"]);
+        assert_eq!(query("$..price", STORE), ["8", "12", "5", "20"]);
+        assert_eq!(query("$.store.book[5]", STORE), Vec::<String>::new());
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(
+            query("$.store.book[?(@.price < 10)].title", STORE),
+            [r#""A""#, r#""C, ]) D""#]
+        );
+        assert_eq!(query("$..book[?(@.email == 'a@b.c')].price", STORE), ["8"]);
+        assert_eq!(
+            query(r#"$..book[?(@.title == "It's")].price"#, STORE),
+            ["12"]
+        );
+        assert_eq!(
+            query(r#"$..book[?(@.title == 'It\'s')].price"#, STORE),
+            ["12"]
+        );
+        assert_eq!(
+            query("$..book[?(@.title == 'C, ]) D')].price", STORE),
+            ["5"]
+        );
+        assert_eq!(query("$..book[?(@.tags[0] == 'x')].price", STORE), ["8"]);
+        assert_eq!(query("$..book[?(@.tags)].price", STORE), ["8", "12"]);
+        assert_eq!(
+            query("$.store.book[0].tags[?(@ == 'y')]", STORE),
+            [r#""y""#]
+        );
+    }
+
+    #[test]
+    fn conditions_keep_strings_intact() {
+        assert_eq!(
+            condition("@.email == 'a@b.c'").unwrap(),
+            r#".email == "a@b.c""#
+        );
+        assert_eq!(condition(r#"@.t == "it's""#).unwrap(), r#".t == "it's""#);
+        assert_eq!(
+            condition(r#"@.t == 'say "hi"'"#).unwrap(),
+            r#".t == "say \"hi\"""#
+        );
+        assert_eq!(condition("@ > 1").unwrap(), ". > 1");
+        assert!(condition("@.t == 'open").is_err());
+    }
+
+    #[test]
+    fn invalid_paths() {
+        for path in [
+            "$.a[]",
+            "$.a[1,]",
+            "$.a[",
+            "$.a[x]",
+            "$.a..",
+            "$a",
+            "$.a[?(@.b == 'c)]",
+        ] {
+            assert!(path.parse::<JsonPath>().is_err(), "{}", path);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! rough language identification, by script and then by common words, good
+//! enough to route pages but not to tell close relatives apart in short texts
+
+use scraper::{Html, Node};
+
+/// common words that are rare in the other languages listed
+const STOPWORDS: [(&str, &[&str]); 12] = [
+    (
+        "en",
+        &[
+            "the", "and", "of", "to", "is", "that", "with", "for", "this", "are", "was", "you",
+            "have", "not", "from",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "und", "das", "ist", "nicht", "mit", "sich", "auf", "ein", "eine", "dem",
+            "den", "auch", "wird",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "les", "et", "des", "est", "une", "du", "dans", "pour", "qui", "pas", "sur",
+            "au", "avec", "sont",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "los", "las", "del", "que", "por", "una", "con", "para", "es", "se", "como",
+            "pero", "sus", "está",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "di", "che", "della", "per", "non", "gli", "sono", "una", "nel", "alla", "anche",
+            "questo", "è", "dei",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "os", "das", "dos", "que", "não", "uma", "com", "para", "em", "ao", "mais", "como",
+            "foi", "são", "pela",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "van", "en", "niet", "dat", "zijn", "voor", "met", "ook", "wordt",
+            "bij", "aan", "maar",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "och", "att", "det", "som", "är", "för", "med", "inte", "till", "på", "av", "den",
+            "har", "ett", "var",
+        ],
+    ),
+    (
+        "pl",
+        &[
+            "nie", "się", "na", "jest", "że", "do", "to", "jak", "ale", "od", "przez", "dla", "są",
+            "jego", "który",
+        ],
+    ),
+    (
+        "tr",
+        &[
+            "ve", "bir", "bu", "için", "ile", "da", "de", "olan", "çok", "daha", "gibi", "ama",
+            "olarak", "değil", "var",
+        ],
+    ),
+    (
+        "fi",
+        &[
+            "ja", "on", "ei", "että", "se", "oli", "ovat", "mutta", "kun", "myös", "tai", "joka",
+            "niin", "kuin", "hän",
+        ],
+    ),
+    (
+        "id",
+        &[
+            "yang", "dan", "di", "ini", "itu", "dengan", "untuk", "tidak", "dari", "dalam", "akan",
+            "pada", "juga", "ada", "adalah",
+        ],
+    ),
+];
+
+fn script(c: char) -> Option<&'static str> {
+    Some(match c as u32 {
+        0x3040..=0x30ff => "ja",
+        0x4e00..=0x9fff | 0x3400..=0x4dbf => "zh",
+        0xac00..=0xd7af | 0x1100..=0x11ff => "ko",
+        0x0400..=0x04ff => "cyrillic",
+        0x0600..=0x06ff => "arabic",
+        0x0370..=0x03ff => "el",
+        0x0590..=0x05ff => "he",
+        0x0900..=0x097f => "hi",
+        0x0e00..=0x0e7f => "th",
+        _ => return None,
+    })
+}
+
+/// ISO 639-1 code of the text's language, if there is enough text to tell
+pub fn detect(text: &str) -> Option<&'static str> {
+    let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.is_empty() {
+        return None;
+    }
+    let mut scripts: Vec<(&str, usize)> = Vec::new();
+    for script in letters.iter().filter_map(|c| script(*c)) {
+        match scripts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, count)) => *count += 1,
+            None => scripts.push((script, 1)),
+        }
+    }
+    let non_latin: usize = scripts.iter().map(|(_, count)| count).sum();
+    if non_latin * 2 > letters.len() {
+        let has = |chars: &str| letters.iter().any(|c| chars.contains(*c));
+        // kana mixed with kanji is japanese, kanji alone is taken as chinese
+        if scripts.iter().any(|(s, _)| *s == "ja") {
+            return Some("ja");
+        }
+        let (dominant, _) = scripts.iter().max_by_key(|(_, count)| *count)?;
+        return Some(match *dominant {
+            "cyrillic" if has("іїєґ") => "uk",
+            "cyrillic" if has("ўЎ") => "be",
+            "cyrillic" => "ru",
+            "arabic" if has("پچژگ") => "fa",
+            "arabic" => "ar",
+            other => other,
+        });
+    }
+
+    let lowercase = text.to_lowercase();
+    let words: Vec<&str> = lowercase
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let (language, hits) = STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| {
+            let hits = words.iter().filter(|word| stopwords.contains(word)).count();
+            (*language, hits)
+        })
+        .max_by_key(|(_, hits)| *hits)?;
+    (hits >= 2 || (hits == 1 && words.len() <= 4)).then_some(language)
+}
+
+/// text a reader would see, leaving out scripts and styles
+pub fn visible_text(document: &Html) -> String {
+    let mut text = String::new();
+    for node in document.tree.nodes() {
+        if let Node::Text(t) = node.value() {
+            let hidden = node.ancestors().any(|ancestor| match ancestor.value() {
+                Node::Element(e) => {
+                    matches!(e.name(), "script" | "style" | "noscript" | "template")
+                }
+                _ => false,
+            });
+            if !hidden {
+                text.push_str(t);
+                text.push(' ');
+            }
+        }
+    }
+    text
+}
+
+/// the language of a document or fragment of html, `und` if it can't be told
+pub fn detect_html(html: &str) -> &'static str {
+    detect(&visible_text(&Html::parse_fragment(html))).unwrap_or("und")
+}
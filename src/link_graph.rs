@@ -0,0 +1,87 @@
+use reqwest::Url;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// which crawled page links to which url
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    nodes: Vec<String>,
+    ids: HashMap<String, usize>,
+    edges: BTreeSet<(usize, usize)>,
+}
+
+impl LinkGraph {
+    fn node(&mut self, url: &Url) -> usize {
+        if let Some(id) = self.ids.get(url.as_str()) {
+            return *id;
+        }
+        self.nodes.push(url.to_string());
+        self.ids.insert(url.to_string(), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    pub fn add_link(&mut self, from: &Url, to: &Url) {
+        let edge = (self.node(from), self.node(to));
+        self.edges.insert(edge);
+    }
+
+    /// writes GraphML for `*.graphml` paths and Graphviz DOT otherwise
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(fs::File::create(path)?);
+        if path.extension().is_some_and(|ext| ext == "graphml") {
+            self.write_graphml(&mut out)?;
+        } else {
+            self.write_dot(&mut out)?;
+        }
+        out.flush()
+    }
+
+    fn write_dot(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "digraph links {{")?;
+        for (id, url) in self.nodes.iter().enumerate() {
+            let label = url.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(out, "  n{} [label=\"{}\"];", id, label)?;
+        }
+        for (from, to) in &self.edges {
+            writeln!(out, "  n{} -> n{};", from, to)?;
+        }
+        writeln!(out, "}}")
+    }
+
+    fn write_graphml(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="url" for="node" attr.name="url" attr.type="string"/>"#
+        )?;
+        writeln!(out, r#"  <graph id="links" edgedefault="directed">"#)?;
+        for (id, url) in self.nodes.iter().enumerate() {
+            writeln!(
+                out,
+                r#"    <node id="n{}"><data key="url">{}</data></node>"#,
+                id,
+                escape_xml(url)
+            )?;
+        }
+        for (from, to) in &self.edges {
+            writeln!(out, r#"    <edge source="n{}" target="n{}"/>"#, from, to)?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,163 @@
+//! pages from local files, given as a path or `file://` url, memory-mapped with --mmap so huge
+//! documents are decoded straight from the page cache instead of being read into a buffer first
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE},
+    StatusCode, Url,
+};
+use std::{
+    fs::File,
+    io,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use crate::{log, Page};
+
+/// the file the url names, if it isn't a web address
+pub fn path(url: &str) -> Option<PathBuf> {
+    if url.starts_with("file://") {
+        return Url::parse(url).ok()?.to_file_path().ok();
+    }
+    let path = Path::new(url);
+    (!url.contains("://") && path.is_file()).then(|| path.to_path_buf())
+}
+
+/// the bytes of a file, read or mapped
+pub enum Contents {
+    Read(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Contents::Read(data) => data,
+            Contents::Mapped(data) => data,
+        }
+    }
+}
+
+/// the whole file, mapped instead of read with --mmap
+pub fn read(path: &Path) -> io::Result<Contents> {
+    match crate::args().mmap {
+        true => Ok(Contents::Mapped(Mmap::open(path)?)),
+        false => Ok(Contents::Read(std::fs::read(path)?)),
+    }
+}
+
+/// a read-only mapping of a whole file
+pub struct Mmap {
+    #[cfg(unix)]
+    data: *mut libc::c_void,
+    #[cfg(not(unix))]
+    data: Vec<u8>,
+    len: usize,
+}
+
+impl Mmap {
+    #[cfg(unix)]
+    pub fn open(path: &Path) -> io::Result<Mmap> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Ok(Mmap {
+                data: std::ptr::null_mut(),
+                len,
+            });
+        }
+        // SAFETY: a private read-only mapping of a file we opened, unmapped in drop. This is
+        // only sound while nobody else changes the file: writes to it can show through the
+        // mapping, and reading pages past its end after another process truncated it raises
+        // SIGBUS, which is why mapping is opt-in with --mmap
+        let data = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if data == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap { data, len })
+    }
+
+    #[cfg(not(unix))]
+    pub fn open(path: &Path) -> io::Result<Mmap> {
+        let data = std::fs::read(path)?;
+        Ok(Mmap {
+            len: data.len(),
+            data,
+        })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    #[cfg(unix)]
+    fn deref(&self) -> &[u8] {
+        match self.len {
+            0 => &[],
+            // SAFETY: the mapping is `len` readable bytes for as long as self lives
+            _ => unsafe { std::slice::from_raw_parts(self.data as *const u8, self.len) },
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            // SAFETY: unmaps exactly what open mapped, no slices of it outlive self
+            unsafe { libc::munmap(self.data, self.len) };
+        }
+    }
+}
+
+/// the content type a web server would probably send the file with
+pub fn content_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "html" | "htm" | "xhtml" => "text/html",
+        "json" => "application/json",
+        "xml" | "rss" | "atom" => "application/xml",
+        "txt" => "text/plain",
+        _ => return None,
+    })
+}
+
+/// the file as a page, decoded like a downloaded body
+pub fn page(path: &Path) -> Result<Page, String> {
+    let failed = |e: io::Error| format!("Failed to read '{}': {}", path.display(), e);
+    log::info(format!("reading {}", path.display()));
+    let data = read(path).map_err(failed)?;
+    let absolute = path.canonicalize().map_err(failed)?;
+    let url = Url::from_file_path(&absolute)
+        .map_err(|_| format!("Failed to read '{}'", path.display()))?;
+    let mut headers = HeaderMap::new();
+    if let Some(content_type) = content_type(path) {
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    }
+    Ok(Page {
+        url,
+        status: StatusCode::OK,
+        body: scrape_core::charset::decode(&data, content_type(path)),
+        headers,
+        size: data.len(),
+        evaluated: None,
+    })
+}
@@ -0,0 +1,113 @@
+//! leveled diagnostics on stderr or in a log file, as text or json lines
+//!
+//! Not `tracing`: without spans to record it would only forward messages, and printing them as
+//! text or json lines to stderr or a file would still need `tracing-subscriber` and its
+//! dependencies for what this module does on its own.
+
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use crate::{datetime, json::Json};
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+struct Logger {
+    level: Level,
+    json: bool,
+    /// stderr if there is none
+    file: Option<File>,
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+    level: Level::Warn,
+    json: false,
+    file: None,
+});
+
+/// warnings and errors by default, `-v` adds info, `-vv` debug and `-vvv` everything
+pub fn init(verbosity: u64, file: Option<&Path>, json: bool) -> io::Result<()> {
+    let level = match verbosity {
+        0 => Level::Warn,
+        1 => Level::Info,
+        2 => Level::Debug,
+        _ => Level::Trace,
+    };
+    let file = file
+        .map(|path| OpenOptions::new().create(true).append(true).open(path))
+        .transpose()?;
+    *LOGGER.lock().unwrap() = Logger { level, json, file };
+    Ok(())
+}
+
+pub fn log(level: Level, message: impl Display) {
+    let mut logger = LOGGER.lock().unwrap();
+    if level > logger.level {
+        return;
+    }
+    let line = match (logger.json, &logger.file) {
+        (true, _) => Json::object([
+            ("timestamp", datetime::now().into()),
+            ("level", level.name().into()),
+            ("message", message.to_string().into()),
+        ])
+        .to_string(),
+        // a file is read later, so it needs to say when
+        (false, Some(_)) => format!(
+            "{} {:<5} {}",
+            datetime::now(),
+            level.name().to_uppercase(),
+            message
+        ),
+        (false, None) => format!("{:<5} {}", level.name().to_uppercase(), message),
+    };
+    match &mut logger.file {
+        Some(file) => {
+            let _ = writeln!(file, "{}", line);
+        }
+        None => eprintln!("{}", line),
+    }
+}
+
+pub fn error(message: impl Display) {
+    log(Level::Error, message)
+}
+
+pub fn warn(message: impl Display) {
+    log(Level::Warn, message)
+}
+
+pub fn info(message: impl Display) {
+    log(Level::Info, message)
+}
+
+pub fn debug(message: impl Display) {
+    log(Level::Debug, message)
+}
+
+pub fn trace(message: impl Display) {
+    log(Level::Trace, message)
+}
@@ -0,0 +1,179 @@
+//! a roff man page generated from the clap definitions
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct ManArgs {}
+
+/// escapes text for roff
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    match text.starts_with(['.', '\'']) {
+        true => format!("\\&{}", text),
+        false => text,
+    }
+}
+
+fn bold(text: &str) -> String {
+    format!("\\fB{}\\fR", escape(text))
+}
+
+fn italic(text: &str) -> String {
+    format!("\\fI{}\\fR", escape(text))
+}
+
+/// `[OPTIONS] <URL> [SELECTOR]` style usage of a command, and one for its subcommands
+fn synopsis(command: &clap::Command, name: &str) -> String {
+    let mut usage = vec![bold(name), "[\\fIOPTIONS\\fR]".to_string()];
+    for arg in command.get_positionals() {
+        let value = arg.get_id().to_uppercase();
+        usage.push(match arg.is_required_set() {
+            true => format!("<{}>", italic(&value)),
+            false => format!("[{}]", italic(&value)),
+        });
+    }
+    let mut lines = Vec::new();
+    if usage.len() > 2 || !command.has_subcommands() {
+        lines.push(usage.join(" "));
+    }
+    if command.has_subcommands() {
+        lines.push(format!(
+            "{} [\\fIOPTIONS\\fR] <\\fISUBCOMMAND\\fR>",
+            bold(name)
+        ));
+    }
+    lines.join("\n.br\n")
+}
+
+/// the `--help` and `--version` clap adds when it builds the command, with `-h` and `-V`
+/// unless something else has them
+fn builtin(root: bool, taken: &[char], page: &mut String) {
+    let mut flags = vec![('h', "help", "Print help information")];
+    if root {
+        flags.push(('V', "version", "Print version information"));
+    }
+    for (short, long, help) in flags {
+        let long = bold(&format!("--{}", long));
+        match taken.contains(&short) {
+            true => page.push_str(&format!(".TP\n{}\n{}\n", long, help)),
+            false => page.push_str(&format!(
+                ".TP\n{}, {}\n{}\n",
+                bold(&format!("-{}", short)),
+                long,
+                help
+            )),
+        }
+    }
+}
+
+fn arguments(args: Vec<&clap::Arg>, page: &mut String) {
+    let args = args.into_iter().filter(|arg| !arg.is_hide_set());
+    for arg in args.filter(|arg| !matches!(arg.get_id(), "help" | "version")) {
+        let mut names = Vec::new();
+        if let Some(short) = arg.get_short() {
+            names.push(bold(&format!("-{}", short)));
+        }
+        if let Some(long) = arg.get_long() {
+            names.push(bold(&format!("--{}", long)));
+        }
+        let value = arg
+            .get_value_names()
+            .and_then(|names| names.first().map(|name| name.to_string()))
+            .unwrap_or_else(|| arg.get_id().to_uppercase());
+        let mut item = match (arg.is_positional(), arg.is_takes_value_set()) {
+            (true, _) => italic(&value),
+            (false, true) => format!("{}=\\fI{}\\fR", names.join(", "), escape(&value)),
+            (false, false) => names.join(", "),
+        };
+        if arg.is_multiple_occurrences_set() && !arg.is_positional() {
+            item.push_str("...");
+        }
+        page.push_str(&format!(".TP\n{}\n", item));
+        let mut help = arg
+            .get_long_help()
+            .or_else(|| arg.get_help())
+            .unwrap_or_default()
+            .to_string();
+        let values: Vec<_> = arg
+            .get_possible_values()
+            .unwrap_or_default()
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name())
+            .collect();
+        if !values.is_empty() {
+            help.push_str(&format!(" [possible values: {}]", values.join(", ")));
+        }
+        let defaults: Vec<_> = arg
+            .get_default_values()
+            .iter()
+            .map(|value| value.to_string_lossy())
+            .collect();
+        if !defaults.is_empty() && !arg.is_hide_default_value_set() {
+            help.push_str(&format!(" [default: {}]", defaults.join(", ")));
+        }
+        if let Some(env) = arg.get_env() {
+            help.push_str(&format!(" [env: {}]", env.to_string_lossy()));
+        }
+        if !help.trim().is_empty() {
+            page.push_str(&escape(help.trim()));
+            page.push('\n');
+        }
+    }
+}
+
+fn options(command: &clap::Command, root: bool, page: &mut String) {
+    if command.get_positionals().next().is_some() {
+        page.push_str(&format!(".{} ARGUMENTS\n", if root { "SH" } else { "SS" }));
+        arguments(command.get_positionals().collect(), page);
+    }
+    page.push_str(&format!(".{} OPTIONS\n", if root { "SH" } else { "SS" }));
+    let options = command.get_arguments().filter(|arg| !arg.is_positional());
+    arguments(options.collect(), page);
+    let taken: Vec<_> = command
+        .get_arguments()
+        .filter_map(|arg| arg.get_short())
+        .collect();
+    builtin(root, &taken, page);
+}
+
+/// a section for the subcommand and the ones below it
+fn subcommand(command: &clap::Command, name: &str, page: &mut String) {
+    page.push_str(&format!(".SS {}\n", escape(name)));
+    if let Some(about) = command.get_long_about().or_else(|| command.get_about()) {
+        page.push_str(&format!("{}\n.PP\n", escape(about)));
+    }
+    page.push_str(&format!("{}\n", synopsis(command, name)));
+    options(command, false, page);
+    for child in command.get_subcommands().filter(|c| !c.is_hide_set()) {
+        subcommand(child, &format!("{} {}", name, child.get_name()), page);
+    }
+}
+
+pub fn run(_: ManArgs) {
+    let command = crate::command();
+    let name = command.get_name().to_string();
+    let version = command.get_version().unwrap_or_default();
+    let mut page = format!(
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"\n",
+        name.to_uppercase(),
+        escape(&name),
+        escape(version)
+    );
+    page.push_str(&format!(
+        ".SH NAME\n{} \\- {}\n",
+        escape(&name),
+        escape(command.get_about().unwrap_or_default())
+    ));
+    page.push_str(&format!(".SH SYNOPSIS\n{}\n", synopsis(&command, &name)));
+    if let Some(about) = command.get_long_about() {
+        page.push_str(&format!(".SH DESCRIPTION\n{}\n", escape(about)));
+    }
+    options(&command, true, &mut page);
+    page.push_str(".SH SUBCOMMANDS\n");
+    for child in command.get_subcommands().filter(|c| !c.is_hide_set()) {
+        subcommand(child, &format!("{} {}", name, child.get_name()), &mut page);
+    }
+    if let Some(author) = command.get_author().filter(|author| !author.is_empty()) {
+        page.push_str(&format!(".SH AUTHORS\n{}\n", escape(author)));
+    }
+    print!("{}", page);
+}
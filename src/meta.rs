@@ -0,0 +1,165 @@
+use regex::Regex;
+use reqwest::{header::LINK, Client, Url};
+use scraper::{Html, Selector};
+
+use crate::{download, json::Json, Error, Page};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct MetaArgs {
+    /// the page to describe
+    url: String,
+}
+
+fn select(selector: &str) -> Selector {
+    Selector::parse(selector).unwrap()
+}
+
+/// adds a field, turning it into an array if the key is already there
+fn push(fields: &mut Vec<(String, Json)>, key: &str, value: &str) {
+    match fields.iter_mut().find(|(k, _)| k == key) {
+        Some((_, Json::Array(values))) => values.push(value.into()),
+        Some((_, existing)) => {
+            let first = std::mem::replace(existing, Json::Null);
+            *existing = Json::Array(vec![first, value.into()]);
+        }
+        None => fields.push((key.to_string(), value.into())),
+    }
+}
+
+/// `content` of every `<meta>` whose `attribute` starts with `prefix`, keyed without it
+fn properties(document: &Html, attribute: &str, prefix: &str) -> Json {
+    let mut fields = Vec::new();
+    for meta in document.select(&select("meta[content]")) {
+        let key = meta
+            .value()
+            .attr(attribute)
+            .and_then(|key| key.strip_prefix(prefix));
+        if let (Some(key), Some(content)) = (key, meta.value().attr("content")) {
+            push(&mut fields, key, content.trim());
+        }
+    }
+    Json::Object(fields)
+}
+
+/// href of the first `<link>` matching the selector, resolved against the page
+fn link(document: &Html, selector: &str, base: &Url) -> Option<String> {
+    document
+        .select(&select(selector))
+        .find_map(|link| link.value().attr("href"))
+        .and_then(|href| base.join(href.trim()).ok())
+        .map(String::from)
+}
+
+/// title, description, canonical url, icon, OpenGraph and Twitter card properties
+pub fn metadata(document: &Html, base: &Url) -> Json {
+    let title = document
+        .select(&select("title"))
+        .next()
+        .map(|title| title.text().collect::<String>().trim().to_string());
+    let description = document
+        .select(&select("meta[name=description]"))
+        .find_map(|meta| meta.value().attr("content"))
+        .map(str::trim);
+
+    let mut twitter = properties(document, "name", "twitter:");
+    // some sites declare twitter cards the OpenGraph way
+    if let (Json::Object(fields), Json::Object(more)) =
+        (&mut twitter, properties(document, "property", "twitter:"))
+    {
+        for (key, value) in more {
+            if !fields.iter().any(|(k, _)| *k == key) {
+                fields.push((key, value));
+            }
+        }
+    }
+
+    Json::object([
+        ("url", base.as_str().into()),
+        ("title", title.into()),
+        ("description", description.into()),
+        (
+            "canonical",
+            link(document, "link[rel~=canonical]", base).into(),
+        ),
+        ("icon", link(document, "link[rel~=icon]", base).into()),
+        ("opengraph", properties(document, "property", "og:")),
+        ("twitter", twitter),
+    ])
+}
+
+/// where a `<meta http-equiv=refresh content="0; url=...">` sends the browser, if anywhere
+pub fn refresh(document: &Html, base: &Url) -> Option<Url> {
+    let target = Regex::new(r#"(?i)^\s*[\d.]*\s*[;,]\s*(?:url\s*=\s*)?['"]?([^'"]*)"#).unwrap();
+    document
+        .select(&select("meta[http-equiv][content]"))
+        .filter(|meta| {
+            let equiv = meta.value().attr("http-equiv").unwrap_or_default();
+            equiv.eq_ignore_ascii_case("refresh")
+        })
+        .find_map(|meta| {
+            let content = meta.value().attr("content")?;
+            let href = target.captures(content)?.get(1)?.as_str().trim();
+            (!href.is_empty()).then(|| base.join(href).ok())?
+        })
+}
+
+/// `rel` and url of every `<https://...>; rel="..."` entry of a Link header
+fn link_header(page: &Page) -> Vec<(String, String)> {
+    page.headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|link| {
+            let (target, params) = link.trim().split_once(';')?;
+            let target = target.trim().strip_prefix('<')?.strip_suffix('>')?;
+            let rel = params.split(';').find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                (key.trim() == "rel").then(|| value.trim().trim_matches('"').to_string())
+            })?;
+            Some((rel, page.url.join(target).ok()?.to_string()))
+        })
+        .collect()
+}
+
+/// canonical url, hreflang alternates and AMP version of the page, one per line
+/// as `canonical\turl`, `alternate\tlanguage\turl` and `amphtml\turl`
+pub fn canonical_links(page: &Page) -> Vec<String> {
+    let document = Html::parse_document(&page.body);
+    let mut lines = Vec::new();
+    let resolve = |href: &str| page.url.join(href.trim()).ok().map(String::from);
+    for link in document.select(&select("link[rel][href]")) {
+        let rels = link
+            .value()
+            .attr("rel")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let href = match link.value().attr("href").and_then(resolve) {
+            Some(href) => href,
+            None => continue,
+        };
+        for rel in rels.split_whitespace() {
+            match (rel, link.value().attr("hreflang")) {
+                ("canonical", _) => lines.push(format!("canonical\t{}", href)),
+                ("alternate", Some(language)) => {
+                    lines.push(format!("alternate\t{}\t{}", language, href))
+                }
+                ("amphtml", _) => lines.push(format!("amphtml\t{}", href)),
+                _ => {}
+            }
+        }
+    }
+    for (rel, href) in link_header(page) {
+        if rel.eq_ignore_ascii_case("canonical") {
+            lines.push(format!("canonical\t{}", href));
+        }
+    }
+    lines
+}
+
+pub async fn run(client: &Client, args: MetaArgs) -> Result<(), Error> {
+    let page = download(client, &args.url).await?;
+    let document = Html::parse_document(&page.body);
+    println!("{}", metadata(&document, &page.url));
+    Ok(())
+}
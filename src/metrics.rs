@@ -0,0 +1,195 @@
+//! counters for watch and daemon jobs, served in the Prometheus text format
+
+use std::{
+    fmt::Write as _,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::Error;
+
+#[derive(Default)]
+struct Job {
+    name: String,
+    requests: u64,
+    failures: u64,
+    /// total and last response time in seconds
+    response_seconds: f64,
+    last_response_seconds: f64,
+    last_success: Option<f64>,
+    last_change: Option<f64>,
+}
+
+static JOBS: Mutex<Vec<Job>> = Mutex::new(Vec::new());
+
+fn now() -> f64 {
+    let since = SystemTime::now().duration_since(UNIX_EPOCH);
+    since.unwrap_or_default().as_secs_f64()
+}
+
+fn update(name: &str, update: impl FnOnce(&mut Job)) {
+    let mut jobs = JOBS.lock().unwrap();
+    match jobs.iter_mut().find(|job| job.name == name) {
+        Some(job) => update(job),
+        None => {
+            let mut job = Job {
+                name: name.to_string(),
+                ..Job::default()
+            };
+            update(&mut job);
+            jobs.push(job);
+        }
+    }
+}
+
+/// makes the job show up before its first request
+pub fn register(name: &str) {
+    update(name, |_| {});
+}
+
+pub fn request(name: &str, elapsed: Duration, success: bool) {
+    update(name, |job| {
+        job.requests += 1;
+        job.response_seconds += elapsed.as_secs_f64();
+        job.last_response_seconds = elapsed.as_secs_f64();
+        if success {
+            job.last_success = Some(now());
+        } else {
+            job.failures += 1;
+        }
+    });
+}
+
+pub fn changed(name: &str) {
+    update(name, |job| job.last_change = Some(now()));
+}
+
+/// `"` `\` and newlines escaped for a label value
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// a metric family and how to read its samples from a job
+type Family = (
+    &'static str,
+    &'static str,
+    &'static str,
+    Vec<(&'static str, Sample)>,
+);
+type Sample = fn(&Job) -> Option<f64>;
+
+fn render() -> String {
+    let jobs = JOBS.lock().unwrap();
+    let families: [Family; 6] = [
+        (
+            "scrape_requests_total",
+            "counter",
+            "Pages fetched",
+            vec![("", |job| Some(job.requests as f64))],
+        ),
+        (
+            "scrape_failures_total",
+            "counter",
+            "Fetches that failed or got an error status",
+            vec![("", |job| Some(job.failures as f64))],
+        ),
+        (
+            "scrape_response_seconds",
+            "summary",
+            "Time spent fetching",
+            vec![
+                ("_sum", |job| Some(job.response_seconds)),
+                ("_count", |job| Some(job.requests as f64)),
+            ],
+        ),
+        (
+            "scrape_last_response_seconds",
+            "gauge",
+            "How long the last fetch took",
+            vec![("", |job| {
+                (job.requests > 0).then_some(job.last_response_seconds)
+            })],
+        ),
+        (
+            "scrape_last_success_timestamp_seconds",
+            "gauge",
+            "When the page was last fetched successfully",
+            vec![("", |job| job.last_success)],
+        ),
+        (
+            "scrape_last_change_timestamp_seconds",
+            "gauge",
+            "When the extracted content last changed",
+            vec![("", |job| job.last_change)],
+        ),
+    ];
+    let mut text = String::new();
+    for (name, kind, help, samples) in families {
+        let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+        for job in jobs.iter() {
+            for (suffix, sample) in &samples {
+                if let Some(value) = sample(job) {
+                    let job = label(&job.name);
+                    let _ = writeln!(text, "{}{}{{job=\"{}\"}} {}", name, suffix, job, value);
+                }
+            }
+        }
+    }
+    text
+}
+
+async fn respond(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        match stream.read(&mut buffer).await? {
+            0 => break,
+            read => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let (status, body) = match path {
+        "/metrics" | "/" => ("200 OK", render()),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// answers `GET /metrics` in the background until the process exits
+pub async fn serve(address: SocketAddr) -> Result<(), Error> {
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+    eprintln!("Serving metrics on http://{}/metrics", address);
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(async move {
+                        if let Err(error) = respond(stream).await {
+                            eprintln!("Failed to answer metrics request: {}", error);
+                        }
+                    });
+                }
+                Err(error) => eprintln!("Failed to accept metrics connection: {}", error),
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use reqwest::{Client, Url};
+use scraper::{Html, Node, Selector};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    crawl::resolve,
+    download, parse_selector, rate,
+    robots::{self, RobotsCache},
+    Error,
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct MirrorArgs {
+    /// where to start mirroring
+    url: String,
+
+    /// only keep these parts of every page
+    selector: Option<String>,
+
+    /// how many links away from the start page to follow
+    #[clap(short, long, default_value = "2")]
+    depth: usize,
+
+    /// directory to save the mirror to
+    #[clap(short, long, default_value = ".")]
+    output: PathBuf,
+
+    /// mirror pages even if robots.txt disallows it
+    #[clap(long)]
+    ignore_robots: bool,
+}
+
+/// elements and the attribute they reference other documents with
+pub const REFERENCES: [(&str, &str); 6] = [
+    ("a[href]", "href"),
+    ("link[href]", "href"),
+    ("img[src]", "src"),
+    ("script[src]", "src"),
+    ("source[src]", "src"),
+    ("iframe[src]", "src"),
+];
+
+pub fn is_asset(selector: &str, element: &scraper::node::Element) -> bool {
+    match selector {
+        "a[href]" | "iframe[src]" => false,
+        "link[href]" => element
+            .attr("rel")
+            .unwrap_or_default()
+            .split_ascii_whitespace()
+            .any(|rel| matches!(rel, "stylesheet" | "icon" | "preload")),
+        _ => true,
+    }
+}
+
+/// where a url is saved, relative to the output directory
+pub fn local_path(url: &Url, is_page: bool) -> PathBuf {
+    let mut path = PathBuf::from(match url.port() {
+        Some(port) => format!("{}_{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    });
+    let segments: Vec<_> = url.path_segments().map_or(vec![], |s| s.collect());
+    for segment in &segments {
+        if !segment.is_empty() && *segment != ".." && *segment != "." {
+            path.push(segment);
+        }
+    }
+    let last = segments.last().copied().unwrap_or_default();
+    if last.is_empty() {
+        path.push("index.html");
+    } else if is_page && !last.contains('.') {
+        path.set_extension("html");
+    }
+    if let Some(query) = url.query() {
+        let query: String = query
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match path.extension() {
+            Some(ext) => format!("{}_{}.{}", stem, query, ext.to_string_lossy()),
+            None => format!("{}_{}", stem, query),
+        };
+        path.set_file_name(name);
+    }
+    path
+}
+
+/// `to` as seen from the directory containing `from`
+fn relative(from: &Path, to: &Path) -> String {
+    let from: Vec<_> = from.parent().unwrap_or(from).components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![".."; from.len() - common];
+    let rest: Vec<_> = to[common..]
+        .iter()
+        .map(|c| c.as_os_str().to_str().unwrap_or_default())
+        .collect();
+    parts.extend(rest);
+    parts.join("/")
+}
+
+/// keeps the head and only the selected parts of the body
+fn filtered(document: &Html, selector: &Selector) -> Html {
+    let head = Selector::parse("head").unwrap();
+    let head = document
+        .select(&head)
+        .next()
+        .map(|head| head.inner_html())
+        .unwrap_or_default();
+    let body: String = document.select(selector).map(|node| node.html()).collect();
+    Html::parse_document(&format!(
+        "<!DOCTYPE html><html><head>{}</head><body>{}</body></html>",
+        head, body
+    ))
+}
+
+pub async fn mirror(client: &Client, args: MirrorArgs) -> Result<(), Error> {
+    let start = Url::parse(&args.url).map_err(|_| Error::InvalidUrl(args.url.clone()))?;
+    let selector = args.selector.as_deref().map(parse_selector).transpose()?;
+    let references: Vec<_> = REFERENCES
+        .iter()
+        .map(|(css, attr)| (*css, Selector::parse(css).unwrap(), *attr))
+        .collect();
+    let robots = (!args.ignore_robots).then(RobotsCache::default);
+
+    let mut pages = Vec::new();
+    let mut assets = HashSet::new();
+    let mut seen = HashSet::from([start.clone()]);
+    let mut frontier = VecDeque::from([(start.clone(), 0)]);
+
+    while let Some((url, depth)) = frontier.pop_front() {
+        if let Some(robots) = &robots {
+            let robots = match robots.get(client, &url).await {
+                Ok(robots) => robots,
+                Err(error) => {
+                    eprintln!("Skipping '{}', failed to fetch robots.txt: {}", url, error);
+                    continue;
+                }
+            };
+            if !robots.is_allowed(robots::AGENT, &robots::robots_path(&url)) {
+                eprintln!("Skipping '{}', disallowed by robots.txt", url);
+                continue;
+            }
+        }
+        let page = match download(client, url.as_str()).await {
+            Ok(page) if page.status.is_success() => page,
+            Ok(page) => {
+                eprintln!("Skipping '{}', got {}", url, page.status);
+                continue;
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                continue;
+            }
+        };
+        let document = Html::parse_document(&page.body);
+
+        for (css, selector, attr) in &references {
+            for element in document.select(selector) {
+                let target = element
+                    .value()
+                    .attr(attr)
+                    .and_then(|r| resolve(&page.url, r));
+                let target = match target {
+                    Some(target) if target.host_str() == start.host_str() => target,
+                    _ => continue,
+                };
+                if is_asset(css, element.value()) {
+                    assets.insert(target);
+                } else if depth < args.depth && seen.insert(target.clone()) {
+                    frontier.push_back((target, depth + 1));
+                }
+            }
+        }
+        pages.push((url, page.url, document));
+    }
+
+    let mut saved: HashMap<Url, PathBuf> = HashMap::new();
+    for (url, final_url, _) in &pages {
+        saved.insert(url.clone(), local_path(final_url, true));
+        saved.insert(final_url.clone(), local_path(final_url, true));
+    }
+
+    for asset in assets {
+        if saved.contains_key(&asset) {
+            continue;
+        }
+        rate::wait(&asset).await;
+        let bytes = match client.get(asset.clone()).send().await {
+            Ok(res) if res.status().is_success() => res.bytes().await?,
+            _ => {
+                eprintln!("Failed to GET from '{}'", asset);
+                continue;
+            }
+        };
+        let path = local_path(&asset, false);
+        let target = args.output.join(&path);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(target, bytes)?;
+        saved.insert(asset, path);
+    }
+
+    for (_, final_url, document) in pages {
+        let mut document = match &selector {
+            Some(selector) => filtered(&document, selector),
+            None => document,
+        };
+        let path = local_path(&final_url, true);
+        rewrite_links(&mut document, &final_url, &path, &saved);
+
+        let target = args.output.join(&path);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(
+            &target,
+            format!("<!DOCTYPE html>\n{}", document.root_element().html()),
+        )?;
+        println!("{}", target.display());
+    }
+
+    Ok(())
+}
+
+/// points references at the mirrored copies, everything else at the absolute url
+fn rewrite_links(document: &mut Html, base: &Url, path: &Path, saved: &HashMap<Url, PathBuf>) {
+    let ids: Vec<_> = document.tree.nodes().map(|node| node.id()).collect();
+    for id in ids {
+        let mut node = document.tree.get_mut(id).unwrap();
+        let element = match node.value() {
+            Node::Element(element) => element,
+            _ => continue,
+        };
+        for (name, value) in element.attrs.iter_mut() {
+            if !matches!(&*name.local, "href" | "src") {
+                continue;
+            }
+            let absolute = match base.join(value) {
+                Ok(absolute) => absolute,
+                Err(_) => continue,
+            };
+            let mut key = absolute.clone();
+            key.set_fragment(None);
+            let rewritten = match saved.get(&key) {
+                Some(local) => match absolute.fragment() {
+                    Some(fragment) => format!("{}#{}", relative(path, local), fragment),
+                    None => relative(path, local),
+                },
+                None if value.starts_with('#') => continue,
+                None => absolute.to_string(),
+            };
+            *value = rewritten.into();
+        }
+    }
+}
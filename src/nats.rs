@@ -0,0 +1,234 @@
+//! publishing every extracted value to a NATS subject as it's printed, with the plain text
+//! protocol NATS servers speak over TCP
+
+use reqwest::Url;
+use std::{collections::VecDeque, io, sync::Mutex, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
+    sync::{mpsc, oneshot},
+};
+
+use crate::{json::Json, log};
+
+/// what the connection task is asked to do
+enum Command {
+    Publish(String),
+    /// answered once the server processed everything before, with its last error if any
+    Flush(oneshot::Sender<Result<(), String>>),
+}
+
+static PUBLISHER: Mutex<Option<mpsc::UnboundedSender<Command>>> = Mutex::new(None);
+
+/// how often to try to get a lost connection back before giving up
+const RECONNECTS: u32 = 3;
+
+struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    /// what was read of the next line, kept when waiting for it is cancelled by a publish
+    line: Vec<u8>,
+}
+
+impl Connection {
+    /// a line from the server, without the line break
+    async fn line(&mut self) -> io::Result<String> {
+        if self.reader.read_until(b'\n', &mut self.line).await? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "connection closed",
+            ));
+        }
+        let line = String::from_utf8_lossy(&self.line).trim_end().to_string();
+        self.line.clear();
+        Ok(line)
+    }
+
+    /// the server's answer to what was sent, skipping `+OK`s and answering its `PING`s
+    async fn expect(&mut self, expected: &str) -> io::Result<String> {
+        loop {
+            let line = self.line().await?;
+            if line == "PING" {
+                self.writer.write_all(b"PONG\r\n").await?;
+            } else if let Some(error) = error(&line) {
+                return Err(io::Error::other(error));
+            } else if line.starts_with(expected) {
+                return Ok(line);
+            }
+        }
+    }
+
+    /// connects to the server and logs in
+    async fn open(url: &Url) -> Result<Self, String> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| format!("No host in '{}'", url))?;
+        let port = url.port().unwrap_or(4222);
+        let failed = |e: io::Error| format!("Failed to connect to '{}:{}': {}", host, port, e);
+        let (reader, writer) = TcpStream::connect((host, port))
+            .await
+            .map_err(failed)?
+            .into_split();
+        let mut connection = Connection {
+            reader: BufReader::new(reader),
+            writer,
+            line: Vec::new(),
+        };
+        connection.expect("INFO").await.map_err(failed)?;
+
+        let mut options = vec![
+            ("verbose", false.into()),
+            ("pedantic", false.into()),
+            ("name", "scrape".into()),
+            ("lang", "rust".into()),
+            ("version", env!("CARGO_PKG_VERSION").into()),
+        ];
+        match (url.username(), url.password()) {
+            ("", _) => {}
+            (token, None) => options.push(("auth_token", token.into())),
+            (user, Some(password)) => {
+                options.push(("user", user.into()));
+                options.push(("pass", password.into()));
+            }
+        }
+        let connect = format!("CONNECT {}\r\nPING\r\n", Json::object(options));
+        connection
+            .writer
+            .write_all(connect.as_bytes())
+            .await
+            .map_err(failed)?;
+        connection.expect("PONG").await.map_err(failed)?;
+        Ok(connection)
+    }
+}
+
+/// the message of an `-ERR` line
+fn error(line: &str) -> Option<String> {
+    let error = line.strip_prefix("-ERR")?;
+    Some(error.trim().trim_matches('\'').to_string())
+}
+
+/// connects to `nats://[user:password@ or token@]host[:port]` to publish to the subject
+pub async fn connect(url: &Url, subject: &str) -> Result<(), String> {
+    if url.scheme() != "nats" {
+        return Err(format!("'{}' is not a nats:// url", url));
+    }
+    let connection = Connection::open(url).await?;
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(run(url.clone(), subject.to_string(), connection, receiver));
+    *PUBLISHER.lock().unwrap() = Some(sender);
+    Ok(())
+}
+
+/// writes what's published and answers the server's `PING`s for as long as the run lasts, so the
+/// server doesn't drop the connection as stale, and reconnects if it's lost
+async fn run(
+    url: Url,
+    subject: String,
+    mut connection: Connection,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+) {
+    // flushes waiting for the `PONG` to their `PING`
+    let mut flushes: VecDeque<oneshot::Sender<Result<(), String>>> = VecDeque::new();
+    let mut rejected = None;
+    // what couldn't be written before the connection was lost
+    let mut unsent: Option<String> = None;
+    loop {
+        let result = async {
+            if let Some(data) = &unsent {
+                connection.writer.write_all(data.as_bytes()).await?;
+                unsent = None;
+            }
+            loop {
+                tokio::select! {
+                    command = commands.recv() => {
+                        let data = match command {
+                            Some(Command::Publish(line)) => {
+                                format!("PUB {} {}\r\n{}\r\n", subject, line.len(), line)
+                            }
+                            Some(Command::Flush(flushed)) => {
+                                flushes.push_back(flushed);
+                                "PING\r\n".to_string()
+                            }
+                            None => return Ok(()),
+                        };
+                        if let Err(error) = connection.writer.write_all(data.as_bytes()).await {
+                            unsent = Some(data);
+                            return Err(error);
+                        }
+                    }
+                    line = connection.line() => {
+                        let line = line?;
+                        if line == "PING" {
+                            connection.writer.write_all(b"PONG\r\n").await?;
+                        } else if line == "PONG" {
+                            if let Some(flushed) = flushes.pop_front() {
+                                let _ = flushed.send(rejected.take().map_or(Ok(()), Err));
+                            }
+                        } else if let Some(error) = error(&line) {
+                            log::warn(format!("Failed to publish to '{}': {}", subject, error));
+                            rejected = Some(error);
+                        }
+                    }
+                }
+            }
+        }
+        .await;
+        let error: io::Error = match result {
+            Ok(()) => return,
+            Err(error) => error,
+        };
+        log::warn(format!("Lost the connection to '{}': {}", url, error));
+        let mut reconnected = None;
+        for attempt in 1..=RECONNECTS {
+            tokio::time::sleep(Duration::from_secs(attempt.into())).await;
+            match Connection::open(&url).await {
+                Ok(connection) => {
+                    reconnected = Some(connection);
+                    break;
+                }
+                Err(error) => log::warn(error),
+            }
+        }
+        connection = match reconnected {
+            Some(connection) => connection,
+            None => {
+                log::error(format!("Gave up publishing to '{}'", url));
+                *PUBLISHER.lock().unwrap() = None;
+                for flushed in flushes {
+                    let _ = flushed.send(Err(format!("lost the connection to '{}'", url)));
+                }
+                return;
+            }
+        };
+        // the server won't answer the pings it didn't get
+        let pings = "PING\r\n".repeat(flushes.len());
+        unsent = Some(unsent.unwrap_or_default() + &pings);
+    }
+}
+
+/// sends a result to the subject if `--publish` connected to a server
+pub fn publish(line: &str) {
+    if let Some(publisher) = PUBLISHER.lock().unwrap().as_ref() {
+        let _ = publisher.send(Command::Publish(line.to_string()));
+    }
+}
+
+/// waits for the server to have processed everything published, to report rejected messages
+pub async fn flush() -> Result<(), String> {
+    let publisher = PUBLISHER.lock().unwrap().clone();
+    match publisher {
+        Some(publisher) => {
+            let (flushed, done) = oneshot::channel();
+            publisher
+                .send(Command::Flush(flushed))
+                .map_err(|_| "the connection is gone".to_string())?;
+            done.await
+                .map_err(|_| "the connection is gone".to_string())?
+        }
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,47 @@
+//! desktop notifications through whatever the platform ships with
+
+use std::process::Command;
+
+#[cfg(target_os = "macos")]
+fn command(title: &str, body: &str) -> Command {
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(format!(
+        "display notification \"{}\" with title \"{}\"",
+        quote(body),
+        quote(title)
+    ));
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn command(title: &str, body: &str) -> Command {
+    let quote = |s: &str| s.replace('\'', "''");
+    let script = format!(
+        "[reflection.assembly]::loadwithpartialname('System.Windows.Forms') | Out-Null; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+         $n.ShowBalloonTip(10000, '{}', '{}', 'Info'); Start-Sleep 10",
+        quote(title),
+        quote(body)
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", &script]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn command(title: &str, body: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name=scrape", title, body]);
+    command
+}
+
+/// shows a notification, failures are reported but not fatal
+pub fn send(title: &str, body: &str) {
+    match command(title, body).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Failed to show notification, {}", status),
+        Err(error) => eprintln!("Failed to show notification: {}", error),
+    }
+}
@@ -0,0 +1,113 @@
+use reqwest::Url;
+use scraper::{Html, Selector};
+use std::str::FromStr;
+
+/// link texts that usually lead to the next page
+const NEXT_TEXTS: [&str; 7] = ["next", "next page", "next »", "next ›", "»", "›", "more"];
+
+/// the page after this one, from `rel=next` or a "next" looking anchor
+pub fn next_page(document: &Html, base: &Url) -> Option<Url> {
+    let rel_next = Selector::parse("link[rel~=next][href], a[rel~=next][href]").unwrap();
+    let anchors = Selector::parse("a[href]").unwrap();
+
+    let href = document
+        .select(&rel_next)
+        .next()
+        .or_else(|| {
+            document.select(&anchors).find(|a| {
+                let text = a.text().collect::<String>().trim().to_lowercase();
+                let label = a
+                    .value()
+                    .attr("aria-label")
+                    .unwrap_or_default()
+                    .to_lowercase();
+                NEXT_TEXTS.contains(&text.as_str())
+                    || label.contains("next")
+                    || a.value()
+                        .classes()
+                        .any(|class| class.eq_ignore_ascii_case("next"))
+            })
+        })?
+        .value()
+        .attr("href")?;
+
+    let mut next = base.join(href).ok()?;
+    next.set_fragment(None);
+    Some(next)
+}
+
+/// numbered pages, either as a query parameter (`page={1..N}`)
+/// or substituted for `{}` in the url (`{1..10}`)
+#[derive(Debug, Clone)]
+pub struct Paginate {
+    param: Option<String>,
+    from: u64,
+    to: Option<u64>,
+}
+
+impl FromStr for Paginate {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid pagination '{}', expected e.g. 'page={{1..N}}'",
+                pattern
+            )
+        };
+        let (param, range) = match pattern.split_once('=') {
+            Some((param, range)) => (Some(param.to_string()), range),
+            None => (None, pattern),
+        };
+        let range = range
+            .strip_prefix('{')
+            .and_then(|r| r.strip_suffix('}'))
+            .ok_or_else(invalid)?;
+        let (from, to) = range.split_once("..").ok_or_else(invalid)?;
+        Ok(Paginate {
+            param,
+            from: from.parse().map_err(|_| invalid())?,
+            to: match to {
+                "N" | "" => None,
+                to => Some(to.parse().map_err(|_| invalid())?),
+            },
+        })
+    }
+}
+
+impl Paginate {
+    pub fn is_bounded(&self) -> bool {
+        self.to.is_some()
+    }
+
+    pub fn pages(&self) -> impl Iterator<Item = u64> {
+        self.from..=self.to.unwrap_or(u64::MAX)
+    }
+
+    /// the url of page `number`
+    pub fn url(&self, template: &str, number: u64) -> Result<Url, String> {
+        let invalid = || format!("Invalid URL '{}'", template);
+        match &self.param {
+            Some(param) => {
+                let mut url = Url::parse(template).map_err(|_| invalid())?;
+                let pairs: Vec<(String, String)> = url
+                    .query_pairs()
+                    .filter(|(key, _)| key != param)
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect();
+                url.query_pairs_mut()
+                    .clear()
+                    .extend_pairs(pairs)
+                    .append_pair(param, &number.to_string());
+                Ok(url)
+            }
+            None if template.contains("{}") => {
+                Url::parse(&template.replace("{}", &number.to_string())).map_err(|_| invalid())
+            }
+            None => Err(format!(
+                "'{}' has no {{}} to put the page number in",
+                template
+            )),
+        }
+    }
+}
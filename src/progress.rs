@@ -0,0 +1,138 @@
+//! download progress on stderr: a bar per download, and in batches a bar per concurrent
+//! download under a counter of finished pages
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::{ops::Deref, sync::Mutex, thread};
+
+struct Batch {
+    pages: ProgressBar,
+    slots: Vec<ProgressBar>,
+    /// the slots that no download is using
+    idle: Vec<usize>,
+    drawing: thread::JoinHandle<()>,
+}
+
+static BATCH: Mutex<Option<Batch>> = Mutex::new(None);
+
+fn download_style() -> ProgressStyle {
+    ProgressStyle::default_bar()
+        .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+        .progress_chars("█>-")
+}
+
+fn idle_style() -> ProgressStyle {
+    ProgressStyle::default_bar().template("{msg}")
+}
+
+/// shows a bar per download slot and a finished/total counter until `finish_batch`, the total
+/// grows with `queued` if it isn't known
+pub fn start_batch(concurrency: usize, total: Option<usize>) {
+    if crate::args().quiet {
+        return;
+    }
+    let multi = MultiProgress::new();
+    let pages = multi.add(ProgressBar::new(total.unwrap_or(0) as u64));
+    pages.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] [{wide_bar:.green}] {pos}/{len} pages ({per_sec})")
+            .progress_chars("█>-"),
+    );
+    let slots: Vec<_> = (0..concurrency.max(1))
+        .map(|_| {
+            let slot = multi.add(ProgressBar::new(0));
+            slot.set_style(idle_style());
+            slot
+        })
+        .collect();
+    let drawing = thread::spawn(move || {
+        let _ = multi.join_and_clear();
+    });
+    *BATCH.lock().unwrap() = Some(Batch {
+        pages,
+        idle: (0..slots.len()).rev().collect(),
+        slots,
+        drawing,
+    });
+}
+
+/// counts another page in a batch of unknown size
+pub fn queued() {
+    if let Some(batch) = BATCH.lock().unwrap().as_ref() {
+        batch.pages.inc_length(1);
+    }
+}
+
+/// counts a page of the batch as done
+pub fn page_done() {
+    if let Some(batch) = BATCH.lock().unwrap().as_ref() {
+        batch.pages.inc(1);
+    }
+}
+
+pub fn finish_batch() {
+    if let Some(batch) = BATCH.lock().unwrap().take() {
+        for slot in batch.slots {
+            slot.finish_and_clear();
+        }
+        batch.pages.finish_and_clear();
+        let _ = batch.drawing.join();
+    }
+}
+
+/// the bar of one download
+pub struct Download {
+    progress_bar: ProgressBar,
+    slot: Option<usize>,
+}
+
+impl Deref for Download {
+    type Target = ProgressBar;
+
+    fn deref(&self) -> &ProgressBar {
+        &self.progress_bar
+    }
+}
+
+/// a bar for downloading `total_size` bytes from the url, in a free slot during batches
+pub fn download(total_size: u64, url: &str) -> Download {
+    let hidden = Download {
+        progress_bar: ProgressBar::hidden(),
+        slot: None,
+    };
+    let download = match BATCH.lock().unwrap().as_mut() {
+        Some(batch) => match batch.idle.pop() {
+            Some(slot) => Download {
+                progress_bar: batch.slots[slot].clone(),
+                slot: Some(slot),
+            },
+            None => return hidden,
+        },
+        None if crate::args().quiet => return hidden,
+        None => Download {
+            progress_bar: ProgressBar::new(total_size),
+            slot: None,
+        },
+    };
+    download.set_style(download_style());
+    download.set_length(total_size);
+    download.set_position(0);
+    download.reset_elapsed();
+    download.reset_eta();
+    download.set_message(format!("Downloading {}", url));
+    download
+}
+
+/// clears the bar, or frees its slot for the next download
+impl Drop for Download {
+    fn drop(&mut self) {
+        let mut batch = BATCH.lock().unwrap();
+        match (batch.as_mut(), self.slot) {
+            (Some(batch), Some(slot)) => {
+                self.set_style(idle_style());
+                self.set_message("");
+                batch.idle.push(slot);
+            }
+            _ => self.finish_and_clear(),
+        }
+    }
+}
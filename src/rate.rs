@@ -0,0 +1,155 @@
+//! `--rate` and `--rate-per-host`: token buckets every request of a run waits on, so a site's
+//! documented limits hold whatever the concurrency
+
+use reqwest::Url;
+use std::{collections::BTreeMap, str::FromStr, sync::Mutex, time::Duration};
+use tokio::time::{sleep_until, Instant};
+
+use crate::{log, parse_duration};
+
+/// at most `requests` every `per`, e.g. `2/s`, `30/m` or `1/500ms`
+#[derive(Debug, Clone, Copy)]
+pub struct Rate {
+    requests: u32,
+    per: Duration,
+}
+
+impl Rate {
+    /// the time a token takes to come back
+    fn interval(&self) -> Duration {
+        self.per / self.requests
+    }
+}
+
+impl FromStr for Rate {
+    type Err = String;
+
+    fn from_str(rate: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid rate '{}', expected e.g. '2/s' or '30/m'", rate);
+        let (requests, per) = rate.split_once('/').ok_or_else(invalid)?;
+        let requests = requests.trim().parse().map_err(|_| invalid())?;
+        let per = per.trim();
+        // `s` is short for `1s`
+        let per = match per.starts_with(|c: char| c.is_ascii_digit()) {
+            true => parse_duration(per),
+            false => parse_duration(&format!("1{}", per)),
+        }
+        .map_err(|_| invalid())?;
+        if requests == 0 || per.is_zero() {
+            return Err(invalid());
+        }
+        Ok(Rate { requests, per })
+    }
+}
+
+/// holds a single token, so requests are spaced out evenly and never burst above the rate
+struct Bucket {
+    rate: Rate,
+    /// when the next token is there
+    next: Instant,
+}
+
+impl Bucket {
+    fn new(rate: Rate) -> Self {
+        Bucket {
+            rate,
+            next: Instant::now(),
+        }
+    }
+}
+
+struct Limits {
+    global: Option<Bucket>,
+    per_host: Option<Rate>,
+    hosts: BTreeMap<String, Bucket>,
+}
+
+static LIMITS: Mutex<Limits> = Mutex::new(Limits {
+    global: None,
+    per_host: None,
+    hosts: BTreeMap::new(),
+});
+
+/// limits the requests from now on
+pub fn limit(rate: Option<Rate>, per_host: Option<Rate>) {
+    let mut limits = LIMITS.lock().unwrap();
+    limits.global = rate.map(Bucket::new);
+    limits.per_host = per_host;
+}
+
+/// waits until the url's host's bucket and the global bucket both have a token, then takes them;
+/// nothing is reserved ahead, so a request held up by its host doesn't hold up other hosts
+pub async fn wait(url: &Url) {
+    let mut logged = false;
+    loop {
+        let now = Instant::now();
+        let at = {
+            let mut limits = LIMITS.lock().unwrap();
+            let limits = &mut *limits;
+            let host = match limits.per_host {
+                Some(rate) => Some(
+                    limits
+                        .hosts
+                        .entry(url.host_str().unwrap_or_default().to_string())
+                        .or_insert_with(|| Bucket::new(rate)),
+                ),
+                None => None,
+            };
+            let mut buckets: Vec<&mut Bucket> =
+                host.into_iter().chain(&mut limits.global).collect();
+            let at = buckets.iter().map(|b| b.next).fold(now, Instant::max);
+            if at <= now {
+                for bucket in &mut buckets {
+                    bucket.next = now + bucket.rate.interval();
+                }
+                return;
+            }
+            at
+        };
+        if !logged {
+            log::debug(format!("Waiting {:?} to request '{}'", at - now, url));
+            logged = true;
+        }
+        sleep_until(at).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rates() {
+        let interval = |rate: &str| rate.parse::<Rate>().unwrap().interval();
+        assert_eq!(interval("2/s"), Duration::from_millis(500));
+        assert_eq!(interval("30/m"), Duration::from_secs(2));
+        assert_eq!(interval("1/500ms"), Duration::from_millis(500));
+        assert!("0/s".parse::<Rate>().is_err());
+        assert!("2".parse::<Rate>().is_err());
+    }
+
+    #[tokio::test]
+    async fn a_busy_host_doesnt_hold_up_others() {
+        let global = "1/20ms".parse().ok();
+        limit(global, "1/400ms".parse().ok());
+        let a = Url::parse("http://a.example/").unwrap();
+        let b = Url::parse("http://b.example/").unwrap();
+        let started = Instant::now();
+        wait(&a).await;
+        let second_a = tokio::spawn({
+            let a = a.clone();
+            async move {
+                wait(&a).await;
+                started.elapsed()
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        wait(&b).await;
+        // b only waits for the global bucket, not behind a's second request
+        let b_at = started.elapsed();
+        assert!(b_at < Duration::from_millis(200), "{:?}", b_at);
+        let a_at = second_a.await.unwrap();
+        assert!(a_at >= Duration::from_millis(400), "{:?}", a_at);
+        limit(None, None);
+    }
+}
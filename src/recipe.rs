@@ -0,0 +1,320 @@
+use reqwest::{Client, Url};
+use scraper::{Html, Selector};
+use std::{
+    collections::HashSet,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    archive, dirs, download, extract, interrupt,
+    json::Json,
+    nats, page_fields,
+    pagination::{self, Paginate},
+    parse_duration, parse_selector, split_attribute, yaml, Error, Format, Page,
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct RunArgs {
+    /// yaml recipe describing urls, pagination, links to follow and fields to extract
+    recipe: PathBuf,
+}
+
+/// a named value to extract from every page
+struct Field {
+    name: String,
+    selector: Selector,
+    attribute: Option<String>,
+    /// collect every match instead of the first
+    all: bool,
+}
+
+impl Field {
+    fn parse(name: &str, spec: &Json) -> Result<Self, Error> {
+        let (selector, attribute, all) = match spec {
+            Json::String(spec) => {
+                let (selector, attribute) = split_attribute(spec);
+                (selector, attribute, false)
+            }
+            spec => (
+                spec.get("selector")
+                    .and_then(Json::as_str)
+                    .ok_or_else(|| format!("field '{}' needs a selector", name))?,
+                spec.get("attribute").and_then(Json::as_str),
+                spec.get("all").and_then(Json::as_bool).unwrap_or(false),
+            ),
+        };
+        Ok(Field {
+            name: name.to_string(),
+            selector: parse_selector(selector)?,
+            attribute: attribute.map(String::from),
+            all,
+        })
+    }
+
+    fn extract(&self, document: &Html) -> Json {
+        let mut values = extract(document, &self.selector, self.attribute.as_deref());
+        if self.all {
+            values.into()
+        } else if values.is_empty() {
+            Json::Null
+        } else {
+            values.swap_remove(0).into()
+        }
+    }
+}
+
+/// a scrape described in a yaml file, e.g.
+///
+/// ```yaml
+/// urls:
+///   - https://example.com/products
+/// paginate: page={1..N}
+/// until_empty: true
+/// follow: a.product@href
+/// fields:
+///   title: h1
+///   image: img.main@src
+/// format: jsonl
+/// delay: 500ms
+/// ```
+pub struct Recipe {
+    urls: Vec<String>,
+    paginate: Option<Paginate>,
+    until_empty: bool,
+    follow_next: Option<usize>,
+    follow: Option<(Selector, String)>,
+    fields: Vec<Field>,
+    format: Format,
+    delay: Option<Duration>,
+}
+
+impl Recipe {
+    pub fn parse(recipe: &Json) -> Result<Self, Error> {
+        let urls = match recipe.get("urls").or_else(|| recipe.get("url")) {
+            Some(Json::String(url)) => vec![url.clone()],
+            Some(Json::Array(urls)) => urls
+                .iter()
+                .filter_map(|url| url.as_str().map(String::from))
+                .collect(),
+            Some(_) => return Err("`urls` must be a list of urls".into()),
+            None => vec![],
+        };
+        let string = |key| recipe.get(key).and_then(Json::as_str);
+
+        let follow = match string("follow").map(split_attribute) {
+            Some((links, attribute)) => Some((
+                parse_selector(links)?,
+                attribute.unwrap_or("href").to_string(),
+            )),
+            None => None,
+        };
+        let fields = match recipe.get("fields") {
+            Some(Json::Object(fields)) => fields
+                .iter()
+                .map(|(name, spec)| Field::parse(name, spec))
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err("`fields` must map names to selectors".into()),
+            None => vec![],
+        };
+        let follow_next = match recipe.get("follow_next") {
+            Some(Json::Bool(true)) => Some(usize::MAX),
+            Some(Json::Number(max_pages)) => Some(*max_pages as usize),
+            _ => None,
+        };
+
+        Ok(Recipe {
+            urls,
+            paginate: string("paginate").map(str::parse).transpose()?,
+            until_empty: recipe
+                .get("until_empty")
+                .and_then(Json::as_bool)
+                .unwrap_or(false),
+            follow_next,
+            follow,
+            fields,
+            format: match string("format") {
+                Some("jsonl") | Some("json") => Format::Jsonl,
+                Some("text") | None => Format::Text,
+                Some(other) => return Err(format!("unknown format '{}'", other).into()),
+            },
+            delay: string("delay").map(parse_duration).transpose()?,
+        })
+    }
+
+    /// the page and how long it took
+    async fn fetch(&self, client: &Client, url: &str) -> Result<(Page, Duration), Error> {
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        let started = Instant::now();
+        let page = download(client, url).await?;
+        Ok((page, started.elapsed()))
+    }
+
+    /// prints one record for the page requested from the url, returns whether any field matched
+    fn emit(&self, url: &str, page: &Page, elapsed: Duration) -> bool {
+        let document = Html::parse_document(&page.body);
+        let values: Vec<_> = self.fields.iter().map(|f| f.extract(&document)).collect();
+        let matched = values.iter().any(|value| match value {
+            Json::Null => false,
+            Json::Array(values) => !values.is_empty(),
+            _ => true,
+        });
+
+        let line = match self.format {
+            Format::Jsonl => {
+                let mut record: Vec<_> = page_fields(url, page, elapsed)
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value))
+                    .collect();
+                record.extend(self.fields.iter().map(|f| f.name.clone()).zip(values));
+                Json::Object(record).to_string()
+            }
+            Format::Text => {
+                let mut line = page.url.to_string();
+                for value in values {
+                    line.push('\t');
+                    match value {
+                        Json::String(value) => line.push_str(&value),
+                        Json::Null => {}
+                        value => line.push_str(&value.to_string()),
+                    }
+                }
+                line
+            }
+        };
+        archive::add_result(&line);
+        nats::publish(&line);
+        println!("{}", line);
+        matched
+    }
+
+    /// emits the page or the pages it links to, returns how many results there were
+    async fn listing(&self, client: &Client, url: &str, page: &Page, elapsed: Duration) -> usize {
+        let (links, attribute) = match &self.follow {
+            Some(follow) => follow,
+            None => return self.emit(url, page, elapsed) as usize,
+        };
+        let targets: Vec<Url> = Html::parse_document(&page.body)
+            .select(links)
+            .filter_map(|link| link.value().attr(attribute))
+            .filter_map(|href| page.url.join(href).ok())
+            .collect();
+        for target in &targets {
+            if interrupt::interrupted() {
+                break;
+            }
+            match self.fetch(client, target.as_str()).await {
+                Ok((detail, elapsed)) => {
+                    self.emit(target.as_str(), &detail, elapsed);
+                }
+                Err(error) => eprintln!("{}", error),
+            }
+        }
+        targets.len()
+    }
+
+    pub async fn scrape(&self, client: &Client) -> Result<(), Error> {
+        for url in &self.urls {
+            if interrupt::interrupted() {
+                break;
+            }
+            if let Some(paginate) = &self.paginate {
+                if !paginate.is_bounded() && !self.until_empty {
+                    return Err("open ended `paginate` needs `until_empty`".into());
+                }
+                for number in paginate.pages() {
+                    let page_url = paginate.url(url, number)?;
+                    let (page, elapsed) = self.fetch(client, page_url.as_str()).await?;
+                    if !page.status.is_success() {
+                        break;
+                    }
+                    let matches = self
+                        .listing(client, page_url.as_str(), &page, elapsed)
+                        .await;
+                    if matches == 0 && self.until_empty || interrupt::interrupted() {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            let (mut page, mut elapsed) = self.fetch(client, url).await?;
+            let mut requested = url.clone();
+            let mut seen = HashSet::from([page.url.clone()]);
+            loop {
+                self.listing(client, &requested, &page, elapsed).await;
+                if interrupt::interrupted() {
+                    break;
+                }
+                let max_pages = self.follow_next.unwrap_or(1);
+                let document = Html::parse_document(&page.body);
+                let next = match pagination::next_page(&document, &page.url) {
+                    Some(next) if seen.len() < max_pages && seen.insert(next.clone()) => next,
+                    _ => break,
+                };
+                (page, elapsed) = self.fetch(client, next.as_str()).await?;
+                requested = next.to_string();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Recipe {
+    /// scrape these urls instead of the ones in the recipe
+    pub fn with_urls(self, urls: Vec<String>) -> Self {
+        Recipe { urls, ..self }
+    }
+}
+
+/// `auto` picks `~/.config/scrape/recipes/<domain>.yaml` for the url's domain or a parent domain,
+/// anything else is the path or name of a recipe
+pub fn find(recipe: &str, url: &str) -> Result<Recipe, Error> {
+    let path = PathBuf::from(recipe);
+    if recipe != "auto" && path.exists() {
+        return load(&path);
+    }
+    let dir = dirs::config_dir()
+        .ok_or("can't find the config directory")?
+        .join("recipes");
+    if recipe != "auto" {
+        return load(&dir.join(recipe).with_extension("yaml"));
+    }
+
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .ok_or_else(|| Error::InvalidUrl(url.to_string()))?;
+    let mut domain = host.as_str();
+    loop {
+        let candidate = dir.join(format!("{}.yaml", domain));
+        if candidate.exists() {
+            return load(&candidate);
+        }
+        match domain.split_once('.') {
+            Some((_, parent)) if parent.contains('.') => domain = parent,
+            _ => return Err(format!("no recipe for '{}' in {}", host, dir.display()).into()),
+        }
+    }
+}
+
+pub fn load(path: &std::path::Path) -> Result<Recipe, Error> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read recipe '{}': {}", path.display(), e))?;
+    let recipe = yaml::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Recipe::parse(&recipe).map_err(|e| match e {
+        Error::Other(message) => Error::Other(format!("{}: {}", path.display(), message)),
+        e => e,
+    })
+}
+
+pub async fn run(client: &Client, args: RunArgs) -> Result<(), Error> {
+    let recipe = load(&args.recipe)?;
+    if recipe.urls.is_empty() {
+        return Err(format!("{}: recipe has no `urls`", args.recipe.display()).into());
+    }
+    recipe.scrape(client).await
+}
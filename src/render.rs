@@ -0,0 +1,430 @@
+//! pages rendered by a headless browser over WebDriver, for sites that build their content
+//! with javascript
+
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE},
+    Client, Method, StatusCode, Url,
+};
+use std::{
+    fs,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+use crate::{json::Json, rate, Error, Page};
+
+/// how to render every page
+pub struct Options {
+    /// a selector to wait for before taking the document
+    pub wait_for: Option<String>,
+    /// how long a page may take to load, and the selector to match
+    pub timeout: Duration,
+    /// where to save a png of the rendered page
+    pub screenshot: Option<PathBuf>,
+    /// capture the whole page instead of the window
+    pub full_page: bool,
+    /// where to save the rendered page as pdf
+    pub pdf: Option<PathBuf>,
+    /// a running WebDriver server or grid to use instead of starting chromedriver
+    pub webdriver: Option<Url>,
+    /// try not to look like a headless browser
+    pub stealth: bool,
+    /// a javascript expression to evaluate in every page once it's loaded
+    pub eval: Option<String>,
+}
+
+/// a browser session, started on first use and ended by [`quit`]
+struct Session {
+    client: Client,
+    /// where the WebDriver server listens
+    base: Url,
+    id: String,
+    /// the chromedriver started for this session, none with a remote --webdriver
+    driver: Option<Child>,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::const_new(None);
+
+/// the proxy and user agent chrome uses, from the command line or the config profile
+static BROWSER: std::sync::Mutex<(Option<String>, Option<String>)> =
+    std::sync::Mutex::new((None, None));
+
+/// has chrome send its requests like scrape's own, through the proxy and with the user agent
+pub fn configure(proxy: Option<String>, user_agent: Option<String>) {
+    *BROWSER.lock().unwrap() = (proxy, user_agent);
+}
+
+/// runs before any script of a page with --stealth
+const STEALTH: &str = "
+Object.defineProperty(navigator, 'webdriver', {get: () => undefined});
+Object.defineProperty(navigator, 'languages', {get: () => ['en-US', 'en']});
+Object.defineProperty(navigator, 'plugins', {get: () => [1, 2, 3, 4, 5]});
+window.chrome = window.chrome || {runtime: {}};
+";
+
+/// sends a WebDriver command and returns the `value` of the response
+async fn command(
+    client: &Client,
+    method: Method,
+    url: Url,
+    body: Option<Json>,
+) -> Result<Json, Error> {
+    let request = client.request(method, url.clone());
+    let request = match body {
+        Some(body) => request
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string()),
+        None => request,
+    };
+    let res = request
+        .send()
+        .await
+        .map_err(|_| format!("Failed to reach WebDriver at '{}'", url))?;
+    let response = Json::parse(&res.text().await?)
+        .map_err(|e| format!("Invalid WebDriver response: {}", e))?;
+    let value = response.get("value").cloned().unwrap_or(Json::Null);
+    if let Some(error) = value.get("error").and_then(Json::as_str) {
+        let message = value.get("message").and_then(Json::as_str);
+        let message = message
+            .unwrap_or_default()
+            .lines()
+            .next()
+            .unwrap_or_default();
+        return Err(format!("WebDriver {}: {}", error, message).into());
+    }
+    Ok(value)
+}
+
+impl Session {
+    async fn start(options: &Options) -> Result<Self, Error> {
+        // not scrape's client, its proxy, headers and timeout are for sites, not the WebDriver
+        let client = &Client::builder()
+            .no_proxy()
+            .build()
+            .map_err(|e| format!("Failed to set up the WebDriver client: {}", e))?;
+        let (base, mut driver) = match &options.webdriver {
+            Some(url) => {
+                // keep the path of grids like `/wd/hub` when joining commands to it
+                let mut base = url.clone();
+                if !base.path().ends_with('/') {
+                    base.set_path(&format!("{}/", base.path()));
+                }
+                (base, None)
+            }
+            None => {
+                let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+                let driver = Command::new("chromedriver")
+                    .arg(format!("--port={}", port))
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .map_err(|_| {
+                        "Failed to start chromedriver, is it installed and on the PATH?"
+                    })?;
+                let base = Url::parse(&format!("http://127.0.0.1:{}/", port))
+                    .map_err(|e| e.to_string())?;
+                (base, Some(driver))
+            }
+        };
+        let id = match Session::create(client, &base, options.stealth).await {
+            Ok(id) => id,
+            Err(error) => {
+                if let Some(driver) = &mut driver {
+                    let _ = driver.kill();
+                }
+                return Err(error);
+            }
+        };
+        let session = Session {
+            client: client.clone(),
+            base,
+            id,
+            driver,
+        };
+        let timeout = options.timeout.as_millis() as u64;
+        let timeouts = Json::object([("pageLoad", timeout.into()), ("script", timeout.into())]);
+        session
+            .call(Method::POST, "timeouts", Some(timeouts))
+            .await?;
+        if options.stealth {
+            if let Err(error) = session.stealth().await {
+                eprintln!(
+                    "Failed to apply --stealth, only chrome supports it: {}",
+                    error
+                );
+            }
+        }
+        Ok(session)
+    }
+
+    /// a chrome DevTools command, through chromedriver
+    async fn devtools(&self, command: &str, params: Json) -> Result<Json, Error> {
+        let body = Json::object([("cmd", command.into()), ("params", params)]);
+        self.call(Method::POST, "goog/cdp/execute", Some(body))
+            .await
+    }
+
+    /// hides what gives a headless browser away: the user agent and client hints
+    /// naming `HeadlessChrome`, `navigator.webdriver`, and missing languages and plugins
+    async fn stealth(&self) -> Result<(), Error> {
+        let agent = self
+            .execute(
+                "return {agent: navigator.userAgent, \
+                 brands: navigator.userAgentData ? navigator.userAgentData.brands : [], \
+                 platform: navigator.userAgentData ? navigator.userAgentData.platform : ''};",
+            )
+            .await?;
+        let user_agent = agent
+            .get("agent")
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        let brands = match agent.get("brands") {
+            Some(Json::Array(brands)) => brands
+                .iter()
+                .map(|brand| {
+                    let name = brand
+                        .get("brand")
+                        .and_then(Json::as_str)
+                        .unwrap_or_default();
+                    Json::object([
+                        (
+                            "brand",
+                            name.replace("HeadlessChrome", "Google Chrome").into(),
+                        ),
+                        (
+                            "version",
+                            brand.get("version").cloned().unwrap_or(Json::Null),
+                        ),
+                    ])
+                })
+                .collect(),
+            _ => vec![],
+        };
+        let platform = agent
+            .get("platform")
+            .and_then(Json::as_str)
+            .unwrap_or_default();
+        let metadata = Json::object([
+            ("brands", Json::Array(brands)),
+            ("fullVersion", "".into()),
+            ("platform", platform.into()),
+            ("platformVersion", "".into()),
+            ("architecture", "".into()),
+            ("model", "".into()),
+            ("mobile", false.into()),
+        ]);
+        let user_agent = Json::object([
+            (
+                "userAgent",
+                user_agent.replace("HeadlessChrome", "Chrome").into(),
+            ),
+            ("acceptLanguage", "en-US,en".into()),
+            ("userAgentMetadata", metadata),
+        ]);
+        self.devtools("Network.setUserAgentOverride", user_agent)
+            .await?;
+        let script = Json::object([("source", STEALTH.into())]);
+        self.devtools("Page.addScriptToEvaluateOnNewDocument", script)
+            .await?;
+        Ok(())
+    }
+
+    /// waits for the WebDriver server to be ready and opens a headless chrome
+    async fn create(client: &Client, base: &Url, stealth: bool) -> Result<String, Error> {
+        let started = Instant::now();
+        loop {
+            let status = command(
+                client,
+                Method::GET,
+                base.join("status").map_err(|e| e.to_string())?,
+                None,
+            )
+            .await;
+            if status.is_ok_and(|status| status.get("ready") == Some(&Json::Bool(true))) {
+                break;
+            }
+            if started.elapsed() > Duration::from_secs(10) {
+                return Err(format!("WebDriver at '{}' is not ready", base).into());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let mut args: Vec<String> = ["--headless=new", "--disable-gpu", "--no-sandbox"]
+            .map(String::from)
+            .into();
+        let (proxy, user_agent) = BROWSER.lock().unwrap().clone();
+        if let Some(proxy) = proxy {
+            args.push(format!("--proxy-server={}", proxy));
+        }
+        if let Some(user_agent) = user_agent {
+            args.push(format!("--user-agent={}", user_agent));
+        }
+        let mut options = vec![];
+        if stealth {
+            args.extend([
+                "--disable-blink-features=AutomationControlled".to_string(),
+                "--lang=en-US".to_string(),
+            ]);
+            options.push(("excludeSwitches", vec!["enable-automation"].into()));
+        }
+        options.push(("args", args.into()));
+        let options = Json::object(options);
+        let capabilities = Json::object([(
+            "capabilities",
+            Json::object([(
+                "alwaysMatch",
+                Json::object([
+                    ("browserName", "chrome".into()),
+                    ("goog:chromeOptions", options),
+                ]),
+            )]),
+        )]);
+        let url = base.join("session").map_err(|e| e.to_string())?;
+        let session = command(client, Method::POST, url, Some(capabilities)).await?;
+        let id = session.get("sessionId").and_then(Json::as_str);
+        Ok(id.ok_or("WebDriver did not start a session")?.to_string())
+    }
+
+    /// a command on this session, like `url` or `source`
+    async fn call(&self, method: Method, path: &str, body: Option<Json>) -> Result<Json, Error> {
+        let url = self
+            .base
+            .join(&format!("session/{}/{}", self.id, path))
+            .map_err(|e| e.to_string())?;
+        command(&self.client, method, url, body).await
+    }
+
+    async fn execute(&self, script: &str) -> Result<Json, Error> {
+        let script = Json::object([("script", script.into()), ("args", Json::Array(vec![]))]);
+        self.call(Method::POST, "execute/sync", Some(script)).await
+    }
+
+    /// saves a png, grows the window to the size of the document first for `full_page`
+    async fn screenshot(&self, path: &Path, full_page: bool) -> Result<(), Error> {
+        let png = if full_page {
+            let window = self.call(Method::GET, "window/rect", None).await?;
+            let document = self
+                .execute(
+                    "const root = document.documentElement; \
+                     return {width: root.scrollWidth, height: root.scrollHeight};",
+                )
+                .await?;
+            self.resize(&document).await?;
+            let png = self.call(Method::GET, "screenshot", None).await;
+            self.resize(&window).await?;
+            png?
+        } else {
+            self.call(Method::GET, "screenshot", None).await?
+        };
+        let png = base64::decode(png.as_str().unwrap_or_default()).map_err(|e| e.to_string())?;
+        fs::write(path, png).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// prints the page to a pdf, with backgrounds like on screen
+    async fn print(&self, path: &Path) -> Result<(), Error> {
+        let settings = Json::object([("background", true.into())]);
+        let pdf = self.call(Method::POST, "print", Some(settings)).await?;
+        let pdf = base64::decode(pdf.as_str().unwrap_or_default()).map_err(|e| e.to_string())?;
+        fs::write(path, pdf).map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// sets the window to the `width` and `height` of `size`
+    async fn resize(&self, size: &Json) -> Result<Json, Error> {
+        let rect = Json::object(["width", "height"].map(|side| {
+            let length = size.get(side).cloned().unwrap_or(Json::Null);
+            (side, length)
+        }));
+        self.call(Method::POST, "window/rect", Some(rect)).await
+    }
+
+    /// polls until the selector matches an element, or the deadline passed
+    async fn wait_for(&self, selector: &str, deadline: Instant) -> Result<(), String> {
+        let find = Json::object([("using", "css selector".into()), ("value", selector.into())]);
+        loop {
+            match self.call(Method::POST, "element", Some(find.clone())).await {
+                Ok(_) => return Ok(()),
+                Err(error) if !error.to_string().contains("no such element") => {
+                    return Err(error.to_string())
+                }
+                Err(_) if Instant::now() >= deadline => {
+                    return Err(format!("'{}' didn't show up in time", selector))
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(250)).await,
+            }
+        }
+    }
+}
+
+/// loads the url in the browser and returns the document once scripts built it
+pub async fn fetch(url: &str, options: &Options) -> Result<Page, Error> {
+    let mut session = SESSION.lock().await;
+    if session.is_none() {
+        *session = Some(Session::start(options).await?);
+    }
+    let session = session.as_ref().unwrap();
+    if let Ok(url) = Url::parse(url) {
+        rate::wait(&url).await;
+    }
+    let deadline = Instant::now() + options.timeout;
+    let navigate = Json::object([("url", url.into())]);
+    session
+        .call(Method::POST, "url", Some(navigate))
+        .await
+        .map_err(|e| format!("Failed to render '{}': {}", url, e))?;
+    if let Some(selector) = &options.wait_for {
+        session
+            .wait_for(selector, deadline)
+            .await
+            .map_err(|e| format!("Failed to render '{}': {}", url, e))?;
+    }
+    let final_url = session.call(Method::GET, "url", None).await?;
+    let final_url = final_url.as_str().and_then(|url| Url::parse(url).ok());
+    let body = session.call(Method::GET, "source", None).await?;
+    let evaluated = match &options.eval {
+        Some(expression) => Some(
+            session
+                .execute(&format!("return ({}\n);", expression))
+                .await
+                .map_err(|e| format!("Failed to evaluate --eval in '{}': {}", url, e))?
+                .to_string(),
+        ),
+        None => None,
+    };
+    if let Some(path) = &options.screenshot {
+        session.screenshot(path, options.full_page).await?;
+    }
+    if let Some(path) = &options.pdf {
+        session.print(path).await?;
+    }
+    let body = body.as_str().unwrap_or_default().to_string();
+    Ok(Page {
+        url: final_url.ok_or_else(|| format!("Invalid URL '{}'", url))?,
+        // WebDriver doesn't tell, it only fails if the page doesn't load at all
+        status: StatusCode::OK,
+        headers: HeaderMap::new(),
+        size: body.len(),
+        body,
+        evaluated,
+    })
+}
+
+/// closes the browser, if one was started
+pub async fn quit() {
+    let session = SESSION.lock().await.take();
+    if let Some(mut session) = session {
+        let url = session.base.join(&format!("session/{}", session.id));
+        if let Ok(url) = url {
+            if let Err(error) = command(&session.client, Method::DELETE, url, None).await {
+                eprintln!("Failed to close the browser: {}", error);
+            }
+        }
+        if let Some(driver) = &mut session.driver {
+            let _ = driver.kill();
+            let _ = driver.wait();
+        }
+    }
+}
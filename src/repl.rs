@@ -0,0 +1,56 @@
+//! trying selectors and regexes against one downloaded page
+
+use regex::Regex;
+use scraper::Html;
+use std::io::{self, BufRead, Write};
+
+use crate::{extract, parse_selector, split_attribute, Error, Page};
+
+/// matches of `/regex/` in the body, its first group if it has one
+fn regex_matches(pattern: &str, body: &str) -> Result<Vec<String>, Error> {
+    let regex = Regex::new(pattern).map_err(|_| Error::InvalidRegex(pattern.to_string()))?;
+    Ok(scrape_core::regex_matches(&regex, body))
+}
+
+/// reads `selector@attribute` or `/regex/` lines until `:q` or the end of input
+pub fn run(page: &Page) -> io::Result<()> {
+    let document = Html::parse_document(&page.body);
+    eprintln!(
+        "{} loaded, type a selector (`a@href` for an attribute) or /regex/, :q to quit",
+        page.url
+    );
+    let stdin = io::stdin();
+    loop {
+        eprint!("> ");
+        io::stderr().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            eprintln!();
+            return Ok(());
+        }
+        let query = line.trim();
+        let values = match query {
+            "" => continue,
+            ":q" | ":quit" | "exit" => return Ok(()),
+            _ if query.len() > 1 && query.starts_with('/') && query.ends_with('/') => {
+                regex_matches(&query[1..query.len() - 1], &page.body)
+            }
+            _ => {
+                let (selector, attribute) = split_attribute(query);
+                parse_selector(selector).map(|selector| extract(&document, &selector, attribute))
+            }
+        };
+        match values {
+            Ok(values) => {
+                for value in &values {
+                    println!("{}", value);
+                }
+                match values.len() {
+                    1 => eprintln!("1 match"),
+                    n => eprintln!("{} matches", n),
+                }
+            }
+            Err(error) => eprintln!("{}", error),
+        }
+    }
+}
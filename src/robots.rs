@@ -0,0 +1,290 @@
+use reqwest::{Client, Url};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{rate, Error};
+
+/// product token matched against `User-agent` lines
+pub const AGENT: &str = "scrape";
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct RobotsArgs {
+    /// site whose robots.txt to inspect
+    url: String,
+
+    /// check whether this path may be crawled
+    path: Option<String>,
+
+    /// user-agent to check rules for
+    #[clap(short, long, default_value = AGENT)]
+    user_agent: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Robots {
+    groups: Vec<Group>,
+    pub sitemaps: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    pub fn parse(text: &str) -> Self {
+        let mut robots = Robots::default();
+        let mut in_agents = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_ascii_lowercase(), value.trim()),
+                None => continue,
+            };
+
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_agents {
+                        robots.groups.push(Group::default());
+                        in_agents = true;
+                    }
+                    let group = robots.groups.last_mut().unwrap();
+                    group.agents.push(value.to_ascii_lowercase());
+                }
+                "sitemap" => robots.sitemaps.push(value.to_string()),
+                _ => {
+                    in_agents = false;
+                    let group = match robots.groups.last_mut() {
+                        Some(group) => group,
+                        None => continue,
+                    };
+                    match key.as_str() {
+                        "allow" => group.rules.push((true, value.to_string())),
+                        "disallow" if !value.is_empty() => {
+                            group.rules.push((false, value.to_string()))
+                        }
+                        "crawl-delay" => {
+                            group.crawl_delay = value.parse().ok().map(Duration::from_secs_f64)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        robots
+    }
+
+    /// robots.txt that forbids everything, used when the server fails to answer with one
+    fn disallow_all() -> Self {
+        Robots::parse("User-agent: *\nDisallow: /")
+    }
+
+    /// groups addressing `agent`, or the `*` groups if there are none
+    fn groups_for(&self, agent: &str) -> Vec<&Group> {
+        let agent = agent.to_ascii_lowercase();
+        let named: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|g| g.agents.contains(&agent))
+            .collect();
+        if !named.is_empty() {
+            return named;
+        }
+        self.groups
+            .iter()
+            .filter(|g| g.agents.iter().any(|a| a == "*"))
+            .collect()
+    }
+
+    /// the longest matching rule wins, `Allow` wins ties
+    pub fn is_allowed(&self, agent: &str, path: &str) -> bool {
+        self.groups_for(agent)
+            .iter()
+            .flat_map(|g| g.rules.iter())
+            .filter(|(_, pattern)| matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .map(|(allow, _)| *allow)
+            .unwrap_or(true)
+    }
+
+    pub fn crawl_delay(&self, agent: &str) -> Option<Duration> {
+        self.groups_for(agent).iter().find_map(|g| g.crawl_delay)
+    }
+}
+
+/// prefix match supporting `*` wildcards and a trailing `$` anchor
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match path.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<_> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i + 1 == parts.len();
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// path and query as matched by robots.txt rules
+pub fn robots_path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// the site's robots.txt, none if it's missing and one disallowing everything if the server fails,
+/// an error if it can't be reached at all
+pub async fn fetch(client: &Client, url: &Url) -> Result<Robots, Error> {
+    let robots_url = match url.join("/robots.txt") {
+        Ok(robots_url) => robots_url,
+        Err(_) => return Ok(Robots::default()),
+    };
+    rate::wait(&robots_url).await;
+    let res = client
+        .get(robots_url.clone())
+        .send()
+        .await
+        .map_err(|e| Error::request(robots_url.as_str(), e))?;
+    let status = res.status();
+    if status.is_success() {
+        let text = res
+            .text()
+            .await
+            .map_err(|e| Error::request(robots_url.as_str(), e))?;
+        Ok(Robots::parse(&text))
+    } else if status.is_client_error() {
+        Ok(Robots::default())
+    } else {
+        Ok(Robots::disallow_all())
+    }
+}
+
+/// robots.txt per origin, fetched once
+#[derive(Default)]
+pub struct RobotsCache {
+    origins: Mutex<HashMap<String, Arc<Robots>>>,
+}
+
+impl RobotsCache {
+    /// fetches it again next time if the origin couldn't be reached
+    pub async fn get(&self, client: &Client, url: &Url) -> Result<Arc<Robots>, Error> {
+        let origin = url.origin().ascii_serialization();
+        if let Some(robots) = self.origins.lock().unwrap().get(&origin) {
+            return Ok(robots.clone());
+        }
+        let robots = Arc::new(fetch(client, url).await?);
+        Ok(self
+            .origins
+            .lock()
+            .unwrap()
+            .entry(origin)
+            .or_insert(robots)
+            .clone())
+    }
+}
+
+pub async fn inspect(client: &Client, args: RobotsArgs) -> Result<(), Error> {
+    let url = Url::parse(&args.url).map_err(|_| Error::InvalidUrl(args.url.clone()))?;
+    let robots = fetch(client, &url).await?;
+
+    let mut allowed = true;
+    if let Some(path) = &args.path {
+        let path = match url.join(path) {
+            Ok(joined) => robots_path(&joined),
+            Err(_) => path.clone(),
+        };
+        allowed = robots.is_allowed(&args.user_agent, &path);
+        let verdict = if allowed { "allowed" } else { "disallowed" };
+        println!("{} {} for {}", path, verdict, args.user_agent);
+    }
+    if let Some(delay) = robots.crawl_delay(&args.user_agent) {
+        println!("crawl-delay: {:?}", delay);
+    }
+    for sitemap in &robots.sitemaps {
+        println!("sitemap: {}", sitemap);
+    }
+
+    if !allowed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("/", "/anything"));
+        assert!(matches("/*.php", "/index.php"));
+        assert!(matches("/*.php", "/dir/index.php?q=1"));
+        assert!(!matches("/*.php", "/index.html"));
+        assert!(matches("/private*/", "/private-stuff/page"));
+        assert!(matches("/*.php$", "/index.php"));
+        assert!(!matches("/*.php$", "/index.php?q=1"));
+        assert!(matches("/exact$", "/exact"));
+        assert!(!matches("/exact$", "/exact/more"));
+        assert!(!matches("/fish", "/Fish"));
+    }
+
+    #[test]
+    fn longest_match_wins() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /shop\nAllow: /shop/public\nDisallow: /shop/public/drafts",
+        );
+        assert!(!robots.is_allowed(AGENT, "/shop/cart"));
+        assert!(robots.is_allowed(AGENT, "/shop/public/item"));
+        assert!(!robots.is_allowed(AGENT, "/shop/public/drafts/1"));
+        assert!(robots.is_allowed(AGENT, "/about"));
+    }
+
+    #[test]
+    fn allow_wins_ties() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /page\nAllow: /page");
+        assert!(robots.is_allowed(AGENT, "/page"));
+        let robots = Robots::parse("User-agent: *\nAllow: /page\nDisallow: /page");
+        assert!(robots.is_allowed(AGENT, "/page"));
+    }
+
+    #[test]
+    fn named_group_or_star() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: Scrape\nUser-agent: other\nDisallow: /admin\nCrawl-delay: 2",
+        );
+        assert!(robots.is_allowed("scrape", "/page"));
+        assert!(!robots.is_allowed("scrape", "/admin"));
+        assert_eq!(robots.crawl_delay("scrape"), Some(Duration::from_secs(2)));
+        assert!(!robots.is_allowed("somebot", "/page"));
+        assert_eq!(robots.crawl_delay("somebot"), None);
+    }
+
+    #[test]
+    fn empty_disallow_allows_everything() {
+        let robots =
+            Robots::parse("User-agent: *\nDisallow:\n\nSitemap: https://example.com/s.xml");
+        assert!(robots.is_allowed(AGENT, "/anything"));
+        assert_eq!(robots.sitemaps, ["https://example.com/s.xml"]);
+    }
+}
@@ -0,0 +1,264 @@
+//! uploading to S3 or an S3-compatible store like MinIO, with the credentials, region and
+//! endpoint the AWS tools read from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`,
+//! `AWS_SESSION_TOKEN`, `AWS_REGION` and `AWS_ENDPOINT_URL`
+
+use reqwest::{header::CONTENT_TYPE, Client, Url};
+use std::{env, str::FromStr, time::SystemTime};
+
+use crate::{
+    datetime, log,
+    sha256::{digest, hex, hmac},
+};
+
+/// `s3://bucket/prefix/`
+#[derive(Debug, Clone)]
+pub struct Location {
+    bucket: String,
+    prefix: String,
+}
+
+impl FromStr for Location {
+    type Err = String;
+
+    fn from_str(location: &str) -> Result<Self, Self::Err> {
+        let path = location
+            .strip_prefix("s3://")
+            .ok_or_else(|| format!("'{}' is not an s3://bucket/prefix/ location", location))?;
+        let (bucket, prefix) = path.split_once('/').unwrap_or((path, ""));
+        if bucket.is_empty() {
+            return Err(format!("No bucket in '{}'", location));
+        }
+        Ok(Location {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+        })
+    }
+}
+
+fn variable(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// percent-encodes all but the characters S3 leaves alone, and `/` if asked to
+fn encode(text: &str, slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// the Signature Version 4 of a request to S3 in the region, at a `20221003T100000Z` timestamp,
+/// for headers sorted by their lowercase names
+fn signature(
+    secret_key: &str,
+    region: &str,
+    timestamp: &str,
+    method: &str,
+    path: &str,
+    headers: &[(&str, String)],
+    payload: &str,
+) -> String {
+    let date = &timestamp[..8];
+    let signed: Vec<_> = headers.iter().map(|(name, _)| *name).collect();
+    let canonical = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method,
+        path,
+        headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect::<String>(),
+        signed.join(";"),
+        payload
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, region);
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        timestamp,
+        scope,
+        hex(&digest(canonical.as_bytes()))
+    );
+    let mut signing_key = hmac(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    for part in [region, "s3", "aws4_request"] {
+        signing_key = hmac(&signing_key, part.as_bytes());
+    }
+    hex(&hmac(&signing_key, to_sign.as_bytes()))
+}
+
+impl Location {
+    /// the url of an object, path style for custom endpoints and virtual hosted for AWS
+    fn url(&self, key: &str, region: &str) -> Result<Url, String> {
+        let key = encode(key, false);
+        let url = match variable("AWS_ENDPOINT_URL") {
+            Some(endpoint) => format!(
+                "{}/{}/{}",
+                endpoint.trim_end_matches('/'),
+                encode(&self.bucket, true),
+                key
+            ),
+            None => format!(
+                "https://{}.s3.{}.amazonaws.com/{}",
+                self.bucket, region, key
+            ),
+        };
+        Url::parse(&url).map_err(|_| format!("Invalid S3 url '{}'", url))
+    }
+
+    /// stores the data under the prefix and name, signed with AWS Signature Version 4
+    pub async fn put(
+        &self,
+        client: &Client,
+        name: &str,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), String> {
+        let access_key = variable("AWS_ACCESS_KEY_ID").ok_or("AWS_ACCESS_KEY_ID is not set")?;
+        let secret_key =
+            variable("AWS_SECRET_ACCESS_KEY").ok_or("AWS_SECRET_ACCESS_KEY is not set")?;
+        let region = variable("AWS_REGION")
+            .or_else(|| variable("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| "us-east-1".to_string());
+        let key = format!("{}{}", self.prefix, name);
+        let url = self.url(&key, &region)?;
+
+        // 2022-10-03T10:00:00Z as 20221003T100000Z
+        let timestamp = datetime::rfc3339(SystemTime::now()).replace(['-', ':'], "");
+        let date = &timestamp[..8];
+        let payload = hex(&digest(&data));
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(format!("No host in '{}'", url)),
+        };
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload.clone()),
+            ("x-amz-date", timestamp.clone()),
+        ];
+        if let Some(token) = variable("AWS_SESSION_TOKEN") {
+            headers.push(("x-amz-security-token", token));
+        }
+        let signed: Vec<_> = headers.iter().map(|(name, _)| *name).collect();
+        let signature = signature(
+            &secret_key,
+            &region,
+            &timestamp,
+            "PUT",
+            url.path(),
+            &headers,
+            &payload,
+        );
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}/{}/s3/aws4_request, SignedHeaders={}, Signature={}",
+            access_key,
+            date,
+            region,
+            signed.join(";"),
+            signature
+        );
+
+        let mut request = client
+            .put(url)
+            .header("authorization", authorization)
+            .header(CONTENT_TYPE, content_type)
+            .body(data);
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+        let res = request
+            .send()
+            .await
+            .map_err(|_| format!("Failed to PUT to 's3://{}/{}'", self.bucket, key))?;
+        if !res.status().is_success() {
+            let status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            return Err(format!(
+                "Failed to PUT to 's3://{}/{}': {} {}",
+                self.bucket,
+                key,
+                status,
+                body.trim()
+            ));
+        }
+        log::info(format!("uploaded s3://{}/{}", self.bucket, key));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the examples of https://docs.aws.amazon.com/AmazonS3/latest/API/sig-v4-header-based-auth.html
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+    const TIMESTAMP: &str = "20130524T000000Z";
+
+    #[test]
+    fn signs_the_aws_put_object_example() {
+        let payload = hex(&digest(b"Welcome to Amazon S3."));
+        assert_eq!(
+            payload,
+            "44ce7dd67c959e0d3524ffac1771dfbba87d2b6b4b4e99e42034a8b803f8b072"
+        );
+        let path = format!("/{}", encode("test$file.text", false));
+        let headers = [
+            ("date", "Fri, 24 May 2013 00:00:00 GMT".to_string()),
+            ("host", "examplebucket.s3.amazonaws.com".to_string()),
+            ("x-amz-content-sha256", payload.clone()),
+            ("x-amz-date", TIMESTAMP.to_string()),
+            ("x-amz-storage-class", "REDUCED_REDUNDANCY".to_string()),
+        ];
+        assert_eq!(
+            signature(
+                SECRET_KEY,
+                "us-east-1",
+                TIMESTAMP,
+                "PUT",
+                &path,
+                &headers,
+                &payload
+            ),
+            "98ad721746da40c64f1a55b78f14c238d841ea1380cd77a1b5971af0ece108bd"
+        );
+    }
+
+    #[test]
+    fn signs_the_aws_get_object_example() {
+        let payload = hex(&digest(b""));
+        let headers = [
+            ("host", "examplebucket.s3.amazonaws.com".to_string()),
+            ("range", "bytes=0-9".to_string()),
+            ("x-amz-content-sha256", payload.clone()),
+            ("x-amz-date", TIMESTAMP.to_string()),
+        ];
+        assert_eq!(
+            signature(
+                SECRET_KEY,
+                "us-east-1",
+                TIMESTAMP,
+                "GET",
+                "/test.txt",
+                &headers,
+                &payload
+            ),
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+    }
+
+    #[test]
+    fn encodes_keys() {
+        assert_eq!(
+            encode("pages/a b+c~d.html", false),
+            "pages/a%20b%2Bc~d.html"
+        );
+        assert_eq!(encode("a/b", true), "a%2Fb");
+        assert_eq!(encode("é", false), "%C3%A9");
+    }
+}
@@ -0,0 +1,136 @@
+//! transforming every matched element with a jq program, for cleanup a selector or regex can't do
+
+use scraper::{ElementRef, Html, Selector};
+use std::fs;
+
+use crate::{
+    jq::{self, Filter},
+    json::Json,
+    log,
+};
+
+/// the program in the file
+pub fn load(path: &str) -> Result<Filter, String> {
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read script '{}': {}", path, e))?;
+    parse(&text).map_err(|e| format!("Invalid script '{}': {}", path, e))
+}
+
+/// a jq program, where `#` starts a comment outside of strings like in jq
+fn parse(text: &str) -> Result<Filter, String> {
+    let program: Vec<_> = text.lines().map(strip_comment).collect();
+    program
+        .join("\n")
+        .parse()
+        .map_err(|e| format!("{}, scripts are jq programs", e))
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' if string && !escaped => {
+                escaped = true;
+                continue;
+            }
+            '"' if !escaped => string = !string,
+            '#' if !string => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// what a script gets for an element
+fn node(element: ElementRef) -> Json {
+    let attributes = element
+        .value()
+        .attrs()
+        .map(|(name, value)| (name.to_string(), value.into()))
+        .collect();
+    Json::object([
+        ("tag", element.value().name().into()),
+        ("text", element.text().collect::<String>().trim().into()),
+        ("html", element.inner_html().trim().into()),
+        ("attributes", Json::Object(attributes)),
+    ])
+}
+
+/// what the script outputs for every match, except nulls
+pub fn transform(document: &Html, selector: &Selector, script: &Filter) -> Vec<String> {
+    let mut values = Vec::new();
+    for element in document.select(selector) {
+        match script.apply(&node(element)) {
+            Ok(outputs) => values.extend(
+                outputs
+                    .iter()
+                    .filter(|output| **output != Json::Null)
+                    .map(jq::to_line),
+            ),
+            Err(error) => log::error(format!("script: {}", error)),
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(program: &str, html: &str, selector: &str) -> Vec<String> {
+        let script = parse(program).unwrap();
+        let selector = Selector::parse(selector).unwrap();
+        transform(&Html::parse_fragment(html), &selector, &script)
+    }
+
+    #[test]
+    fn transforms_every_match() {
+        let html = r#"<a href="/a" class="x"> A </a><a>B <b>bold</b></a><a href="/c">C</a>"#;
+        assert_eq!(
+            run(".attributes.href // .text", html, "a"),
+            ["/a", "B bold", "/c"]
+        );
+        assert_eq!(run(".tag, .html", html, "b"), ["b", "bold"]);
+        assert_eq!(
+            run("{text, href: .attributes.href}", html, "a.x"),
+            [r#"{"text":"A","href":"/a"}"#]
+        );
+        // nulls are dropped
+        assert_eq!(run(".attributes.href", html, "a"), ["/a", "/c"]);
+    }
+
+    #[test]
+    fn comments() {
+        let program = "# links\n.attributes.href # the target\n  | . + \"#top\" # anchor\n";
+        let html = r#"<a href="/a">A</a>"#;
+        assert_eq!(run(program, html, "a"), ["/a#top"]);
+        assert_eq!(strip_comment(r##"."a\"#b" # c"##), r##"."a\"#b" "##);
+    }
+
+    #[test]
+    fn rejects_unsupported_syntax() {
+        let error = |program: &str| parse(program).unwrap_err();
+        assert_eq!(
+            error("if .tag == \"a\" then .text else empty end"),
+            "filters don't support 'if', scripts are jq programs"
+        );
+        assert_eq!(
+            error("def f: .text; f"),
+            "filters don't support function definitions, scripts are jq programs"
+        );
+        assert_eq!(
+            error(".text as $t | $t"),
+            "filters don't support variables, scripts are jq programs"
+        );
+        assert_eq!(
+            error("let text = .text"),
+            "unknown function 'let', scripts are jq programs"
+        );
+        assert_eq!(
+            error(".text;"),
+            "unexpected ';' in filter, scripts are jq programs"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use reqwest::Client;
+use scraper::{Html, Selector};
+
+use crate::{
+    crawl::{is_nofollow, meta_robots, resolve},
+    download,
+    json::Json,
+    Error, Format,
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct SeoArgs {
+    /// the page to audit
+    url: String,
+
+    /// `jsonl` prints the report as a single json object
+    #[clap(short, long, arg_enum, default_value = "text")]
+    format: Format,
+}
+
+fn select(selector: &str) -> Selector {
+    Selector::parse(selector).unwrap()
+}
+
+fn text(document: &Html, selector: &str) -> Option<String> {
+    document.select(&select(selector)).next().map(|element| {
+        let text: String = element.text().collect();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    })
+}
+
+/// an issue if `length` is outside what search results display well
+fn check_length(what: &str, length: usize, min: usize, max: usize) -> Option<String> {
+    if length == 0 {
+        Some(format!("{} is missing", what))
+    } else if length < min {
+        Some(format!(
+            "{} is short, {} characters, aim for {}-{}",
+            what, length, min, max
+        ))
+    } else if length > max {
+        Some(format!(
+            "{} is long, {} characters, aim for {}-{}",
+            what, length, min, max
+        ))
+    } else {
+        None
+    }
+}
+
+pub async fn run(client: &Client, args: SeoArgs) -> Result<(), Error> {
+    let page = download(client, &args.url).await?;
+    let document = Html::parse_document(&page.body);
+    let mut issues = Vec::new();
+
+    let title = text(&document, "title").unwrap_or_default();
+    let description = document
+        .select(&select("meta[name=description]"))
+        .find_map(|meta| meta.value().attr("content"))
+        .map(str::trim)
+        .unwrap_or_default();
+    issues.extend(check_length("title", title.chars().count(), 30, 60));
+    issues.extend(check_length(
+        "description",
+        description.chars().count(),
+        70,
+        160,
+    ));
+
+    let mut headings = Vec::new();
+    let mut previous = 0;
+    for heading in document.select(&select("h1, h2, h3, h4, h5, h6")) {
+        let level = heading.value().name()[1..].parse::<usize>().unwrap_or(1);
+        let text: String = heading.text().collect();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if level > previous + 1 {
+            issues.push(format!(
+                "h{} '{}' skips a level after h{}",
+                level, text, previous
+            ));
+        }
+        previous = level;
+        headings.push((level, text));
+    }
+    match headings.iter().filter(|(level, _)| *level == 1).count() {
+        0 => issues.push("no h1 heading".into()),
+        1 => {}
+        n => issues.push(format!("{} h1 headings, expected one", n)),
+    }
+
+    let images = document.select(&select("img")).count();
+    let without_alt = document.select(&select("img:not([alt])")).count();
+    if without_alt > 0 {
+        issues.push(format!(
+            "{} of {} images have no alt text",
+            without_alt, images
+        ));
+    }
+
+    let canonical = document
+        .select(&select("link[rel~=canonical]"))
+        .find_map(|link| link.value().attr("href"))
+        .and_then(|href| page.url.join(href).ok());
+    if canonical.is_none() {
+        issues.push("no canonical link".into());
+    }
+    let robots = meta_robots(&document);
+    if robots.iter().any(|directive| directive == "noindex") {
+        issues.push("robots meta tag says noindex".into());
+    }
+
+    let (mut internal, mut external, mut nofollow) = (0usize, 0usize, 0usize);
+    for link in document.select(&select("a[href]")) {
+        let target = match resolve(&page.url, link.value().attr("href").unwrap_or_default()) {
+            Some(target) => target,
+            None => continue,
+        };
+        if target.host_str() == page.url.host_str() {
+            internal += 1;
+        } else {
+            external += 1;
+        }
+        if is_nofollow(link.value().attr("rel")) {
+            nofollow += 1;
+        }
+    }
+
+    match args.format {
+        Format::Jsonl => {
+            let headings: Vec<_> = headings
+                .iter()
+                .map(|(level, text)| {
+                    Json::object([("level", (*level).into()), ("text", text.as_str().into())])
+                })
+                .collect();
+            let report = Json::object([
+                ("url", page.url.as_str().into()),
+                ("title", title.as_str().into()),
+                ("title_length", title.chars().count().into()),
+                ("description", description.into()),
+                ("description_length", description.chars().count().into()),
+                ("headings", headings.into()),
+                ("images", images.into()),
+                ("images_without_alt", without_alt.into()),
+                ("canonical", canonical.as_ref().map(|c| c.as_str()).into()),
+                ("robots", robots.into()),
+                ("internal_links", internal.into()),
+                ("external_links", external.into()),
+                ("nofollow_links", nofollow.into()),
+                ("issues", issues.into()),
+            ]);
+            println!("{}", report);
+        }
+        Format::Text => {
+            println!("{}", page.url);
+            println!(
+                "  title        {:?} ({} characters)",
+                title,
+                title.chars().count()
+            );
+            println!(
+                "  description  {:?} ({} characters)",
+                description,
+                description.chars().count()
+            );
+            println!("  headings");
+            for (level, text) in &headings {
+                println!("    {}h{} {}", "  ".repeat(level - 1), level, text);
+            }
+            println!(
+                "  images       {}, {} without alt text",
+                images, without_alt
+            );
+            match &canonical {
+                Some(canonical) => println!("  canonical    {}", canonical),
+                None => println!("  canonical    none"),
+            }
+            println!("  robots       {}", robots.join(", "));
+            println!(
+                "  links        {} internal, {} external, {} nofollow",
+                internal, external, nofollow
+            );
+            if issues.is_empty() {
+                println!("no issues found");
+            } else {
+                println!("{} issues", issues.len());
+                for issue in &issues {
+                    println!("  - {}", issue);
+                }
+            }
+        }
+    }
+    Ok(())
+}
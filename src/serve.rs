@@ -0,0 +1,297 @@
+//! answering JSON-RPC 2.0 requests to fetch and extract from pages, one per line on stdin or
+//! POSTed over http, so other tools can keep one scrape running instead of starting it per page
+//!
+//! ```text
+//! {"jsonrpc":"2.0","id":1,"method":"extract","params":{"url":"https://example.com","selector":"a","attribute":"href"}}
+//! {"jsonrpc":"2.0","id":1,"result":{"url":"https://example.com/","status":200,"values":["https://www.iana.org/domains/example"]}}
+//! ```
+
+use regex::Regex;
+use reqwest::{Client, Url};
+use scraper::Html;
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    task::{self, LocalSet},
+};
+
+use crate::{download, extract, json::Json, log, parse_duration, parse_selector, Error, Page};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct ServeArgs {
+    /// read one request per line on stdin and answer each with a line on stdout
+    #[clap(long, required_unless_present = "listen", conflicts_with = "listen")]
+    stdio: bool,
+
+    /// answer requests POSTed to this address instead, e.g. `127.0.0.1:8800`
+    #[clap(long)]
+    listen: Option<SocketAddr>,
+
+    /// answer from a page downloaded this recently instead of downloading it again, 0 to always download
+    #[clap(long, default_value = "1m", parse(try_from_str = parse_duration))]
+    cache_for: Duration,
+}
+
+/// pages by url and when they were downloaded
+static CACHE: Mutex<BTreeMap<String, (Instant, Page)>> = Mutex::new(BTreeMap::new());
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// the request was fine but the page couldn't be fetched
+const FETCH_FAILED: i64 = -32000;
+
+type Failure = (i64, String);
+
+/// how large the request line and headers of an http request can be
+const MAX_HEAD: usize = 16 * 1024;
+/// how large the JSON-RPC request POSTed can be
+const MAX_BODY: usize = 1024 * 1024;
+
+async fn page(client: &Client, url: &str, cache_for: Duration) -> Result<Page, Failure> {
+    if let Some((fetched, page)) = CACHE.lock().unwrap().get(url) {
+        if fetched.elapsed() < cache_for {
+            return Ok(page.clone());
+        }
+    }
+    let page = match download(client, url).await {
+        Ok(page) => page,
+        Err(error) => return Err((FETCH_FAILED, error.to_string())),
+    };
+    if !cache_for.is_zero() {
+        let mut cache = CACHE.lock().unwrap();
+        cache.retain(|_, (fetched, _)| fetched.elapsed() < cache_for);
+        cache.insert(url.to_string(), (Instant::now(), page.clone()));
+    }
+    Ok(page)
+}
+
+fn param<'a>(params: &'a Json, name: &str) -> Result<Option<&'a str>, Failure> {
+    match params.get(name) {
+        None | Some(Json::Null) => Ok(None),
+        Some(Json::String(value)) => Ok(Some(value)),
+        Some(_) => Err((INVALID_PARAMS, format!("'{}' needs to be a string", name))),
+    }
+}
+
+async fn call(
+    client: &Client,
+    method: &str,
+    params: &Json,
+    cache_for: Duration,
+) -> Result<Json, Failure> {
+    if !matches!(method, "fetch" | "extract") {
+        return Err((METHOD_NOT_FOUND, format!("No method '{}'", method)));
+    }
+    let url = param(params, "url")?.ok_or((INVALID_PARAMS, "'url' is missing".to_string()))?;
+    // paths and file:// urls would let any client read the files of the machine serving
+    let scheme = Url::parse(url).map(|url| url.scheme().to_string());
+    if !matches!(scheme.as_deref(), Ok("http" | "https")) {
+        return Err((
+            INVALID_PARAMS,
+            format!("'{}' needs to be an http(s) url", url),
+        ));
+    }
+    match method {
+        "fetch" => {
+            let page = page(client, url, cache_for).await?;
+            let headers = page.headers.iter().map(|(name, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                (name.to_string(), value.as_ref().into())
+            });
+            Ok(Json::object([
+                ("url", page.url.as_str().into()),
+                ("status", page.status.as_u16().into()),
+                ("headers", Json::Object(headers.collect())),
+                ("body", page.body.as_str().into()),
+            ]))
+        }
+        "extract" => {
+            let invalid = |e: String| (INVALID_PARAMS, e);
+            let selector = param(params, "selector")?
+                .map(parse_selector)
+                .transpose()
+                .map_err(|e| invalid(e.to_string()))?;
+            let regex = match param(params, "regex")? {
+                Some(regex) => Some(
+                    Regex::new(regex).map_err(|_| invalid(format!("Invalid regex '{}'", regex)))?,
+                ),
+                None => None,
+            };
+            let page = page(client, url, cache_for).await?;
+            let values = match &selector {
+                Some(selector) => extract(
+                    &Html::parse_document(&page.body),
+                    selector,
+                    param(params, "attribute")?,
+                ),
+                None => vec![page.body.clone()],
+            };
+            let values = match regex {
+                Some(regex) => values
+                    .iter()
+                    .flat_map(|value| scrape_core::regex_matches(&regex, value))
+                    .collect(),
+                None => values,
+            };
+            Ok(Json::object([
+                ("url", page.url.as_str().into()),
+                ("status", page.status.as_u16().into()),
+                ("values", values.into()),
+            ]))
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// the response to a request line or body, none for notifications
+async fn handle(client: &Client, request: &str, cache_for: Duration) -> Option<Json> {
+    let request = match Json::parse(request) {
+        Ok(request) => request,
+        Err(error) => return Some(response(Json::Null, Err((PARSE_ERROR, error)))),
+    };
+    let id = request.get("id").cloned();
+    let result = match (
+        &request,
+        request.get("method").and_then(Json::as_str),
+        request.get("params"),
+    ) {
+        (Json::Object(_), Some(method), params) => {
+            let params = params.cloned().unwrap_or(Json::Object(vec![]));
+            call(client, method, &params, cache_for).await
+        }
+        (Json::Object(_), None, _) => Err((INVALID_REQUEST, "'method' is missing".to_string())),
+        _ => Err((INVALID_REQUEST, "requests need to be objects".to_string())),
+    };
+    if let Err((_, error)) = &result {
+        log::info(format!("request failed: {}", error));
+    }
+    match (id, &result) {
+        (Some(id), _) => Some(response(id, result)),
+        // invalid requests are answered even without an id, notifications aren't
+        (None, Err((INVALID_REQUEST, _))) => Some(response(Json::Null, result)),
+        (None, _) => None,
+    }
+}
+
+fn response(id: Json, result: Result<Json, Failure>) -> Json {
+    let outcome = match result {
+        Ok(result) => ("result", result),
+        Err((code, message)) => (
+            "error",
+            Json::object([("code", (code as f64).into()), ("message", message.into())]),
+        ),
+    };
+    Json::object([("jsonrpc", "2.0".into()), ("id", id), outcome])
+}
+
+async fn stdio(client: &Client, cache_for: Duration) -> io::Result<()> {
+    let mut lines = BufReader::new(io::stdin()).lines();
+    let mut stdout = io::stdout();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle(client, &line, cache_for).await {
+            stdout
+                .write_all(format!("{}\n", response).as_bytes())
+                .await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+async fn reply(mut stream: TcpStream, status: &str, body: &str) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn respond(client: &Client, mut stream: TcpStream, cache_for: Duration) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+    let head = loop {
+        let end = request.windows(4).position(|w| w == b"\r\n\r\n");
+        if end.unwrap_or(request.len()) > MAX_HEAD {
+            return reply(stream, "431 Request Header Fields Too Large", "").await;
+        }
+        if let Some(end) = end {
+            break end + 4;
+        }
+        match stream.read(&mut buffer).await? {
+            0 => return Ok(()),
+            read => request.extend_from_slice(&buffer[..read]),
+        }
+    };
+    let head_text = String::from_utf8_lossy(&request[..head]).to_string();
+    let length: usize = head_text
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return reply(stream, "413 Payload Too Large", "").await;
+    }
+    while request.len() < head + length {
+        match stream.read(&mut buffer).await? {
+            0 => break,
+            read => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let body = String::from_utf8_lossy(&request[head..]);
+    let (status, body) = match head_text.split_whitespace().next() {
+        Some("POST") => match handle(client, &body, cache_for).await {
+            Some(response) => ("200 OK", response.to_string()),
+            None => ("204 No Content", String::new()),
+        },
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    reply(stream, status, &body).await
+}
+
+pub async fn run(client: &Client, args: ServeArgs) -> Result<(), Error> {
+    let address = match args.listen {
+        Some(address) => address,
+        None => return Ok(stdio(client, args.cache_for).await?),
+    };
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+    log::info(format!(
+        "Answering JSON-RPC requests on http://{}/",
+        address
+    ));
+    // pages rendered with --render can't move between threads, so connections share this one
+    let connections = LocalSet::new();
+    connections
+        .run_until(async {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let client = client.clone();
+                        task::spawn_local(async move {
+                            if let Err(error) = respond(&client, stream, args.cache_for).await {
+                                log::warn(format!("Failed to answer request: {}", error));
+                            }
+                        });
+                    }
+                    Err(error) => log::warn(format!("Failed to accept connection: {}", error)),
+                }
+            }
+        })
+        .await
+}
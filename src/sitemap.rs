@@ -0,0 +1,168 @@
+use reqwest::{Client, Url};
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{datetime, gzip, link_graph::escape_xml, log, rate, robots, xml, Error};
+
+/// the sitemaps of a site root are taken from robots.txt, anything else is a sitemap itself
+async fn locate(client: &Client, url: &Url) -> Vec<Url> {
+    if url.path() != "/" {
+        return vec![url.clone()];
+    }
+    let robots = match robots::fetch(client, url).await {
+        Ok(robots) => robots,
+        Err(error) => {
+            log::warn(format!("Failed to fetch robots.txt: {}", error));
+            Default::default()
+        }
+    };
+    let declared: Vec<_> = robots
+        .sitemaps
+        .iter()
+        .filter_map(|sitemap| Url::parse(sitemap).ok())
+        .collect();
+    if declared.is_empty() {
+        url.join("/sitemap.xml").into_iter().collect()
+    } else {
+        declared
+    }
+}
+
+/// whether the entry changed at or after `since`, in seconds since the epoch, entries without a
+/// `lastmod` or with one that isn't a W3C datetime might have
+fn modified_since(lastmod: Option<&str>, since: Option<i64>) -> bool {
+    match (lastmod.and_then(datetime::w3c), since) {
+        (Some(lastmod), Some(since)) => lastmod >= since,
+        _ => true,
+    }
+}
+
+/// pages listed in a sitemap, following sitemap indexes and unpacking gzip
+pub async fn urls(client: &Client, url: &Url, since: Option<i64>) -> Result<Vec<Url>, Error> {
+    let mut sitemaps: VecDeque<_> = locate(client, url).await.into();
+    let mut seen = HashSet::new();
+    let mut urls = Vec::new();
+
+    while let Some(sitemap) = sitemaps.pop_front() {
+        if !seen.insert(sitemap.clone()) {
+            continue;
+        }
+        rate::wait(&sitemap).await;
+        let res = client
+            .get(sitemap.clone())
+            .send()
+            .await
+            .map_err(|_| format!("Failed to GET from '{}'", &sitemap))?;
+        if !res.status().is_success() {
+            log::warn(format!(
+                "Skipped sitemap '{}', it answered {}",
+                sitemap,
+                res.status()
+            ));
+            continue;
+        }
+        let bytes = res.bytes().await?;
+        let bytes = if gzip::is_gzip(&bytes) {
+            gzip::decompress(&bytes)?
+        } else {
+            bytes.to_vec()
+        };
+        let text = String::from_utf8_lossy(&bytes);
+
+        // plain text sitemaps list one url per line
+        if !text.trim_start().starts_with('<') {
+            urls.extend(text.lines().filter_map(|line| Url::parse(line.trim()).ok()));
+            continue;
+        }
+
+        let root =
+            xml::parse(&text).map_err(|e| format!("Invalid sitemap '{}': {}", sitemap, e))?;
+        for child in root.elements() {
+            let lastmod = child.child_text("lastmod");
+            let loc = child
+                .child_text("loc")
+                .and_then(|loc| sitemap.join(&loc).ok());
+            let loc = match loc {
+                Some(loc) if modified_since(lastmod.as_deref(), since) => loc,
+                _ => continue,
+            };
+            match child.local_name() {
+                "sitemap" => sitemaps.push_back(loc),
+                "url" => urls.push(loc),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(urls)
+}
+
+/// writes a sitemap listing `pages` with their optional lastmod
+pub fn write(path: &Path, pages: &[(Url, Option<String>)]) -> io::Result<()> {
+    let mut out = BufWriter::new(fs::File::create(path)?);
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#
+    )?;
+    for (url, lastmod) in pages {
+        write!(out, "  <url><loc>{}</loc>", escape_xml(url.as_str()))?;
+        if let Some(lastmod) = lastmod {
+            write!(out, "<lastmod>{}</lastmod>", lastmod)?;
+        }
+        writeln!(out, "</url>")?;
+    }
+    writeln!(out, "</urlset>")?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn w3c_datetimes() {
+        assert_eq!(datetime::w3c("1970-01-01"), Some(0));
+        assert_eq!(datetime::w3c("2022-10-03"), Some(1_664_755_200));
+        assert_eq!(datetime::w3c("2022-10-03T00:00:00Z"), Some(1_664_755_200));
+        assert_eq!(datetime::w3c("2022-10-03T02:00+02:00"), Some(1_664_755_200));
+        assert_eq!(
+            datetime::w3c("2022-10-02T19:00:00.25-05:00"),
+            Some(1_664_755_200)
+        );
+        assert_eq!(
+            datetime::w3c("2022-10-03T04:30:00+0430"),
+            Some(1_664_755_200)
+        );
+        assert_eq!(datetime::w3c("2022-10"), datetime::w3c("2022-10-01"));
+        assert_eq!(datetime::w3c("2022"), datetime::w3c("2022-01-01T00:00:00Z"));
+        for invalid in [
+            "",
+            "22-10-03",
+            "2022-13-01",
+            "2022-10-03T25",
+            "2022-10-03T10:00+2",
+            "soon",
+        ] {
+            assert_eq!(datetime::w3c(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn modified_since_compares_instants() {
+        let since = datetime::w3c("2022-10-03");
+        assert!(modified_since(Some("2022-10-03T00:00:00Z"), since));
+        assert!(modified_since(Some("2022-10-03"), since));
+        assert!(modified_since(Some("2022-10-03T01:30:00+01:00"), since));
+        assert!(!modified_since(Some("2022-10-03T01:30:00+02:00"), since));
+        assert!(!modified_since(Some("2022-10-02T23:59:59Z"), since));
+        assert!(modified_since(Some("2022-10-02T20:00:00-05:00"), since));
+        assert!(modified_since(None, since));
+        assert!(modified_since(Some("yesterday"), since));
+        assert!(modified_since(Some("2000-01-01"), None));
+    }
+}
@@ -0,0 +1,147 @@
+//! the extractions of every watched page: the last one, so the next run can diff against it,
+//! and a content-addressed history of all of them
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::{datetime, dirs, Error};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct HistoryArgs {
+    /// the watched or diffed page, list the commands scrape ran if left out
+    url: Option<String>,
+
+    /// the selector it was watched with
+    #[clap(short, long, requires = "url")]
+    selector: Option<String>,
+
+    #[clap(short, long, requires = "url")]
+    attribute: Option<String>,
+
+    /// print the snapshot with this hash (or the start of it) instead of listing them
+    #[clap(long, requires = "url")]
+    show: Option<String>,
+}
+
+/// the FNV-1a hash of nothing, to `fnv1a_extend` from
+pub const FNV1A_EMPTY: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a, stable across runs and rust versions unlike the std hasher
+pub fn fnv1a(input: &str) -> u64 {
+    fnv1a_extend(FNV1A_EMPTY, input)
+}
+
+/// the hash of what `hash` was taken of followed by `input`
+pub fn fnv1a_extend(hash: u64, input: &str) -> u64 {
+    input.bytes().fold(hash, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn dir() -> io::Result<PathBuf> {
+    let dir = dirs::data_dir().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "no home directory for snapshots")
+    })?;
+    Ok(dir.join("snapshots"))
+}
+
+/// the file name, without extension, of what is saved for a page
+fn key(url: &str, selector: Option<&str>, attribute: Option<&str>) -> String {
+    let key = format!(
+        "{}\t{}\t{}",
+        url,
+        selector.unwrap_or_default(),
+        attribute.unwrap_or_default()
+    );
+    format!("{:016x}", fnv1a(&key))
+}
+
+pub fn load(url: &str, selector: Option<&str>, attribute: Option<&str>) -> Option<String> {
+    let path = dir().ok()?.join(key(url, selector, attribute) + ".txt");
+    fs::read_to_string(path).ok()
+}
+
+/// saves the content as the latest snapshot and adds it to the history if it changed
+pub fn save(
+    url: &str,
+    selector: Option<&str>,
+    attribute: Option<&str>,
+    content: &str,
+) -> io::Result<()> {
+    let dir = dir()?;
+    let key = key(url, selector, attribute);
+    fs::create_dir_all(dir.join("objects"))?;
+    fs::write(dir.join(format!("{}.txt", key)), content)?;
+
+    let hash = format!("{:016x}", fnv1a(content));
+    let object = dir.join("objects").join(format!("{}.txt", hash));
+    if !object.exists() {
+        fs::write(object, content)?;
+    }
+    let history = history(url, selector, attribute);
+    if history.last().is_none_or(|(_, last)| *last != hash) {
+        let log = dir.join(format!("{}.log", key));
+        let mut log = OpenOptions::new().create(true).append(true).open(log)?;
+        writeln!(log, "{}\t{}", datetime::now(), hash)?;
+    }
+    Ok(())
+}
+
+/// when each snapshot was taken and its hash, oldest first
+pub fn history(
+    url: &str,
+    selector: Option<&str>,
+    attribute: Option<&str>,
+) -> Vec<(String, String)> {
+    let log = match dir() {
+        Ok(dir) => dir.join(key(url, selector, attribute) + ".log"),
+        Err(_) => return vec![],
+    };
+    fs::read_to_string(log)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(time, hash)| (time.to_string(), hash.to_string()))
+        .collect()
+}
+
+fn object(hash: &str) -> io::Result<String> {
+    fs::read_to_string(dir()?.join("objects").join(format!("{}.txt", hash)))
+}
+
+pub fn run(args: HistoryArgs) -> Result<(), Error> {
+    let url = match &args.url {
+        Some(url) => url,
+        None => return crate::history::list(),
+    };
+    let history = history(url, args.selector.as_deref(), args.attribute.as_deref());
+    if history.is_empty() {
+        return Err(format!("No snapshots of '{}' yet", url).into());
+    }
+    let prefix = match &args.show {
+        Some(prefix) => prefix,
+        None => {
+            for (time, hash) in &history {
+                let lines = object(hash).map_or(0, |content| content.lines().count());
+                println!("{}\t{}\t{} lines", time, hash, lines);
+            }
+            return Ok(());
+        }
+    };
+    let mut matching: Vec<_> = history
+        .iter()
+        .map(|(_, hash)| hash)
+        .filter(|hash| hash.starts_with(prefix.as_str()))
+        .collect();
+    matching.sort();
+    matching.dedup();
+    match matching[..] {
+        [hash] => println!("{}", object(hash)?),
+        [] => return Err(format!("No snapshot '{}' of '{}'", prefix, url).into()),
+        _ => return Err(format!("'{}' matches more than one snapshot", prefix).into()),
+    }
+    Ok(())
+}
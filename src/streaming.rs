@@ -0,0 +1,660 @@
+//! extracting while the page downloads, without ever holding the whole document: the body goes
+//! through in pieces, or html5ever's tokenizer looks for elements matching simple selectors like
+//! `li.item` or `a[rel=next]` and only keeps the html of the matches it's inside of
+//!
+//! Without a tree the tokenizer only knows about the implied end tags of paragraphs, list items
+//! and table cells and the implied `<tbody>`, so badly nested html can match differently than
+//! with the whole document.
+
+use encoding_rs::Decoder;
+use futures_util::StreamExt;
+use html5ever::{
+    tendril::StrTendril,
+    tokenizer::{
+        states::RawKind, BufferQueue, Doctype, Tag, TagKind, Token, TokenSink, TokenSinkResult,
+        Tokenizer,
+    },
+};
+use regex::Regex;
+use reqwest::{header::CONTENT_TYPE, Client};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Read, Write},
+};
+
+use crate::{enough, local, log, parse_selector, print_values, rate, request, trace, Error};
+
+/// one compound selector, like `a.external[rel=nofollow]`
+#[derive(Debug, Default)]
+struct Simple {
+    name: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, Option<String>)>,
+}
+
+/// comma separated compound selectors, the ones that can match without looking at other elements
+fn parse(selector: &str) -> Result<Vec<Simple>, Error> {
+    parse_selector(selector)?;
+    let part = Regex::new(
+        r#"^(?:([\w-]+|\*)|#([\w-]+)|\.([\w-]+)|\[\s*([\w-]+)\s*(?:=\s*(?:"([^"]*)"|'([^']*)'|([\w-]+))\s*)?\])"#,
+    )
+    .unwrap();
+    let unsupported = || {
+        format!(
+            "--stream only supports selectors like `tag.class#id[attribute=value]`, not '{}'",
+            selector
+        )
+    };
+    let mut selectors = vec![];
+    for compound in selector.split(',').map(str::trim) {
+        let mut simple = Simple::default();
+        let mut rest = compound;
+        while let Some(captures) = part.captures(rest) {
+            let text = |i| {
+                captures
+                    .get(i)
+                    .map(|m: regex::Match| m.as_str().to_string())
+            };
+            if let Some(name) = text(1).filter(|name| name != "*") {
+                simple.name = Some(name.to_ascii_lowercase());
+            }
+            if let Some(id) = text(2) {
+                simple.id = Some(id);
+            }
+            if let Some(class) = text(3) {
+                simple.classes.push(class);
+            }
+            if let Some(attribute) = text(4) {
+                let value = text(5).or_else(|| text(6)).or_else(|| text(7));
+                simple
+                    .attributes
+                    .push((attribute.to_ascii_lowercase(), value));
+            }
+            rest = &rest[captures[0].len()..];
+        }
+        if compound.is_empty() || !rest.is_empty() {
+            return Err(unsupported().into());
+        }
+        selectors.push(simple);
+    }
+    Ok(selectors)
+}
+
+impl Simple {
+    fn matches(&self, tag: &Tag) -> bool {
+        let attribute = |name: &str| {
+            tag.attrs
+                .iter()
+                .find(|a| &*a.name.local == name)
+                .map(|a| &*a.value)
+        };
+        self.name.as_ref().is_none_or(|name| *name == *tag.name)
+            && self
+                .id
+                .as_ref()
+                .is_none_or(|id| attribute("id") == Some(id))
+            && self.classes.iter().all(|class| {
+                attribute("class")
+                    .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+            })
+            && self.attributes.iter().all(|(name, value)| match value {
+                Some(value) => attribute(name) == Some(value),
+                None => attribute(name).is_some(),
+            })
+    }
+}
+
+const VOID: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// elements whose text is serialized without escaping
+const RAW: [&str; 8] = [
+    "script",
+    "style",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+    "noscript",
+];
+
+/// elements that end an open paragraph
+const BLOCKS: [&str; 27] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "details",
+    "div",
+    "dl",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "ul",
+];
+
+/// whether starting `new` implies the end of the open element, tables only end paragraphs in
+/// documents with a standards mode doctype
+fn ends(open: &str, new: &str, quirks: bool) -> bool {
+    match open {
+        "p" => BLOCKS.contains(&new) || (new == "table" && !quirks),
+        "li" => new == "li",
+        "dt" | "dd" => matches!(new, "dt" | "dd"),
+        "option" => matches!(new, "option" | "optgroup"),
+        "td" | "th" => matches!(new, "td" | "th" | "tr"),
+        "tr" => new == "tr",
+        _ => false,
+    }
+}
+
+/// roughly how browsers pick quirks mode: no `html` doctype, or the public id of an html version
+/// before 4.01, or of a transitional or frameset one without a system id
+fn quirks(doctype: &Doctype) -> bool {
+    let public = doctype
+        .public_id
+        .as_deref()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let old = [
+        "-//w3c//dtd html 3",
+        "-//ietf//dtd html",
+        "-//w3o//dtd w3 html",
+    ];
+    let transitional = [
+        "-//w3c//dtd html 4.01 transitional//",
+        "-//w3c//dtd html 4.01 frameset//",
+    ];
+    doctype.force_quirks
+        || !doctype
+            .name
+            .as_deref()
+            .is_some_and(|name| name.eq_ignore_ascii_case("html"))
+        || old.iter().any(|prefix| public.starts_with(prefix))
+        || (doctype.system_id.is_none()
+            && transitional.iter().any(|prefix| public.starts_with(prefix)))
+}
+
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '<' if !attribute => escaped.push_str("&lt;"),
+            '>' if !attribute => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// the html of the matches the tokenizer is inside of
+struct Capture {
+    /// how many elements were open outside of the match
+    depth: usize,
+    /// which match this is, counting from the first in the document
+    index: usize,
+    html: String,
+}
+
+struct Matcher<'a> {
+    selectors: Vec<Simple>,
+    attribute: Option<&'a str>,
+    /// names of the open elements
+    open: Vec<String>,
+    captures: Vec<Capture>,
+    /// values in the order their elements started, none while still reading them
+    values: VecDeque<Option<String>>,
+    /// how many values were taken from the front of `values`
+    taken: usize,
+    /// whether the document renders in quirks mode, like any without a doctype
+    quirks: bool,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(selectors: Vec<Simple>, attribute: Option<&'a str>) -> Self {
+        Matcher {
+            selectors,
+            attribute,
+            open: vec![],
+            captures: vec![],
+            values: VecDeque::new(),
+            taken: 0,
+            quirks: true,
+        }
+    }
+
+    fn write(&mut self, html: &str) {
+        for capture in &mut self.captures {
+            capture.html.push_str(html);
+        }
+    }
+
+    /// ends the innermost open element and the matches it ends
+    fn close(&mut self) {
+        if let Some(name) = self.open.pop() {
+            let depth = self.open.len();
+            while let Some(capture) = self.captures.pop_if(|capture| capture.depth == depth) {
+                self.values[capture.index - self.taken] = Some(capture.html.trim().to_string());
+            }
+            self.write(&format!("</{}>", name));
+        }
+    }
+
+    fn start(&mut self, tag: Tag) -> TokenSinkResult<()> {
+        let name = tag.name.to_string();
+        while self
+            .open
+            .last()
+            .is_some_and(|open| ends(open, &name, self.quirks))
+        {
+            self.close();
+        }
+        if name == "tr" && self.open.last().is_some_and(|open| open == "table") {
+            self.write("<tbody>");
+            self.open.push("tbody".to_string());
+        }
+
+        let mut html = format!("<{}", name);
+        for attribute in &tag.attrs {
+            html.push_str(&format!(
+                " {}=\"{}\"",
+                attribute.name.local,
+                escape(&attribute.value, true)
+            ));
+        }
+        html.push('>');
+        self.write(&html);
+
+        if self.selectors.iter().any(|selector| selector.matches(&tag)) {
+            let value = self.attribute.and_then(|attribute| {
+                let attribute = tag.attrs.iter().find(|a| &*a.name.local == attribute)?;
+                Some(attribute.value.to_string())
+            });
+            let void = VOID.contains(&name.as_str()) || tag.self_closing;
+            match value {
+                Some(value) => self.values.push_back(Some(value)),
+                None if void => self.values.push_back(Some(String::new())),
+                None => {
+                    self.values.push_back(None);
+                    self.captures.push(Capture {
+                        depth: self.open.len(),
+                        index: self.taken + self.values.len() - 1,
+                        html: String::new(),
+                    });
+                }
+            }
+        }
+
+        if VOID.contains(&name.as_str()) || tag.self_closing {
+            return TokenSinkResult::Continue;
+        }
+        self.open.push(name);
+        match &*tag.name {
+            "script" => TokenSinkResult::RawData(RawKind::ScriptData),
+            "style" | "xmp" | "iframe" | "noembed" | "noframes" => {
+                TokenSinkResult::RawData(RawKind::Rawtext)
+            }
+            "title" | "textarea" => TokenSinkResult::RawData(RawKind::Rcdata),
+            "plaintext" => TokenSinkResult::Plaintext,
+            _ => TokenSinkResult::Continue,
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        if let Some(position) = self.open.iter().rposition(|open| *open == *tag.name) {
+            while self.open.len() > position {
+                self.close();
+            }
+        }
+    }
+
+    /// the values whose elements ended, as long as none that started earlier is still open
+    fn ready(&mut self) -> Vec<String> {
+        let mut ready = vec![];
+        while let Some(Some(_)) = self.values.front() {
+            ready.extend(self.values.pop_front().flatten());
+            self.taken += 1;
+        }
+        ready
+    }
+}
+
+impl TokenSink for Matcher<'_> {
+    type Handle = ();
+
+    fn process_token(&mut self, token: Token, _line: u64) -> TokenSinkResult<()> {
+        match token {
+            Token::TagToken(tag) if tag.kind == TagKind::StartTag => return self.start(tag),
+            Token::TagToken(tag) => self.end(tag),
+            Token::CharacterTokens(text) => {
+                let raw = self
+                    .open
+                    .last()
+                    .is_some_and(|open| RAW.contains(&open.as_str()));
+                match raw {
+                    true => self.write(&text),
+                    false => self.write(&escape(&text, false)),
+                }
+            }
+            Token::CommentToken(comment) => self.write(&format!("<!--{}-->", comment)),
+            Token::DoctypeToken(doctype) => self.quirks = quirks(&doctype),
+            Token::EOFToken => {
+                while !self.open.is_empty() {
+                    self.close();
+                }
+            }
+            _ => {}
+        }
+        TokenSinkResult::Continue
+    }
+}
+
+/// turns the body's bytes into text as they arrive, in the encoding the first kilobyte declares,
+/// like browsers look for `<meta charset>`
+struct Decoding {
+    content_type: Option<String>,
+    head: Vec<u8>,
+    decoder: Option<Decoder>,
+    text: String,
+}
+
+impl Decoding {
+    fn new(content_type: Option<&str>) -> Self {
+        Decoding {
+            content_type: content_type.map(str::to_string),
+            head: Vec::new(),
+            decoder: None,
+            text: String::new(),
+        }
+    }
+
+    fn decode(&mut self, bytes: &[u8], last: bool) -> &str {
+        let decoder = self.decoder.get_or_insert_with(|| {
+            scrape_core::charset::detect(bytes, self.content_type.as_deref()).new_decoder()
+        });
+        self.text.clear();
+        self.text
+            .reserve(decoder.max_utf8_buffer_length(bytes.len()).unwrap_or(0));
+        let _ = decoder.decode_to_string(bytes, &mut self.text, last);
+        &self.text
+    }
+
+    /// the text of the chunk, nothing until there's a kilobyte to detect the encoding on
+    fn push(&mut self, chunk: &[u8]) -> &str {
+        if self.decoder.is_some() {
+            return self.decode(chunk, false);
+        }
+        self.head.extend_from_slice(chunk);
+        if self.head.len() < 1024 {
+            return "";
+        }
+        let head = std::mem::take(&mut self.head);
+        self.decode(&head, false)
+    }
+
+    /// the rest of the text once the body is complete
+    fn finish(&mut self) -> &str {
+        let head = std::mem::take(&mut self.head);
+        self.decode(&head, true)
+    }
+}
+
+/// downloads the url and prints the body as it arrives, or the matches of the selector as soon
+/// as they're complete, returns how many values were printed
+pub async fn run(
+    client: &Client,
+    url: &str,
+    selector: Option<&str>,
+    attribute: Option<&str>,
+    verbose: bool,
+) -> Result<usize, Error> {
+    let mut tokenizer = match selector {
+        Some(selector) => Some(Tokenizer::new(
+            Matcher::new(parse(selector)?, attribute),
+            Default::default(),
+        )),
+        None => None,
+    };
+
+    let mut stdout = io::stdout();
+    let mut queue = BufferQueue::new();
+    let mut count = 0;
+    let mut output = |text: &str| -> io::Result<()> {
+        match tokenizer.as_mut() {
+            Some(tokenizer) => {
+                queue.push_back(StrTendril::from(text));
+                let _ = tokenizer.feed(&mut queue);
+                count += print_values(tokenizer.sink.ready(), None);
+            }
+            None => stdout.write_all(text.as_bytes())?,
+        }
+        Ok(())
+    };
+
+    if let Some(path) = local::path(url) {
+        log::info(format!("reading {}", path.display()));
+        let failed = |e: io::Error| format!("Failed to read '{}': {}", path.display(), e);
+        let mut file = File::open(&path).map_err(failed)?;
+        let mut decoding = Decoding::new(local::content_type(&path));
+        let mut chunk = vec![0; 1 << 16];
+        loop {
+            match file.read(&mut chunk).map_err(failed)? {
+                0 => break,
+                read => output(decoding.push(&chunk[..read]))?,
+            }
+            if enough() {
+                return Ok(count);
+            }
+        }
+        output(decoding.finish())?;
+    } else {
+        let request = request(client, url)?;
+        rate::wait(request.url()).await;
+        log::info(format!("GET {}", url));
+        if verbose {
+            trace::request(&request);
+        }
+        let res = client
+            .execute(request)
+            .await
+            .map_err(|e| Error::request(url, e))?;
+        if verbose {
+            trace::response(&res);
+        }
+        if !res.status().is_success() {
+            return Err(Error::Status {
+                url: url.to_string(),
+                status: res.status(),
+            });
+        }
+        let content_type = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        let mut decoding = Decoding::new(content_type);
+        let mut body = res.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| Error::request(url, e))?;
+            output(decoding.push(&chunk))?;
+            if enough() {
+                // dropping the response closes the connection
+                log::debug(format!(
+                    "Stopped downloading '{}' after enough matches",
+                    url
+                ));
+                return Ok(count);
+            }
+        }
+        output(decoding.finish())?;
+    }
+
+    match tokenizer {
+        Some(mut tokenizer) => {
+            tokenizer.end();
+            count += print_values(tokenizer.sink.ready(), None);
+        }
+        None => {
+            stdout.write_all(b"\n")?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract;
+    use scraper::Html;
+
+    /// the values the matcher finds with the html arriving a few characters at a time
+    fn streamed(html: &str, selector: &str, attribute: Option<&str>) -> Vec<String> {
+        let matcher = Matcher::new(parse(selector).unwrap(), attribute);
+        let mut tokenizer = Tokenizer::new(matcher, Default::default());
+        let mut queue = BufferQueue::new();
+        let mut values = vec![];
+        let chars: Vec<char> = html.chars().collect();
+        for chunk in chars.chunks(3) {
+            queue.push_back(StrTendril::from(chunk.iter().collect::<String>()));
+            let _ = tokenizer.feed(&mut queue);
+            values.extend(tokenizer.sink.ready());
+        }
+        tokenizer.end();
+        values.extend(tokenizer.sink.ready());
+        values
+    }
+
+    fn assert_same(html: &str, selector: &str, attribute: Option<&str>) {
+        let document = Html::parse_document(html);
+        let expected = extract(&document, &parse_selector(selector).unwrap(), attribute);
+        assert_eq!(
+            streamed(html, selector, attribute),
+            expected,
+            "{} in {}",
+            selector,
+            html
+        );
+    }
+
+    #[test]
+    fn parses_simple_selectors() {
+        let selectors = parse(r#"li.item#first[data-x="1"], a[rel=next][href]"#).unwrap();
+        assert_eq!(selectors.len(), 2);
+        assert_eq!(selectors[0].name.as_deref(), Some("li"));
+        assert_eq!(selectors[0].id.as_deref(), Some("first"));
+        assert_eq!(selectors[0].classes, ["item"]);
+        assert_eq!(
+            selectors[0].attributes,
+            [("data-x".to_string(), Some("1".to_string()))]
+        );
+        assert_eq!(
+            selectors[1].attributes,
+            [
+                ("rel".to_string(), Some("next".to_string())),
+                ("href".to_string(), None)
+            ]
+        );
+        assert!(parse("ul > li").is_err());
+        assert!(parse("li:first-child").is_err());
+        assert!(parse("li,").is_err());
+    }
+
+    #[test]
+    fn matches_like_scraper() {
+        let html = r#"<ul><li class="item a">one</li><li class="item">two &amp; <b>three</b></li><li>x</li></ul>
+            <a rel="next" href="/2">next</a><a href="/3">other</a><div id="main">main</div>"#;
+        assert_same(html, "li", None);
+        assert_same(html, "li.item", None);
+        assert_same(html, ".a", None);
+        assert_same(html, "a[rel=next]", None);
+        assert_same(html, "a[href]", Some("href"));
+        assert_same(html, "#main, b", None);
+        assert_same(html, "*[rel]", None);
+    }
+
+    #[test]
+    fn nested_matches_keep_document_order() {
+        let html = "<div class=x>outer <div class=x>inner</div> tail</div><div class=x>last</div>";
+        assert_same(html, "div.x", None);
+    }
+
+    #[test]
+    fn implied_end_tags() {
+        assert_same("<ul><li>one<li>two<li>three</ul>", "li", None);
+        assert_same("<p>one<p>two<div>block</div>", "p", None);
+        assert_same("<p>text<ul><li>item</ul>", "p", None);
+        assert_same("<p>before<table><tr><td>cell</table>", "p", None);
+        assert_same(
+            "<!DOCTYPE html><p>before<table><tr><td>cell</table>",
+            "p",
+            None,
+        );
+        assert_same(
+            r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN"><p>a<table></table>"#,
+            "p",
+            None,
+        );
+        assert_same("<dl><dt>term<dd>one<dd>two<dt>next</dl>", "dt, dd", None);
+        assert_same(
+            "<select><option>a<option>b<optgroup><option>c</select>",
+            "option",
+            None,
+        );
+        assert_same(
+            "<table><tr><td>a<td>b<tr><th>c<td>d</table>",
+            "td, th",
+            None,
+        );
+        assert_same("<table><tr><td>a<tr><td>b</table>", "tr", None);
+        assert_same("<table><tr><td>a</table>", "table", None);
+        assert_same("<ul><li>a<ul><li>b</ul><li>c</ul>", "li", None);
+    }
+
+    #[test]
+    fn raw_text_and_escapes() {
+        assert_same(
+            "<div class=s><script>if (a < b && c) { x = '<li>' }</script></div><li>real</li>",
+            "div.s, li",
+            None,
+        );
+        assert_same("<div>a&nbsp;b &lt;c&gt; \"q\"</div>", "div", None);
+        assert_same(
+            r#"<div><a title="say &quot;hi&quot;">x</a></div>"#,
+            "div",
+            None,
+        );
+        assert_same("<div><!-- note --><br><img src=a.png></div>", "div", None);
+        assert_same("<title>a &amp; <b></title>", "title", None);
+    }
+
+    #[test]
+    fn void_and_attribute_values() {
+        let html = r#"<img src="a.png"><img alt="none"><br class="x"><input value="v">"#;
+        assert_same(html, "img", Some("src"));
+        assert_same(html, "br.x", None);
+        assert_same(html, "input", Some("value"));
+    }
+}
@@ -0,0 +1,203 @@
+//! schema.org style structured data embedded in pages
+
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+
+use crate::json::Json;
+
+/// every top level item of the `application/ld+json` blocks, with `@graph`s flattened
+pub fn json_ld(document: &Html) -> Vec<Json> {
+    let scripts = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    let mut items = Vec::new();
+    for script in document.select(&scripts) {
+        let text: String = script.text().collect();
+        match Json::parse(text.trim()) {
+            Ok(Json::Array(values)) => values.into_iter().for_each(|v| flatten(v, &mut items)),
+            Ok(value) => flatten(value, &mut items),
+            Err(error) => eprintln!("Skipping JSON-LD block, {}", error),
+        }
+    }
+    items
+}
+
+fn flatten(value: Json, items: &mut Vec<Json>) {
+    match value.get("@graph") {
+        Some(Json::Array(graph)) => items.extend(graph.iter().cloned()),
+        _ => items.push(value),
+    }
+}
+
+/// whether the item's `@type` is (or includes) `wanted`, with or without a vocabulary prefix
+pub fn has_type(item: &Json, wanted: &str) -> bool {
+    let matches = |t: &Json| {
+        t.as_str().is_some_and(|t| {
+            t.eq_ignore_ascii_case(wanted)
+                || t.rsplit(['/', '#', ':'])
+                    .next()
+                    .is_some_and(|t| t.eq_ignore_ascii_case(wanted))
+        })
+    };
+    match item.get("@type") {
+        Some(Json::Array(types)) => types.iter().any(matches),
+        Some(t) => matches(t),
+        None => false,
+    }
+}
+
+/// the attributes microdata or RDFa use for the same ideas
+struct Syntax {
+    /// present on elements that start an item
+    scope: &'static str,
+    /// names the properties, space separated
+    property: &'static str,
+    types: &'static str,
+    id: &'static str,
+}
+
+const MICRODATA: Syntax = Syntax {
+    scope: "itemscope",
+    property: "itemprop",
+    types: "itemtype",
+    id: "itemid",
+};
+
+const RDFA: Syntax = Syntax {
+    scope: "typeof",
+    property: "property",
+    types: "typeof",
+    id: "resource",
+};
+
+/// adds a property, turning it into an array if the item already has it
+fn add(fields: &mut Vec<(String, Json)>, key: &str, value: Json) {
+    match fields.iter_mut().find(|(k, _)| k == key) {
+        Some((_, Json::Array(values))) => values.push(value),
+        Some((_, existing)) => {
+            let first = std::mem::replace(existing, Json::Null);
+            *existing = Json::Array(vec![first, value]);
+        }
+        None => fields.push((key.to_string(), value)),
+    }
+}
+
+/// `https://schema.org/Product` as its vocabulary and type name
+fn split_type(full: &str) -> (Option<&str>, &str) {
+    match full.rfind(['/', '#']) {
+        Some(at) if at + 1 < full.len() => (Some(&full[..=at]), &full[at + 1..]),
+        _ => (None, full),
+    }
+}
+
+/// the nearest `vocab` declared for RDFa, like `https://schema.org/`
+fn vocabulary(element: ElementRef) -> Option<String> {
+    std::iter::once(*element)
+        .chain(element.ancestors())
+        .filter_map(ElementRef::wrap)
+        .find_map(|e| e.value().attr("vocab"))
+        .map(String::from)
+}
+
+fn value(element: ElementRef, syntax: &Syntax, base: &Url) -> Json {
+    if element.value().attr(syntax.scope).is_some() {
+        // like nested JSON-LD, inner items share the outer item's context
+        return match item(element, syntax, base) {
+            Json::Object(fields) => Json::Object(
+                fields
+                    .into_iter()
+                    .filter(|(key, _)| key != "@context")
+                    .collect(),
+            ),
+            nested => nested,
+        };
+    }
+    let attr = |name| element.value().attr(name);
+    let url = |href: &str| {
+        base.join(href.trim())
+            .map_or(href.to_string(), String::from)
+    };
+    if let Some(content) = attr("content") {
+        return content.into();
+    }
+    let linked = match element.value().name() {
+        "a" | "area" | "link" => attr("href"),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => attr("src"),
+        "object" => attr("data"),
+        _ => attr("resource").filter(|_| syntax.property == "property"),
+    };
+    if let Some(linked) = linked {
+        return url(linked).into();
+    }
+    match element.value().name() {
+        "data" | "meter" if attr("value").is_some() => attr("value").into(),
+        "time" if attr("datetime").is_some() => attr("datetime").into(),
+        _ => {
+            let text: String = element.text().collect();
+            text.split_whitespace().collect::<Vec<_>>().join(" ").into()
+        }
+    }
+}
+
+/// collects properties below `element`, leaving nested items to themselves
+fn properties(element: ElementRef, syntax: &Syntax, base: &Url, fields: &mut Vec<(String, Json)>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        if let Some(names) = child.value().attr(syntax.property) {
+            let value = value(child, syntax, base);
+            for name in names.split_whitespace() {
+                // RDFa names may carry a prefix like `schema:name`
+                let name = name.rsplit(':').next().unwrap_or(name);
+                add(fields, name, value.clone());
+            }
+        }
+        if child.value().attr(syntax.scope).is_none() {
+            properties(child, syntax, base, fields);
+        }
+    }
+}
+
+fn item(element: ElementRef, syntax: &Syntax, base: &Url) -> Json {
+    let mut fields = Vec::new();
+    let declared = element.value().attr(syntax.types).unwrap_or_default();
+    let mut context = match syntax.property {
+        "property" => vocabulary(element),
+        _ => None,
+    };
+    let mut types = Vec::new();
+    for full in declared.split_whitespace() {
+        let (vocabulary, name) = split_type(full);
+        if let Some(vocabulary) = vocabulary {
+            context.get_or_insert_with(|| vocabulary.trim_end_matches('/').to_string());
+        }
+        types.push(Json::from(name.rsplit(':').next().unwrap_or(name)));
+    }
+    if let Some(context) = context {
+        fields.push(("@context".to_string(), context.trim_end_matches('/').into()));
+    }
+    match types.len() {
+        0 => {}
+        1 => fields.push(("@type".to_string(), types.remove(0))),
+        _ => fields.push(("@type".to_string(), Json::Array(types))),
+    }
+    if let Some(id) = element.value().attr(syntax.id) {
+        fields.push(("@id".to_string(), id.into()));
+    }
+    properties(element, syntax, base, &mut fields);
+    Json::Object(fields)
+}
+
+/// top level microdata and RDFa items, in the same shape as JSON-LD
+fn embedded(document: &Html, syntax: &Syntax, base: &Url) -> Vec<Json> {
+    let selector = Selector::parse(&format!("[{}]", syntax.scope)).unwrap();
+    document
+        .select(&selector)
+        .filter(|element| element.value().attr(syntax.property).is_none())
+        .map(|element| item(element, syntax, base))
+        .collect()
+}
+
+/// JSON-LD, microdata and RDFa items of the page
+pub fn items(document: &Html, base: &Url) -> Vec<Json> {
+    let mut items = json_ld(document);
+    items.extend(embedded(document, &MICRODATA, base));
+    items.extend(embedded(document, &RDFA, base));
+    items
+}
@@ -0,0 +1,188 @@
+use reqwest::Client;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashSet;
+
+use crate::{
+    download,
+    explore::{is_identifier, unique_selector},
+    Error,
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct SuggestArgs {
+    /// the page to look at
+    url: String,
+
+    /// text the wanted elements contain, like a price or a name
+    #[clap(short, long)]
+    contains: String,
+
+    /// how many selectors to suggest
+    #[clap(short = 'n', long, default_value_t = 5)]
+    limit: usize,
+}
+
+/// attributes that usually name what an element is rather than how it looks
+const STABLE_ATTRIBUTES: &[&str] = &[
+    "itemprop",
+    "name",
+    "data-testid",
+    "data-test",
+    "data-qa",
+    "aria-label",
+];
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn text(element: ElementRef) -> String {
+    collapse(&element.text().collect::<String>())
+}
+
+/// a name that looks generated by a build tool, like `css-1x2ab` or `sc-bdVaJa`
+fn is_generated(name: &str) -> bool {
+    name.chars().any(|c| c.is_ascii_digit())
+        || name.starts_with("css-")
+        || name.starts_with("sc-")
+        || name.starts_with("jsx-")
+}
+
+/// the innermost elements containing the text, and the attribute holding it if it isn't their text
+fn targets<'a>(document: &'a Html, example: &str) -> Vec<(ElementRef<'a>, Option<String>)> {
+    let contains = |element: ElementRef| text(element).contains(example);
+    let mut targets = Vec::new();
+    for element in document
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+    {
+        if matches!(element.value().name(), "script" | "style") {
+            continue;
+        }
+        if contains(element) {
+            if !element
+                .children()
+                .filter_map(ElementRef::wrap)
+                .any(contains)
+            {
+                targets.push((element, None));
+            }
+        } else if let Some((name, _)) = element
+            .value()
+            .attrs()
+            .find(|(_, value)| collapse(value).contains(example))
+        {
+            targets.push((element, Some(name.to_string())));
+        }
+    }
+    targets
+}
+
+/// ways to select the element on its own, like `#price`, `span.amount` or `[itemprop="price"]`
+fn parts(element: ElementRef) -> Vec<String> {
+    let value = element.value();
+    let name = value.name();
+    let mut parts = Vec::new();
+    if let Some(id) = value.id().filter(|id| is_identifier(id)) {
+        parts.push(format!("#{}", id));
+    }
+    for attribute in STABLE_ATTRIBUTES {
+        if let Some(content) = value.attr(attribute) {
+            let content = content.replace('\\', "\\\\").replace('"', "\\\"");
+            parts.push(format!("{}[{}=\"{}\"]", name, attribute, content));
+        }
+    }
+    let classes: Vec<_> = value.classes().filter(|c| is_identifier(c)).collect();
+    for class in &classes {
+        parts.push(format!("{}.{}", name, class));
+    }
+    if classes.len() > 1 {
+        parts.push(format!("{}.{}", name, classes.join(".")));
+    }
+    parts.push(name.to_string());
+    parts
+}
+
+/// selectors for the element, alone and below its nearest distinctive ancestors
+fn candidates(element: ElementRef) -> Vec<String> {
+    let own = parts(element);
+    let mut candidates = own.clone();
+    let anchors = element
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .take_while(|ancestor| !matches!(ancestor.value().name(), "body" | "html"))
+        .take(4);
+    for ancestor in anchors {
+        for anchor in parts(ancestor)
+            .iter()
+            .filter(|part| part.contains(['#', '.', '[']))
+        {
+            for part in &own {
+                candidates.push(format!("{} {}", anchor, part));
+            }
+        }
+    }
+    candidates.push(unique_selector(element));
+    candidates
+}
+
+/// how much a selector depends on things that tend to change between deploys
+fn fragility(selector: &str) -> usize {
+    let generated = selector
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .filter(|name| is_generated(name))
+        .count();
+    generated + selector.matches(":nth-of-type").count() * 2
+}
+
+pub async fn run(client: &Client, args: SuggestArgs) -> Result<(), Error> {
+    let page = download(client, &args.url).await?;
+    let document = Html::parse_document(&page.body);
+    let example = collapse(&args.contains);
+    let targets = targets(&document, &example);
+    if targets.is_empty() {
+        return Err(format!("No element on '{}' contains '{}'", page.url, example).into());
+    }
+    let wanted: HashSet<_> = targets.iter().map(|(element, _)| element.id()).collect();
+    let attribute = targets[0].1.as_ref().map(|name| format!("@{}", name));
+
+    let mut seen = HashSet::new();
+    let mut ranked = Vec::new();
+    for candidate in targets.iter().flat_map(|(element, _)| candidates(*element)) {
+        if !seen.insert(candidate.clone()) {
+            continue;
+        }
+        let selector = match Selector::parse(&candidate) {
+            Ok(selector) => selector,
+            Err(_) => continue,
+        };
+        let matched: Vec<_> = document.select(&selector).map(|m| m.id()).collect();
+        let found = matched.iter().filter(|id| wanted.contains(id)).count();
+        if found == 0 {
+            continue;
+        }
+        let missed = wanted.len() - found;
+        let extra = matched.len() - found;
+        // a bare tag name for the element itself matches too much as soon as the page changes
+        let own = candidate.rsplit(' ').next().unwrap_or_default();
+        let bare = !own.contains(['#', '.', '[']);
+        let rank = (missed, extra, fragility(&candidate), bare, candidate.len());
+        ranked.push((rank, candidate, matched.len()));
+    }
+    ranked.sort();
+
+    for (_, selector, count) in ranked.into_iter().take(args.limit) {
+        let count = match count {
+            1 => "1 match".to_string(),
+            n => format!("{} matches", n),
+        };
+        println!(
+            "{}{}\t{}",
+            selector,
+            attribute.as_deref().unwrap_or(""),
+            count
+        );
+    }
+    Ok(())
+}
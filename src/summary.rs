@@ -0,0 +1,91 @@
+//! how a batch with --keep-going went, printed on stderr at the end
+
+use reqwest::Url;
+use std::time::Duration;
+
+use crate::{json::Json, Error, ErrorFormat};
+
+/// how many of the slowest pages to list
+const SLOWEST: usize = 5;
+
+#[derive(Default)]
+pub struct Summary {
+    ok: usize,
+    failures: Vec<Error>,
+    skipped: usize,
+    slowest: Vec<(Duration, Url)>,
+}
+
+impl Summary {
+    pub fn ok(&mut self, url: &Url, elapsed: Duration) {
+        self.ok += 1;
+        self.slowest.push((elapsed, url.clone()));
+        self.slowest
+            .sort_by_key(|(elapsed, _)| std::cmp::Reverse(*elapsed));
+        self.slowest.truncate(SLOWEST);
+    }
+
+    pub fn failed(&mut self, error: Error) {
+        self.failures.push(error);
+    }
+
+    /// counts urls that were never processed, after --count or Ctrl-C
+    pub fn skipped(&mut self, urls: usize) {
+        self.skipped += urls;
+    }
+
+    pub fn processed(&self) -> usize {
+        self.ok + self.failures.len()
+    }
+
+    /// the error to exit with if any url failed
+    pub fn result(&self) -> Result<(), Error> {
+        match self.failures.len() {
+            0 => Ok(()),
+            failed => Err(format!("{} of {} urls failed", failed, self.processed()).into()),
+        }
+    }
+
+    pub fn print(&self, format: ErrorFormat) {
+        match format {
+            ErrorFormat::Text => {
+                eprintln!("ok       {:>6}", self.ok);
+                eprintln!("failed   {:>6}", self.failures.len());
+                eprintln!("skipped  {:>6}", self.skipped);
+                if !self.failures.is_empty() {
+                    eprintln!("\nfailed");
+                    for error in &self.failures {
+                        eprintln!("  {}", error);
+                    }
+                }
+                if !self.slowest.is_empty() {
+                    eprintln!("\nslowest");
+                    for (elapsed, url) in &self.slowest {
+                        eprintln!("  {:>8.3}s  {}", elapsed.as_secs_f64(), url);
+                    }
+                }
+            }
+            ErrorFormat::Json => {
+                let failures = self.failures.iter().map(Error::to_json).collect();
+                let slowest = self
+                    .slowest
+                    .iter()
+                    .map(|(elapsed, url)| {
+                        Json::object([
+                            ("url", url.as_str().into()),
+                            ("elapsed_ms", (elapsed.as_millis() as u64).into()),
+                        ])
+                    })
+                    .collect();
+                let summary = Json::object([
+                    ("ok", self.ok.into()),
+                    ("failed", self.failures.len().into()),
+                    ("skipped", self.skipped.into()),
+                    ("failures", Json::Array(failures)),
+                    ("slowest", Json::Array(slowest)),
+                ]);
+                eprintln!("{}", summary);
+            }
+        }
+    }
+}
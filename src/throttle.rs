@@ -0,0 +1,69 @@
+use reqwest::Url;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::{sleep_until, Instant},
+};
+
+/// limits how hard a single host gets hit
+pub struct Throttle {
+    per_host: Option<usize>,
+    delay: Option<Duration>,
+    hosts: Mutex<HashMap<String, Host>>,
+}
+
+struct Host {
+    slots: Arc<Semaphore>,
+    next_request: Instant,
+    delay: Option<Duration>,
+}
+
+impl Throttle {
+    pub fn new(per_host: Option<usize>, delay: Option<Duration>) -> Self {
+        Throttle {
+            per_host,
+            delay,
+            hosts: Default::default(),
+        }
+    }
+
+    /// waits for a free slot on the url's host, hold on to the permit while requesting
+    pub async fn acquire(&self, url: &Url) -> Option<OwnedSemaphorePermit> {
+        let host = url.host_str().unwrap_or_default().to_string();
+        let slots = self.host(&host, |h| h.slots.clone());
+        let permit = match self.per_host {
+            Some(_) => slots.acquire_owned().await.ok(),
+            None => None,
+        };
+
+        let now = Instant::now();
+        let wait_until = self.host(&host, |h| {
+            let at = h.next_request.max(now);
+            h.next_request = at + h.delay.unwrap_or_default();
+            at
+        });
+        sleep_until(wait_until).await;
+
+        permit
+    }
+
+    /// makes the host wait at least `delay` between requests, e.g. for a robots.txt crawl-delay
+    pub fn slow_down(&self, url: &Url, delay: Duration) {
+        let host = url.host_str().unwrap_or_default();
+        self.host(host, |h| h.delay = h.delay.max(Some(delay)));
+    }
+
+    fn host<T>(&self, host: &str, f: impl FnOnce(&mut Host) -> T) -> T {
+        let mut hosts = self.hosts.lock().unwrap();
+        let host = hosts.entry(host.to_string()).or_insert_with(|| Host {
+            slots: Arc::new(Semaphore::new(self.per_host.unwrap_or(1))),
+            next_request: Instant::now(),
+            delay: self.delay,
+        });
+        f(host)
+    }
+}
@@ -0,0 +1,120 @@
+//! where the time of every request went, and statistics over all of them
+
+use reqwest::Url;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::net::{lookup_host, TcpStream};
+use tokio_native_tls::{native_tls, TlsConnector};
+
+use crate::json::Json;
+
+/// phases of a request, the connection ones measured on a probe connection of their own
+pub struct Timing {
+    pub url: String,
+    pub dns: Option<Duration>,
+    pub connect: Option<Duration>,
+    pub tls: Option<Duration>,
+    /// until the response headers are in
+    pub ttfb: Duration,
+    pub total: Duration,
+}
+
+static TIMINGS: Mutex<Vec<Timing>> = Mutex::new(Vec::new());
+
+/// how long resolving the host, connecting to it and the TLS handshake take
+pub async fn probe(url: &Url) -> (Option<Duration>, Option<Duration>, Option<Duration>) {
+    let (host, port) = match (url.host_str(), url.port_or_known_default()) {
+        (Some(host), Some(port)) => (host, port),
+        _ => return (None, None, None),
+    };
+    let started = Instant::now();
+    let address = match lookup_host((host, port))
+        .await
+        .ok()
+        .and_then(|mut a| a.next())
+    {
+        Some(address) => address,
+        None => return (None, None, None),
+    };
+    let dns = started.elapsed();
+
+    let started = Instant::now();
+    let stream = match TcpStream::connect(address).await {
+        Ok(stream) => stream,
+        Err(_) => return (Some(dns), None, None),
+    };
+    let connect = started.elapsed();
+    if url.scheme() != "https" {
+        return (Some(dns), Some(connect), None);
+    }
+
+    let started = Instant::now();
+    let connector = match native_tls::TlsConnector::new() {
+        Ok(connector) => TlsConnector::from(connector),
+        Err(_) => return (Some(dns), Some(connect), None),
+    };
+    let tls = connector
+        .connect(host, stream)
+        .await
+        .ok()
+        .map(|_| started.elapsed());
+    (Some(dns), Some(connect), tls)
+}
+
+/// milliseconds with microsecond precision, as timings are reported in json
+pub fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1e6).round() / 1e3
+}
+
+/// prints the timing as a json line on stderr and keeps it for the summary
+pub fn record(timing: Timing) {
+    let phase = |duration: Option<Duration>| duration.map(millis);
+    let line = Json::object([
+        ("url", timing.url.as_str().into()),
+        ("dns_ms", phase(timing.dns).into()),
+        ("connect_ms", phase(timing.connect).into()),
+        ("tls_ms", phase(timing.tls).into()),
+        ("ttfb_ms", millis(timing.ttfb).into()),
+        ("total_ms", millis(timing.total).into()),
+    ]);
+    eprintln!("{}", line);
+    TIMINGS.lock().unwrap().push(timing);
+}
+
+/// min, mean, median, 95th percentile and max in milliseconds
+fn statistics(mut values: Vec<f64>) -> Json {
+    if values.is_empty() {
+        return Json::Null;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |p: f64| values[((values.len() - 1) as f64 * p).round() as usize];
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    Json::object([
+        ("min", values[0].into()),
+        ("mean", ((mean * 1e3).round() / 1e3).into()),
+        ("p50", percentile(0.5).into()),
+        ("p95", percentile(0.95).into()),
+        ("max", values[values.len() - 1].into()),
+    ])
+}
+
+/// statistics over every request of the run, none if there was only one
+pub fn summary() -> Option<Json> {
+    let timings = TIMINGS.lock().unwrap();
+    if timings.len() < 2 {
+        return None;
+    }
+    let phase = |get: fn(&Timing) -> Option<Duration>| {
+        statistics(timings.iter().filter_map(get).map(millis).collect())
+    };
+    Some(Json::object([
+        ("requests", timings.len().into()),
+        ("dns_ms", phase(|t| t.dns)),
+        ("connect_ms", phase(|t| t.connect)),
+        ("tls_ms", phase(|t| t.tls)),
+        ("ttfb_ms", phase(|t| Some(t.ttfb))),
+        ("total_ms", phase(|t| Some(t.total))),
+    ]))
+}
@@ -0,0 +1,330 @@
+//! the subset of toml used by config files, read into [`Json`] values: tables, arrays of
+//! tables, strings, numbers, booleans and single-line arrays
+
+use crate::json::Json;
+
+pub fn parse(input: &str) -> Result<Json, String> {
+    let mut root = Json::Object(Vec::new());
+    // the key path of the table that `key = value` lines go into
+    let mut current: Vec<String> = Vec::new();
+
+    for (number, line) in input.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        let error = |message: &str| format!("line {}: {}", number + 1, message);
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix("[[") {
+            let header = header
+                .strip_suffix("]]")
+                .ok_or_else(|| error("missing ]]"))?;
+            current = keys(header).map_err(|e| error(&e))?;
+            let (last, parents) = current.split_last().unwrap();
+            match table(&mut root, parents).map_err(|e| error(&e))? {
+                Json::Object(fields) => match fields.iter_mut().find(|(k, _)| k == last) {
+                    Some((_, Json::Array(tables))) => tables.push(Json::Object(Vec::new())),
+                    Some(_) => return Err(error(&format!("'{}' is not an array", last))),
+                    None => fields.push((last.clone(), Json::Array(vec![Json::Object(vec![])]))),
+                },
+                _ => unreachable!(),
+            }
+        } else if let Some(header) = line.strip_prefix('[') {
+            let header = header.strip_suffix(']').ok_or_else(|| error("missing ]"))?;
+            current = keys(header).map_err(|e| error(&e))?;
+            table(&mut root, &current).map_err(|e| error(&e))?;
+        } else {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected key = value"))?;
+            let key = keys(key).map_err(|e| error(&e))?;
+            let mut reader = Reader { rest: value.trim() };
+            let value = reader.value().map_err(|e| error(&e))?;
+            if !reader.rest.trim().is_empty() {
+                return Err(error(&format!("unexpected '{}'", reader.rest.trim())));
+            }
+            let (last, parents) = key.split_last().unwrap();
+            let path: Vec<_> = current.iter().chain(parents).cloned().collect();
+            match table(&mut root, &path).map_err(|e| error(&e))? {
+                Json::Object(fields) if fields.iter().any(|(k, _)| k == last) => {
+                    return Err(error(&format!("duplicate key '{}'", last)))
+                }
+                Json::Object(fields) => fields.push((last.clone(), value)),
+                _ => unreachable!(),
+            }
+        }
+    }
+    Ok(root)
+}
+
+/// drops a `#` comment that isn't inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('\\', Some('"')) if !escaped => {
+                escaped = true;
+                continue;
+            }
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q && !escaped => quote = None,
+            ('#', None) => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// `a.b."c d"` as its parts
+fn keys(input: &str) -> Result<Vec<String>, String> {
+    let mut reader = Reader { rest: input.trim() };
+    let mut keys = Vec::new();
+    loop {
+        let key = match reader.rest.chars().next() {
+            Some('"' | '\'') => match reader.value()? {
+                Json::String(key) => key,
+                _ => unreachable!(),
+            },
+            _ => {
+                let end = reader
+                    .rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-')
+                    .unwrap_or(reader.rest.len());
+                if end == 0 {
+                    return Err(format!("invalid key '{}'", input.trim()));
+                }
+                let (key, rest) = reader.rest.split_at(end);
+                reader.rest = rest;
+                key.to_string()
+            }
+        };
+        keys.push(key);
+        reader.rest = reader.rest.trim_start();
+        match reader.rest.strip_prefix('.') {
+            Some(rest) => reader.rest = rest.trim_start(),
+            None if reader.rest.is_empty() => return Ok(keys),
+            None => return Err(format!("invalid key '{}'", input.trim())),
+        }
+    }
+}
+
+/// the table at `path`, created if needed, the last one for arrays of tables
+fn table<'a>(root: &'a mut Json, path: &[String]) -> Result<&'a mut Json, String> {
+    let mut node = root;
+    for key in path {
+        let fields = match node {
+            Json::Object(fields) => fields,
+            _ => return Err(format!("'{}' is not a table", key)),
+        };
+        let at = match fields.iter().position(|(k, _)| k == key) {
+            Some(at) => at,
+            None => {
+                fields.push((key.clone(), Json::Object(Vec::new())));
+                fields.len() - 1
+            }
+        };
+        node = match &mut fields[at].1 {
+            Json::Array(tables) => tables
+                .last_mut()
+                .ok_or_else(|| format!("'{}' is not a table", key))?,
+            table @ Json::Object(_) => table,
+            _ => return Err(format!("'{}' is not a table", key)),
+        };
+    }
+    Ok(node)
+}
+
+struct Reader<'a> {
+    rest: &'a str,
+}
+
+impl Reader<'_> {
+    fn value(&mut self) -> Result<Json, String> {
+        let rest = self.rest;
+        if let Some(literal) = rest.strip_prefix('\'') {
+            let end = literal.find('\'').ok_or("unterminated string")?;
+            self.rest = &literal[end + 1..];
+            return Ok(literal[..end].into());
+        }
+        if let Some(mut chars) = rest.strip_prefix('"').map(str::char_indices) {
+            let mut value = String::new();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        self.rest = &rest[i + 2..];
+                        return Ok(value.into());
+                    }
+                    '\\' => match chars.next().map(|(_, c)| c) {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some('r') => value.push('\r'),
+                        Some('"') => value.push('"'),
+                        Some('\\') => value.push('\\'),
+                        Some('u') => {
+                            let hex: String = (&mut chars).take(4).map(|(_, c)| c).collect();
+                            let code = u32::from_str_radix(&hex, 16).ok();
+                            value.push(code.and_then(char::from_u32).ok_or("invalid \\u escape")?);
+                        }
+                        _ => return Err("invalid escape".to_string()),
+                    },
+                    c => value.push(c),
+                }
+            }
+            return Err("unterminated string".to_string());
+        }
+        if let Some(mut rest) = rest.strip_prefix('[') {
+            let mut values = Vec::new();
+            loop {
+                rest = rest.trim_start();
+                if let Some(after) = rest.strip_prefix(']') {
+                    self.rest = after;
+                    return Ok(Json::Array(values));
+                }
+                self.rest = rest;
+                values.push(self.value()?);
+                rest = self.rest.trim_start();
+                match rest.strip_prefix(',') {
+                    Some(after) => rest = after,
+                    None if rest.starts_with(']') => {}
+                    None => return Err("expected , or ] in array".to_string()),
+                }
+            }
+        }
+        let end = rest
+            .find(|c: char| c == ',' || c == ']' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let (word, after) = rest.split_at(end);
+        self.rest = after;
+        match word {
+            "true" => Ok(true.into()),
+            "false" => Ok(false.into()),
+            _ => word
+                .replace('_', "")
+                .parse::<f64>()
+                .map(Json::Number)
+                .map_err(|_| format!("invalid value '{}'", word)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config() {
+        let config = parse(
+            "# scrape\nuser_agent = \"scrape/1.0\"\ntimeout = 30\n\n[headers]\nAccept-Language = \"en\"\n\n\
+             [profile.work]\nproxy = 'http://proxy:3128' # office\nretry = true\n\n\
+             [[job]]\nname = \"a\"\ntags = [\"x\", 'y', ]\n\n[[job]]\nname = \"b\"\nevery.minutes = 1_000\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Json::object(vec![
+                ("user_agent", "scrape/1.0".into()),
+                ("timeout", 30.0.into()),
+                (
+                    "headers",
+                    Json::object(vec![("Accept-Language", "en".into())])
+                ),
+                (
+                    "profile",
+                    Json::object(vec![(
+                        "work",
+                        Json::object(vec![
+                            ("proxy", "http://proxy:3128".into()),
+                            ("retry", true.into()),
+                        ])
+                    )])
+                ),
+                (
+                    "job",
+                    Json::Array(vec![
+                        Json::object(vec![("name", "a".into()), ("tags", vec!["x", "y"].into())]),
+                        Json::object(vec![
+                            ("name", "b".into()),
+                            ("every", Json::object(vec![("minutes", 1000.0.into())])),
+                        ]),
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn quoting() {
+        let parsed = parse(
+            "basic = \"tab\\there \\\"quoted\\\" \\u00e9 # not a comment\"\n\
+             literal = 'C:\\path # not either'\n\"spaced key\" = 1\n'literal key'.nested = -2.5\n\
+             empty = \"\"\nnested = [[1, 2], [\"]\"]]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            Json::object(vec![
+                (
+                    "basic",
+                    "tab\there \"quoted\" \u{e9} # not a comment".into()
+                ),
+                ("literal", "C:\\path # not either".into()),
+                ("spaced key", 1.0.into()),
+                ("literal key", Json::object(vec![("nested", (-2.5).into())])),
+                ("empty", "".into()),
+                (
+                    "nested",
+                    Json::Array(vec![
+                        Json::Array(vec![1.0.into(), 2.0.into()]),
+                        vec!["]"].into(),
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn nesting() {
+        let parsed =
+            parse("[a.b]\nc = 1\n[a]\nd = 2\n[[a.list]]\ne = 3\n[a.list.f]\ng = 4\n").unwrap();
+        assert_eq!(
+            parsed,
+            Json::object(vec![(
+                "a",
+                Json::object(vec![
+                    ("b", Json::object(vec![("c", 1.0.into())])),
+                    ("d", 2.0.into()),
+                    (
+                        "list",
+                        Json::Array(vec![Json::object(vec![
+                            ("e", 3.0.into()),
+                            ("f", Json::object(vec![("g", 4.0.into())])),
+                        ])])
+                    ),
+                ])
+            )])
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = |input: &str| parse(input).unwrap_err();
+        assert_eq!(error("a = 1\nb\n"), "line 2: expected key = value");
+        assert_eq!(
+            error("a = 1\n\nb = \"open\n"),
+            "line 3: unterminated string"
+        );
+        assert_eq!(error("a = 'open\n"), "line 1: unterminated string");
+        assert_eq!(error("a = \"\\x\"\n"), "line 1: invalid escape");
+        assert_eq!(error("[table\n"), "line 1: missing ]");
+        assert_eq!(error("[[tables]\n"), "line 1: missing ]]");
+        assert_eq!(error("a = 1\na = 2\n"), "line 2: duplicate key 'a'");
+        assert_eq!(error("a = 1\n[a]\n"), "line 2: 'a' is not a table");
+        assert_eq!(error("a = 1\n[[a]]\n"), "line 2: 'a' is not an array");
+        assert_eq!(error("a = yes\n"), "line 1: invalid value 'yes'");
+        assert_eq!(error("a = \"x\" \"y\"\n"), "line 1: unexpected '\"y\"'");
+        assert_eq!(error("a = [1 2]\n"), "line 1: expected , or ] in array");
+        assert_eq!(error("a b = 1\n"), "line 1: invalid key 'a b'");
+        assert_eq!(error("= 1\n"), "line 1: invalid key ''");
+    }
+}
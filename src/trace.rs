@@ -0,0 +1,67 @@
+//! curl style tracing of requests and responses on stderr
+
+use reqwest::{header::HeaderMap, redirect, ClientBuilder, Request, Response, StatusCode, Url};
+
+/// a client builder that traces the redirects it follows when verbose
+pub fn client(verbose: bool) -> ClientBuilder {
+    let policy = redirect::Policy::custom(move |attempt| {
+        // reqwest's default limit
+        if attempt.previous().len() > 10 {
+            return attempt.error("too many redirects");
+        }
+        if attempt.previous().contains(attempt.url()) {
+            let error = format!("redirect loop back to {}", attempt.url());
+            return attempt.error(error);
+        }
+        if verbose {
+            redirect(attempt.status(), attempt.url());
+        }
+        attempt.follow()
+    });
+    ClientBuilder::new().redirect(policy)
+}
+
+fn headers(marker: char, headers: &HeaderMap) {
+    for (name, value) in headers {
+        eprintln!(
+            "{} {}: {}",
+            marker,
+            name,
+            String::from_utf8_lossy(value.as_bytes())
+        );
+    }
+    eprintln!("{}", marker);
+}
+
+/// `> GET /path HTTP/1.1`, the host and the headers reqwest was asked to send
+pub fn request(request: &Request) {
+    let url = request.url();
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    eprintln!("* Requesting {}", url);
+    eprintln!("> {} {} {:?}", request.method(), target, request.version());
+    if let Some(host) = url.host_str() {
+        match url.port() {
+            Some(port) => eprintln!("> host: {}:{}", host, port),
+            None => eprintln!("> host: {}", host),
+        }
+    }
+    headers('>', request.headers());
+}
+
+pub fn redirect(status: StatusCode, to: &Url) {
+    eprintln!("< {}", status);
+    eprintln!("* Following redirect to {}", to);
+}
+
+pub fn refresh(to: &Url) {
+    eprintln!("* Following meta refresh to {}", to);
+}
+
+/// `< HTTP/1.1 200 OK` and the headers of the final response
+pub fn response(response: &Response) {
+    eprintln!("< {:?} {}", response.version(), response.status());
+    headers('<', response.headers());
+}
@@ -0,0 +1,166 @@
+//! html5ever's parse errors with the line they were found on
+
+use html5ever::{
+    tendril::{StrTendril, TendrilSink},
+    tokenizer::TokenizerOpts,
+    tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeBuilderOpts, TreeSink},
+    Attribute, ExpandedName, ParseOpts, QualName,
+};
+use regex::Regex;
+use scraper::Html;
+use std::borrow::Cow;
+
+/// scraper's node ids
+type NodeId = <Html as TreeSink>::Handle;
+
+/// builds the same tree as scraper, but keeps track of lines for the errors
+struct Validator {
+    html: Html,
+    line: u64,
+    errors: Vec<(u64, Cow<'static, str>)>,
+    quirks: bool,
+}
+
+impl TreeSink for Validator {
+    type Output = Self;
+    type Handle = NodeId;
+
+    fn finish(self) -> Self {
+        self
+    }
+
+    fn parse_error(&mut self, msg: Cow<'static, str>) {
+        self.errors.push((self.line, msg));
+    }
+
+    fn set_current_line(&mut self, line_number: u64) {
+        self.line = line_number;
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.quirks = mode == QuirksMode::Quirks;
+        self.html.set_quirks_mode(mode);
+    }
+
+    fn get_document(&mut self) -> NodeId {
+        self.html.get_document()
+    }
+
+    fn same_node(&self, x: &NodeId, y: &NodeId) -> bool {
+        self.html.same_node(x, y)
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a NodeId) -> ExpandedName<'a> {
+        self.html.elem_name(target)
+    }
+
+    fn create_element(
+        &mut self,
+        name: QualName,
+        attrs: Vec<Attribute>,
+        flags: ElementFlags,
+    ) -> NodeId {
+        self.html.create_element(name, attrs, flags)
+    }
+
+    fn create_comment(&mut self, text: StrTendril) -> NodeId {
+        self.html.create_comment(text)
+    }
+
+    fn create_pi(&mut self, target: StrTendril, data: StrTendril) -> NodeId {
+        self.html.create_pi(target, data)
+    }
+
+    fn append(&mut self, parent: &NodeId, child: NodeOrText<NodeId>) {
+        self.html.append(parent, child)
+    }
+
+    fn append_based_on_parent_node(
+        &mut self,
+        element: &NodeId,
+        prev_element: &NodeId,
+        child: NodeOrText<NodeId>,
+    ) {
+        self.html
+            .append_based_on_parent_node(element, prev_element, child)
+    }
+
+    fn append_doctype_to_document(
+        &mut self,
+        name: StrTendril,
+        public_id: StrTendril,
+        system_id: StrTendril,
+    ) {
+        self.html
+            .append_doctype_to_document(name, public_id, system_id)
+    }
+
+    fn get_template_contents(&mut self, target: &NodeId) -> NodeId {
+        self.html.get_template_contents(target)
+    }
+
+    fn append_before_sibling(&mut self, sibling: &NodeId, new_node: NodeOrText<NodeId>) {
+        self.html.append_before_sibling(sibling, new_node)
+    }
+
+    fn add_attrs_if_missing(&mut self, target: &NodeId, attrs: Vec<Attribute>) {
+        self.html.add_attrs_if_missing(target, attrs)
+    }
+
+    fn remove_from_parent(&mut self, target: &NodeId) {
+        self.html.remove_from_parent(target)
+    }
+
+    fn reparent_children(&mut self, node: &NodeId, new_parent: &NodeId) {
+        self.html.reparent_children(node, new_parent)
+    }
+}
+
+/// turns the Debug dumps in html5ever's messages into `<div>` and `</div>`
+fn tidy(message: &str) -> String {
+    let tag = Regex::new(
+        r"TagToken\(Tag \{ kind: (StartTag|EndTag), name: Atom\(\\?'([^'\\]+)\\?' type=\w+\).*?\}\)",
+    )
+    .unwrap();
+    let atom = Regex::new(r"Atom\(\\?'([^'\\]+)\\?' type=\w+\)").unwrap();
+    let characters = Regex::new(r"CharacterTokens\(.*\) in").unwrap();
+    let message = tag.replace_all(message, |captures: &regex::Captures| match &captures[1] {
+        "EndTag" => format!("</{}>", &captures[2]),
+        _ => format!("<{}>", &captures[2]),
+    });
+    let message = atom.replace_all(&message, "<$1>");
+    characters.replace_all(&message, "in").into_owned()
+}
+
+/// every parse error as `line N: message`, html5ever does not track columns
+pub fn validate(body: &str) -> Vec<String> {
+    let validator = Validator {
+        html: Html::new_document(),
+        line: 1,
+        errors: Vec::new(),
+        quirks: false,
+    };
+    let opts = ParseOpts {
+        tokenizer: TokenizerOpts {
+            exact_errors: true,
+            ..Default::default()
+        },
+        tree_builder: TreeBuilderOpts {
+            exact_errors: true,
+            ..Default::default()
+        },
+    };
+    let validator = html5ever::parse_document(validator, opts).one(body);
+
+    let mut problems: Vec<_> = validator
+        .errors
+        .into_iter()
+        .map(|(line, message)| format!("line {}: {}", line, tidy(&message)))
+        .collect();
+    // stray text in a table is reported once per character
+    problems.dedup();
+    if validator.quirks {
+        problems.push("document renders in quirks mode, add <!DOCTYPE html>".into());
+    }
+    problems
+}
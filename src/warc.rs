@@ -0,0 +1,380 @@
+use reqwest::{
+    header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, TRANSFER_ENCODING},
+    Url,
+};
+use std::{
+    collections::hash_map::RandomState,
+    fs::{self, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{datetime, gzip, parse_selector, print_page, Error, Page};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct WarcArgs {
+    #[clap(subcommand)]
+    command: WarcCommand,
+}
+
+#[derive(clap::Subcommand, Clone, Debug)]
+enum WarcCommand {
+    /// extract from the responses stored in a WARC file
+    Read(ReadArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct ReadArgs {
+    /// WARC file, optionally gzipped
+    file: PathBuf,
+
+    /// select html from every stored response
+    #[clap(short, long)]
+    selector: Option<String>,
+
+    #[clap(short, long)]
+    attribute: Option<String>,
+}
+
+fn record_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    let high = hasher.finish();
+    hasher.write_u64(1);
+    let low = hasher.finish();
+    format!(
+        "<urn:uuid:{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}>",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xfff,
+        (low >> 48) & 0x3fff | 0x8000,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+fn record(fields: &[(&str, &str)], block: &[u8]) -> Vec<u8> {
+    let mut record = b"WARC/1.0\r\n".to_vec();
+    for (name, value) in fields {
+        record.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    record.extend_from_slice(format!("Content-Length: {}\r\n\r\n", block.len()).as_bytes());
+    record.extend_from_slice(block);
+    record.extend_from_slice(b"\r\n\r\n");
+    record
+}
+
+/// http request line and host header, as far as we know them
+fn request_block(page: &Page) -> Vec<u8> {
+    let url = &page.url;
+    let target = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, host).into_bytes()
+}
+
+/// the response as stored: the body is the decoded page, so the headers about how it was sent are
+/// rewritten to describe that instead of what came over the wire
+fn response_block(page: &Page) -> Vec<u8> {
+    let mut block = format!("HTTP/1.1 {}\r\n", page.status).into_bytes();
+    for (name, value) in &page.headers {
+        if [TRANSFER_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH].contains(name) {
+            continue;
+        }
+        let value = match name == CONTENT_TYPE {
+            true => utf8_content_type(value.to_str().unwrap_or_default()),
+            false => String::from_utf8_lossy(value.as_bytes()).into_owned(),
+        };
+        block.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+    block.extend_from_slice(format!("content-length: {}\r\n\r\n", page.body.len()).as_bytes());
+    block.extend_from_slice(page.body.as_bytes());
+    block
+}
+
+/// the content type with its charset replaced by utf-8, which the body was decoded into
+fn utf8_content_type(content_type: &str) -> String {
+    let mut params: Vec<_> = content_type
+        .split(';')
+        .map(str::trim)
+        .filter(|param| !param.to_ascii_lowercase().starts_with("charset="))
+        .collect();
+    params.insert(1.min(params.len()), "charset=utf-8");
+    params.join("; ")
+}
+
+/// appends request and response records for `page`, gzipping each record for `*.gz` files
+pub fn append(path: &Path, page: &Page) -> io::Result<()> {
+    let gzip = path.extension().is_some_and(|ext| ext == "gz");
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    let date = datetime::now();
+    let uri = page.url.to_string();
+    let response_id = record_id();
+    let mut records = Vec::new();
+
+    if file.metadata()?.len() == 0 {
+        let info = format!("software: scrape/{}\r\n", env!("CARGO_PKG_VERSION"));
+        records.push(record(
+            &[
+                ("WARC-Type", "warcinfo"),
+                ("WARC-Record-ID", &record_id()),
+                ("WARC-Date", &date),
+                ("Content-Type", "application/warc-fields"),
+            ],
+            info.as_bytes(),
+        ));
+    }
+    records.push(record(
+        &[
+            ("WARC-Type", "request"),
+            ("WARC-Record-ID", &record_id()),
+            ("WARC-Date", &date),
+            ("WARC-Target-URI", &uri),
+            ("WARC-Concurrent-To", &response_id),
+            ("Content-Type", "application/http;msgtype=request"),
+        ],
+        &request_block(page),
+    ));
+    records.push(record(
+        &[
+            ("WARC-Type", "response"),
+            ("WARC-Record-ID", &response_id),
+            ("WARC-Date", &date),
+            ("WARC-Target-URI", &uri),
+            ("Content-Type", "application/http;msgtype=response"),
+        ],
+        &response_block(page),
+    ));
+
+    let mut out = Vec::new();
+    for record in records {
+        if gzip {
+            out.extend_from_slice(&gzip::compress(&record));
+        } else {
+            out.extend_from_slice(&record);
+        }
+    }
+    file.write_all(&out)
+}
+
+/// a WARC record's named fields and content block
+pub struct Record<'a> {
+    pub fields: Vec<(&'a str, &'a str)>,
+    pub block: &'a [u8],
+}
+
+impl Record<'_> {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// splits an uncompressed WARC file into its records
+pub fn records(mut data: &[u8]) -> Result<Vec<Record<'_>>, String> {
+    let mut records = Vec::new();
+    while let Some(start) = find(data, b"WARC/") {
+        data = &data[start..];
+        let header_end = find(data, b"\r\n\r\n").ok_or("truncated WARC header")?;
+        let header = std::str::from_utf8(&data[..header_end]).map_err(|e| e.to_string())?;
+        let fields: Vec<_> = header
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .collect();
+        let length: usize = fields
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("Content-Length"))
+            .and_then(|(_, value)| value.parse().ok())
+            .ok_or("WARC record without Content-Length")?;
+        let block_start = header_end + 4;
+        let block = data
+            .get(block_start..block_start + length)
+            .ok_or("truncated WARC record")?;
+        records.push(Record { fields, block });
+        data = &data[block_start + length..];
+    }
+    Ok(records)
+}
+
+/// undoes `Transfer-Encoding: chunked`, none if the body isn't chunked after all
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        let line_end = find(body, b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = size.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        if size == 0 {
+            return Some(out);
+        }
+        let chunk = body.get(line_end + 2..line_end + 2 + size)?;
+        out.extend_from_slice(chunk);
+        body = body.get(line_end + 4 + size..)?;
+    }
+}
+
+/// the body of an `application/http` response block
+pub fn http_body(block: &[u8]) -> &[u8] {
+    find(block, b"\r\n\r\n").map_or(&[], |end| &block[end + 4..])
+}
+
+fn is_chunked(block: &[u8]) -> bool {
+    let head = find(block, b"\r\n\r\n").map_or(block, |end| &block[..end]);
+    String::from_utf8_lossy(head).lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    })
+}
+
+/// the urls and bodies of the responses stored in an uncompressed WARC file
+fn responses(data: &[u8]) -> Result<Vec<(Url, String)>, String> {
+    let mut responses = Vec::new();
+    for record in records(data)? {
+        let is_response = record.field("WARC-Type") == Some("response")
+            && record
+                .field("Content-Type")
+                .is_some_and(|t| t.starts_with("application/http"));
+        let url = record
+            .field("WARC-Target-URI")
+            .and_then(|u| Url::parse(u).ok());
+        let url = match url {
+            Some(url) if is_response => url,
+            _ => continue,
+        };
+        let body = http_body(record.block);
+        // files written before the headers matched the stored body claim chunks that aren't there
+        let body = match is_chunked(record.block) {
+            true => dechunk(body).unwrap_or_else(|| body.to_vec()),
+            false => body.to_vec(),
+        };
+        responses.push((url, String::from_utf8_lossy(&body).into_owned()));
+    }
+    Ok(responses)
+}
+
+pub fn run(args: WarcArgs) -> Result<(), Error> {
+    let WarcCommand::Read(args) = args.command;
+    let data = fs::read(&args.file)?;
+    let data = if gzip::is_gzip(&data) {
+        gzip::decompress(&data)?
+    } else {
+        data
+    };
+    let selector = args.selector.as_deref().map(parse_selector).transpose()?;
+
+    for (url, body) in responses(&data)? {
+        print_page(
+            &body,
+            Some(&url),
+            selector.as_ref(),
+            args.attribute.as_deref(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::{
+        header::{HeaderMap, HeaderValue},
+        StatusCode,
+    };
+
+    fn page(url: &str, headers: &[(&'static str, &'static str)], body: &str) -> Page {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            map.append(*name, HeaderValue::from_static(value));
+        }
+        Page {
+            url: Url::parse(url).unwrap(),
+            status: StatusCode::OK,
+            headers: map,
+            body: body.to_string(),
+            size: body.len(),
+            evaluated: None,
+        }
+    }
+
+    fn round_trip(name: &str, pages: &[Page]) -> Vec<(Url, String)> {
+        let path = std::env::temp_dir().join(format!("scrape-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        for page in pages {
+            append(&path, page).unwrap();
+        }
+        let data = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let data = match gzip::is_gzip(&data) {
+            true => gzip::decompress(&data).unwrap(),
+            false => data,
+        };
+        responses(&data).unwrap()
+    }
+
+    #[test]
+    fn write_then_read() {
+        let pages = [
+            page(
+                "https://example.com/chunked",
+                &[
+                    ("content-type", "text/html; charset=windows-1251"),
+                    ("transfer-encoding", "chunked"),
+                ],
+                "<p>Привет</p>",
+            ),
+            page(
+                "https://example.com/plain?page=2",
+                &[("content-type", "text/plain"), ("content-length", "3")],
+                "two",
+            ),
+        ];
+        for name in ["test.warc", "test.warc.gz"] {
+            let responses = round_trip(name, &pages);
+            let expected: Vec<_> = pages
+                .iter()
+                .map(|page| (page.url.clone(), page.body.clone()))
+                .collect();
+            assert_eq!(responses, expected);
+        }
+    }
+
+    #[test]
+    fn stored_headers_match_the_body() {
+        let chunked = page(
+            "https://example.com/",
+            &[
+                ("content-type", "text/html;charset=ISO-8859-1"),
+                ("transfer-encoding", "chunked"),
+                ("content-encoding", "identity"),
+            ],
+            "café",
+        );
+        let block = String::from_utf8(response_block(&chunked)).unwrap();
+        assert_eq!(
+            block,
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: 5\r\n\r\ncafé"
+        );
+    }
+
+    #[test]
+    fn dechunks_only_chunked_bodies() {
+        let chunked = b"4\r\nWiki\r\n7;ext=1\r\npedia i\r\n0\r\n\r\n";
+        assert_eq!(dechunk(chunked).unwrap(), b"Wikipedia i");
+        assert_eq!(dechunk(b"<html>not chunked</html>"), None);
+        assert_eq!(dechunk(b"4\r\nWi"), None);
+    }
+}
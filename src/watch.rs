@@ -0,0 +1,242 @@
+use reqwest::{header::CONTENT_TYPE, Client, Url};
+use scraper::{Html, Selector};
+use std::{
+    fs,
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    check_status, datetime, diff, download, extract, interrupt, json::Json, log, metrics, notify,
+    parse_duration, parse_selector, snapshot, Error,
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct WatchArgs {
+    /// the page to keep an eye on
+    url: String,
+
+    /// only watch these parts of the page
+    selector: Option<String>,
+
+    #[clap(short, long)]
+    attribute: Option<String>,
+
+    /// how long to wait between two fetches, e.g. `30s` or `5m`
+    #[clap(short, long, default_value = "5m", parse(try_from_str = parse_duration))]
+    interval: Duration,
+
+    /// print a diff against the last snapshot instead of everything
+    #[clap(long)]
+    diff: bool,
+
+    /// show a desktop notification when the page changes
+    #[clap(long)]
+    notify: bool,
+
+    /// POST the change as json to this url, works with Slack, Discord and ntfy
+    #[clap(long)]
+    webhook: Vec<Url>,
+
+    /// serve request, failure and change metrics for Prometheus on this address, e.g. `127.0.0.1:9184`
+    #[clap(long)]
+    metrics: Option<SocketAddr>,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct DiffArgs {
+    /// the page to compare with its last snapshot
+    url: String,
+
+    /// compare with this page instead, e.g. staging against production
+    other: Option<String>,
+
+    /// only compare these parts of the pages
+    #[clap(short, long)]
+    selector: Option<String>,
+
+    #[clap(short, long)]
+    attribute: Option<String>,
+
+    /// compare with a saved html file instead
+    #[clap(long, conflicts_with = "other")]
+    against: Option<PathBuf>,
+}
+
+/// what the page yields, one value per line, error statuses are errors
+pub async fn fetch(
+    client: &Client,
+    url: &str,
+    selector: Option<&Selector>,
+    attribute: Option<&str>,
+) -> Result<String, Error> {
+    let page = download(client, url).await?;
+    check_status(url, &page)?;
+    Ok(lines(page.body, selector, attribute))
+}
+
+fn lines(body: String, selector: Option<&Selector>, attribute: Option<&str>) -> String {
+    match selector {
+        Some(selector) => extract(&Html::parse_document(&body), selector, attribute).join("\n"),
+        None => body,
+    }
+}
+
+/// prints the changes since the last snapshot and saves the new one, returns whether anything changed
+fn compare(url: &str, selector: Option<&str>, attribute: Option<&str>, current: &str) -> bool {
+    let previous = snapshot::load(url, selector, attribute);
+    if let Err(error) = snapshot::save(url, selector, attribute, current) {
+        eprintln!("Failed to save snapshot: {}", error);
+    }
+    match previous {
+        Some(previous) => {
+            let lines = diff::unified(
+                &previous,
+                current,
+                &format!("{} (previous)", url),
+                &format!("{} (now)", url),
+            );
+            diff::print(&lines);
+            !lines.is_empty()
+        }
+        None => {
+            eprintln!("No snapshot of '{}' yet, saved one", url);
+            false
+        }
+    }
+}
+
+/// a one line description of a change, for notifications
+pub fn summary(previous: &str, current: &str) -> String {
+    let lines = diff::unified(previous, current, "", "");
+    let count = |sign| {
+        lines
+            .iter()
+            .skip(2)
+            .filter(|line| line.starts_with(sign))
+            .count()
+    };
+    let first_added = lines
+        .iter()
+        .skip(2)
+        .find_map(|line| line.strip_prefix('+'))
+        .map(|line| line.trim())
+        .unwrap_or_default();
+    let mut summary = format!("+{} -{} lines", count('+'), count('-'));
+    if !first_added.is_empty() {
+        summary.push_str(": ");
+        summary.extend(first_added.chars().take(120));
+    }
+    summary
+}
+
+/// posts the change, with `text` for Slack and `content` for Discord, ntfy gets plain text
+pub async fn webhook(client: &Client, webhook: &Url, url: &str, previous: &str, current: &str) {
+    let summary = format!("{} changed, {}", url, summary(previous, current));
+    let request = if webhook.host_str().is_some_and(|host| host.contains("ntfy")) {
+        client
+            .post(webhook.clone())
+            .header("Title", format!("{} changed", url))
+            .body(summary)
+    } else {
+        let diff = diff::unified(previous, current, "previous", "now").join("\n");
+        let payload = Json::object([
+            ("url", url.into()),
+            ("old", previous.into()),
+            ("new", current.into()),
+            ("diff", diff.into()),
+            ("timestamp", datetime::now().into()),
+            ("text", summary.as_str().into()),
+            ("content", summary.as_str().into()),
+        ]);
+        client
+            .post(webhook.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(payload.to_string())
+    };
+    match request.send().await {
+        Ok(res) if res.status().is_success() => {}
+        Ok(res) => log::warn(format!("Webhook '{}' answered {}", webhook, res.status())),
+        Err(_) => log::warn(format!("Failed to POST to '{}'", webhook)),
+    }
+}
+
+pub async fn run(client: &Client, args: WatchArgs) -> Result<(), Error> {
+    let selector = args.selector.as_deref().map(parse_selector).transpose()?;
+    let attribute = args.attribute.as_deref();
+    let mut previous: Option<String> = None;
+    if let Some(address) = args.metrics {
+        metrics::serve(address).await?;
+        metrics::register(&args.url);
+    }
+
+    loop {
+        let started = Instant::now();
+        let current = fetch(client, &args.url, selector.as_ref(), attribute).await;
+        metrics::request(&args.url, started.elapsed(), current.is_ok());
+        match current {
+            Ok(current) if previous.as_ref() != Some(&current) => {
+                if previous.is_some() {
+                    eprintln!("{} changed", datetime::now());
+                    metrics::changed(&args.url);
+                }
+                if args.diff {
+                    compare(&args.url, args.selector.as_deref(), attribute, &current);
+                } else {
+                    println!("{}", current);
+                    let selector = args.selector.as_deref();
+                    if let Err(error) = snapshot::save(&args.url, selector, attribute, &current) {
+                        eprintln!("Failed to save snapshot: {}", error);
+                    }
+                }
+                if let Some(previous) = &previous {
+                    if args.notify {
+                        let title = format!("{} changed", args.url);
+                        notify::send(&title, &summary(previous, &current));
+                    }
+                    for hook in &args.webhook {
+                        webhook(client, hook, &args.url, previous, &current).await;
+                    }
+                }
+                previous = Some(current);
+            }
+            Ok(_) => {}
+            Err(error) => log::error(error),
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(args.interval) => {}
+            _ = interrupt::wait() => return Ok(()),
+        }
+    }
+}
+
+/// exits with status 1 if the pages differ, like diff(1)
+pub async fn diff(client: &Client, args: DiffArgs) -> Result<(), Error> {
+    let selector = args.selector.as_deref().map(parse_selector).transpose()?;
+    let attribute = args.attribute.as_deref();
+    let current = fetch(client, &args.url, selector.as_ref(), attribute).await?;
+    let lines = match (&args.other, &args.against) {
+        (Some(other), _) => {
+            let other_lines = fetch(client, other, selector.as_ref(), attribute).await?;
+            diff::unified(&current, &other_lines, &args.url, other)
+        }
+        (_, Some(path)) => {
+            let saved = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+            let saved = lines(saved, selector.as_ref(), attribute);
+            diff::unified(&saved, &current, &path.display().to_string(), &args.url)
+        }
+        (None, None) => {
+            if compare(&args.url, args.selector.as_deref(), attribute, &current) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    };
+    diff::print(&lines);
+    if !lines.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use futures_util::{stream, StreamExt};
+use indicatif::HumanBytes;
+use regex::Regex;
+use reqwest::{header::CONTENT_TYPE, Client, Url};
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+
+use crate::{
+    crawl::resolve,
+    download,
+    json::Json,
+    mirror::{is_asset, REFERENCES},
+    rate, Error, Format,
+};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct WeightArgs {
+    /// the page to weigh
+    url: String,
+
+    /// `jsonl` prints the report as a single json object
+    #[clap(short, long, arg_enum, default_value = "text")]
+    format: Format,
+
+    /// how many of the largest assets to list
+    #[clap(long, default_value = "5")]
+    top: usize,
+
+    /// how many assets to download at the same time
+    #[clap(short, long, default_value = "8")]
+    concurrency: usize,
+}
+
+const KINDS: [&str; 6] = ["html", "css", "js", "image", "font", "other"];
+
+/// what kind of asset a response is, by content type and then by extension
+fn kind(content_type: &str, url: &Url) -> &'static str {
+    let content_type = content_type.to_ascii_lowercase();
+    let extension = url
+        .path()
+        .rsplit_once('.')
+        .map_or(String::new(), |(_, ext)| ext.to_ascii_lowercase());
+    if content_type.contains("html") {
+        "html"
+    } else if content_type.contains("css") || extension == "css" {
+        "css"
+    } else if content_type.contains("javascript") || matches!(extension.as_str(), "js" | "mjs") {
+        "js"
+    } else if content_type.starts_with("image/")
+        || matches!(
+            extension.as_str(),
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "avif" | "svg" | "ico"
+        )
+    {
+        "image"
+    } else if content_type.starts_with("font/")
+        || matches!(extension.as_str(), "woff" | "woff2" | "ttf" | "otf" | "eot")
+    {
+        "font"
+    } else {
+        "other"
+    }
+}
+
+/// urls a stylesheet pulls in through `url(...)` and `@import`
+fn stylesheet_references(css: &str, base: &Url) -> Vec<Url> {
+    let references =
+        Regex::new(r#"url\(\s*['"]?([^'")]+)['"]?\s*\)|@import\s+['"]([^'"]+)['"]"#).unwrap();
+    references
+        .captures_iter(css)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .filter(|reference| !reference.as_str().starts_with("data:"))
+        .filter_map(|reference| resolve(base, reference.as_str()))
+        .collect()
+}
+
+struct Asset {
+    url: Url,
+    kind: &'static str,
+    bytes: u64,
+    body: Option<String>,
+}
+
+async fn fetch(client: &Client, url: Url) -> Option<Asset> {
+    rate::wait(&url).await;
+    let res = match client.get(url.clone()).send().await {
+        Ok(res) if res.status().is_success() => res,
+        _ => {
+            eprintln!("Failed to GET from '{}'", url);
+            return None;
+        }
+    };
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let bytes = res.bytes().await.ok()?;
+    let kind = kind(&content_type, &url);
+    Some(Asset {
+        kind,
+        bytes: bytes.len() as u64,
+        // stylesheets are read for the fonts and images they reference
+        body: (kind == "css").then(|| String::from_utf8_lossy(&bytes).into_owned()),
+        url,
+    })
+}
+
+async fn fetch_all(client: &Client, urls: Vec<Url>, concurrency: usize) -> Vec<Asset> {
+    stream::iter(urls)
+        .map(|url| fetch(client, url))
+        .buffer_unordered(concurrency.max(1))
+        .filter_map(|asset| async { asset })
+        .collect()
+        .await
+}
+
+pub async fn run(client: &Client, args: WeightArgs) -> Result<(), Error> {
+    let page = download(client, &args.url).await?;
+    let document = Html::parse_document(&page.body);
+
+    let mut seen = HashSet::from([page.url.clone()]);
+    let mut urls = Vec::new();
+    for (css, attr) in REFERENCES {
+        let selector = Selector::parse(css).unwrap();
+        for element in document.select(&selector) {
+            if !is_asset(css, element.value()) {
+                continue;
+            }
+            let target = element
+                .value()
+                .attr(attr)
+                .and_then(|r| resolve(&page.url, r));
+            if let Some(target) = target.filter(|target| seen.insert(target.clone())) {
+                urls.push(target);
+            }
+        }
+    }
+    let style = Selector::parse("style").unwrap();
+    for element in document.select(&style) {
+        let css: String = element.text().collect();
+        urls.extend(
+            stylesheet_references(&css, &page.url)
+                .into_iter()
+                .filter(|target| seen.insert(target.clone())),
+        );
+    }
+
+    let mut assets = vec![Asset {
+        url: page.url.clone(),
+        kind: "html",
+        bytes: page.size as u64,
+        body: None,
+    }];
+    assets.extend(fetch_all(client, urls, args.concurrency).await);
+    // one more round for what stylesheets reference, mostly fonts and background images
+    let nested: Vec<_> = assets
+        .iter()
+        .filter_map(|asset| Some((asset.body.as_deref()?, &asset.url)))
+        .flat_map(|(css, url)| stylesheet_references(css, url))
+        .filter(|target| seen.insert(target.clone()))
+        .collect();
+    assets.extend(fetch_all(client, nested, args.concurrency).await);
+
+    let total: u64 = assets.iter().map(|asset| asset.bytes).sum();
+    let by_kind: Vec<_> = KINDS
+        .iter()
+        .map(|kind| {
+            let of_kind = assets.iter().filter(|asset| asset.kind == *kind);
+            let requests = of_kind.clone().count();
+            (
+                *kind,
+                requests,
+                of_kind.map(|asset| asset.bytes).sum::<u64>(),
+            )
+        })
+        .filter(|(_, requests, _)| *requests > 0)
+        .collect();
+    let requests: usize = by_kind.iter().map(|(_, requests, _)| requests).sum();
+    assets.sort_by_key(|asset| std::cmp::Reverse(asset.bytes));
+    assets.truncate(args.top);
+
+    match args.format {
+        Format::Jsonl => {
+            let by_kind: Vec<_> = by_kind
+                .iter()
+                .map(|(kind, requests, bytes)| {
+                    (
+                        *kind,
+                        Json::object([
+                            ("requests", (*requests).into()),
+                            ("bytes", (*bytes).into()),
+                        ]),
+                    )
+                })
+                .collect();
+            let largest: Vec<_> = assets
+                .iter()
+                .map(|asset| {
+                    Json::object([
+                        ("url", asset.url.as_str().into()),
+                        ("type", asset.kind.into()),
+                        ("bytes", asset.bytes.into()),
+                    ])
+                })
+                .collect();
+            let report = Json::object([
+                ("url", page.url.as_str().into()),
+                ("requests", requests.into()),
+                ("bytes", total.into()),
+                ("by_type", Json::object(by_kind)),
+                ("largest", largest.into()),
+            ]);
+            println!("{}", report);
+        }
+        Format::Text => {
+            println!("{}", page.url);
+            println!("  {} requests, {}", requests, HumanBytes(total));
+            for (kind, requests, bytes) in &by_kind {
+                println!(
+                    "  {:<6} {:>4} requests  {:>12}",
+                    kind,
+                    requests,
+                    HumanBytes(*bytes).to_string()
+                );
+            }
+            println!("largest");
+            for asset in &assets {
+                println!(
+                    "  {:>12}  {:<6} {}",
+                    HumanBytes(asset.bytes).to_string(),
+                    asset.kind,
+                    asset.url
+                );
+            }
+        }
+    }
+    Ok(())
+}
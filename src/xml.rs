@@ -0,0 +1,235 @@
+//! a small, forgiving xml reader for sitemaps and feeds
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    /// name without its namespace prefix
+    pub fn local_name(&self) -> &str {
+        local(&self.name)
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match child {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|e| e.local_name() == name)
+    }
+
+    /// trimmed text of the first child with the given local name
+    pub fn child_text(&self, name: &str) -> Option<String> {
+        self.child(name)
+            .map(|child| child.text().trim().to_string())
+    }
+
+    /// all text below this element
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for child in &self.children {
+            match child {
+                Node::Text(t) => text.push_str(t),
+                Node::Element(e) => text.push_str(&e.text()),
+            }
+        }
+        text
+    }
+}
+
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let end = match rest.find(';') {
+            Some(end) if end < 12 => end,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse()))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// parses a document and returns its root element
+pub fn parse(input: &str) -> Result<Element, String> {
+    let mut stack = vec![Element::default()];
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let lt = match rest.find('<') {
+            Some(lt) => lt,
+            None => {
+                push_text(&mut stack, rest);
+                break;
+            }
+        };
+        push_text(&mut stack, &rest[..lt]);
+        rest = &rest[lt..];
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>").unwrap_or(after.len());
+            let text = Node::Text(after[..end].to_string());
+            stack.last_mut().unwrap().children.push(text);
+            rest = after.get(end + 3..).unwrap_or("");
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').ok_or("unterminated closing tag")?;
+            let name = after[..end].trim();
+            // forgive unclosed elements by closing up to the matching one
+            if let Some(open) = stack.iter().rposition(|e| e.name == name) {
+                while stack.len() > open.max(1) {
+                    let element = stack.pop().unwrap();
+                    stack
+                        .last_mut()
+                        .unwrap()
+                        .children
+                        .push(Node::Element(element));
+                }
+            }
+            rest = &after[end + 1..];
+        } else {
+            let (element, self_closing, after) = parse_tag(&rest[1..])?;
+            if self_closing {
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .children
+                    .push(Node::Element(element));
+            } else {
+                stack.push(element);
+            }
+            rest = after;
+        }
+    }
+
+    while stack.len() > 1 {
+        let element = stack.pop().unwrap();
+        stack
+            .last_mut()
+            .unwrap()
+            .children
+            .push(Node::Element(element));
+    }
+    stack
+        .pop()
+        .unwrap()
+        .children
+        .into_iter()
+        .find_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+        .ok_or_else(|| "no xml root element".into())
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    if !text.is_empty() && stack.len() > 1 {
+        let text = Node::Text(unescape(text));
+        stack.last_mut().unwrap().children.push(text);
+    }
+}
+
+/// parses the inside of `<name attr="value">`, returns the rest of the input
+fn parse_tag(input: &str) -> Result<(Element, bool, &str), String> {
+    let name_end = input
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .ok_or("unterminated tag")?;
+    let mut element = Element {
+        name: input[..name_end].to_string(),
+        ..Default::default()
+    };
+    let mut rest = &input[name_end..];
+
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            return Ok((element, true, after));
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return Ok((element, false, after));
+        }
+        if rest.is_empty() {
+            return Err(format!("unterminated tag <{}>", element.name));
+        }
+
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace() || c == '>' || c == '/')
+            .ok_or("unterminated attribute")?;
+        let key = rest[..key_end].to_string();
+        rest = rest[key_end..].trim_start();
+        if key.is_empty() {
+            rest = &rest[1..];
+            continue;
+        }
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let quote = after.chars().next().ok_or("unterminated attribute")?;
+                if quote == '"' || quote == '\'' {
+                    let end = after[1..].find(quote).ok_or("unterminated attribute")?;
+                    rest = &after[end + 2..];
+                    unescape(&after[1..end + 1])
+                } else {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    rest = &after[end..];
+                    unescape(&after[..end])
+                }
+            }
+            None => String::new(),
+        };
+        element.attributes.push((key, value));
+    }
+}
@@ -0,0 +1,592 @@
+//! a practical subset of XPath 1.0 over the xml reader: absolute and relative
+//! paths, `//`, `*`, `@attribute`, `text()`, `..` and predicates like `[2]`,
+//! `[last()]`, `[@id]` or `[name='value']`, with the `child`, `descendant`,
+//! `attribute`, `self` and `parent` axes spelled out too, anything else is an error
+//! rather than a path that matches nothing
+//!
+//! prefixed names are matched by namespace uri, using the bindings given with
+//! `--ns` before those declared in the document, unprefixed names match any
+//! namespace so `//loc` works on sitemaps without declaring theirs
+
+use std::rc::Rc;
+
+use crate::xml::{Element, Node};
+
+/// `prefix=uri`, as given to `--ns`
+pub fn parse_namespace(binding: &str) -> Result<(String, String), String> {
+    match binding.split_once('=') {
+        Some((prefix, uri)) if !prefix.is_empty() && !uri.is_empty() => {
+            Ok((prefix.to_string(), uri.to_string()))
+        }
+        _ => Err(format!(
+            "Invalid namespace '{}', expected prefix=uri",
+            binding
+        )),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Test {
+    /// optional prefix and local name, which may be `*`
+    Name(Option<String>, String),
+    Attribute(String),
+    Text,
+    Current,
+    Parent,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Attribute(String),
+    Child(String),
+    Text,
+}
+
+#[derive(Debug, Clone)]
+enum Predicate {
+    Position(usize),
+    Last,
+    Exists(Operand),
+    Compare(Operand, bool, String),
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    descendant: bool,
+    test: Test,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct XPath {
+    steps: Vec<Step>,
+    namespaces: Vec<(String, String)>,
+}
+
+/// in-scope namespace declarations, innermost last
+type Scope = Vec<(String, String)>;
+
+#[derive(Clone)]
+enum Item<'a> {
+    /// the document node above the root element
+    Document(&'a Element),
+    Element(&'a Element, Rc<Scope>, Vec<&'a Element>),
+    Attribute(String),
+    Text(String),
+}
+
+/// splits on `separator` outside of brackets and quotes
+fn split_outside(input: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut start) = (0, None, 0);
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, c) if c == separator && depth == 0 => {
+                parts.push(&input[start..i]);
+                start = i + c.len_utf8();
+            }
+            (None, '[' | '(') => depth += 1,
+            (None, ']' | ')') => depth -= 1,
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+fn parse_operand(operand: &str) -> Result<Operand, String> {
+    let operand = operand.trim();
+    if let Some(attribute) = operand.strip_prefix('@') {
+        Ok(Operand::Attribute(attribute.to_string()))
+    } else if operand == "text()" || operand == "." {
+        Ok(Operand::Text)
+    } else if !operand.is_empty()
+        && operand
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-.:".contains(c))
+    {
+        Ok(Operand::Child(operand.to_string()))
+    } else {
+        Err(format!("unsupported xpath predicate '{}'", operand))
+    }
+}
+
+fn parse_predicate(predicate: &str) -> Result<Predicate, String> {
+    let predicate = predicate.trim();
+    if predicate == "last()" {
+        return Ok(Predicate::Last);
+    }
+    if let Ok(position) = predicate.parse() {
+        return Ok(Predicate::Position(position));
+    }
+    let (operand, negate, literal) = match split_outside(predicate, '=')[..] {
+        [left, right] => match left.strip_suffix('!') {
+            Some(left) => (left, true, right),
+            None => (left, false, right),
+        },
+        [_] => return Ok(Predicate::Exists(parse_operand(predicate)?)),
+        _ => return Err(format!("unsupported xpath predicate '{}'", predicate)),
+    };
+    let literal = literal.trim();
+    let literal = literal
+        .strip_prefix(['\'', '"'])
+        .and_then(|l| l.strip_suffix(['\'', '"']))
+        .unwrap_or(literal);
+    Ok(Predicate::Compare(
+        parse_operand(operand)?,
+        negate,
+        literal.to_string(),
+    ))
+}
+
+/// `*`, `name` or `prefix:name`
+fn is_name(name: &str) -> bool {
+    let valid = |part: &str| {
+        part == "*"
+            || part.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                && part
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "_-.".contains(c))
+    };
+    match name.split_once(':') {
+        Some((prefix, local)) => prefix != "*" && valid(prefix) && valid(local),
+        None => valid(name),
+    }
+}
+
+fn parse_step(step: &str, mut descendant: bool) -> Result<Step, String> {
+    let (test, predicates) = match step.find('[') {
+        Some(open) => (&step[..open], &step[open..]),
+        None => (step, ""),
+    };
+    let test = test.trim();
+    let test = match test.split_once("::") {
+        Some(("child", test)) => test,
+        Some(("descendant", test)) if !descendant => {
+            descendant = true;
+            test
+        }
+        Some(("attribute", name)) => {
+            return parse_step(&format!("@{}{}", name, predicates), descendant)
+        }
+        Some(("self", "node()")) => ".",
+        Some(("parent", "node()")) => "..",
+        Some((axis @ ("self" | "parent"), test)) => {
+            return Err(format!(
+                "only node() is supported on the {} axis, not '{}'",
+                axis, test
+            ))
+        }
+        Some((axis, _)) => return Err(format!("unsupported xpath axis '{}'", axis)),
+        None => test,
+    };
+    let test = match test {
+        "." => Test::Current,
+        ".." => Test::Parent,
+        "text()" => Test::Text,
+        "node()" => Test::Name(None, "*".into()),
+        test if test.starts_with('@') && is_name(&test[1..]) => {
+            Test::Attribute(test[1..].to_string())
+        }
+        "" => return Err("empty step in xpath".into()),
+        test if is_name(test) => match test.split_once(':') {
+            Some((prefix, local)) => Test::Name(Some(prefix.to_string()), local.to_string()),
+            None => Test::Name(None, test.to_string()),
+        },
+        test => match test.split_once('(') {
+            Some((function, _)) => {
+                return Err(format!(
+                    "unsupported xpath function '{}()'",
+                    function.trim()
+                ))
+            }
+            None => return Err(format!("unsupported xpath step '{}'", test)),
+        },
+    };
+
+    let mut parsed = Vec::new();
+    let mut rest = predicates;
+    while let Some(inside) = rest.strip_prefix('[') {
+        let end = split_outside(inside, ']')[0].len();
+        if end == inside.len() {
+            return Err("missing ']' in xpath".into());
+        }
+        parsed.push(parse_predicate(&inside[..end])?);
+        rest = &inside[end + 1..];
+    }
+    if !rest.trim().is_empty() {
+        return Err(format!("unexpected '{}' in xpath", rest));
+    }
+    Ok(Step {
+        descendant,
+        test,
+        predicates: parsed,
+    })
+}
+
+impl XPath {
+    pub fn new(path: &str, namespaces: Vec<(String, String)>) -> Result<Self, String> {
+        let mut steps = Vec::new();
+        let mut descendant = false;
+        let path = path.trim();
+        if split_outside(path, '|').len() > 1 {
+            return Err("unsupported xpath union '|'".into());
+        }
+        let relative = path.strip_prefix('/').unwrap_or(path);
+        for part in split_outside(relative, '/') {
+            // an empty part comes from `//`
+            if part.is_empty() {
+                descendant = true;
+                continue;
+            }
+            steps.push(parse_step(part, descendant)?);
+            descendant = false;
+        }
+        if descendant || steps.is_empty() {
+            return Err(format!("incomplete xpath '{}'", path));
+        }
+        Ok(XPath { steps, namespaces })
+    }
+
+    fn namespace_of<'a>(&'a self, prefix: &str, scope: &'a Scope) -> Option<&'a str> {
+        self.namespaces
+            .iter()
+            .chain(scope.iter().rev())
+            .find(|(p, _)| p == prefix)
+            .map(|(_, uri)| uri.as_str())
+    }
+
+    fn matches_name(
+        &self,
+        element: &Element,
+        scope: &Scope,
+        prefix: Option<&str>,
+        local: &str,
+    ) -> bool {
+        if local != "*" && element.local_name() != local {
+            return false;
+        }
+        let prefix = match prefix {
+            Some(prefix) => prefix,
+            None => return true,
+        };
+        let own_prefix = element.name.split_once(':').map_or("", |(p, _)| p);
+        match (
+            self.namespace_of(prefix, scope),
+            scope.iter().rev().find(|(p, _)| p == own_prefix),
+        ) {
+            (Some(wanted), Some((_, actual))) => wanted == actual,
+            _ => own_prefix == prefix,
+        }
+    }
+
+    fn operand(&self, element: &Element, scope: &Rc<Scope>, operand: &Operand) -> Vec<String> {
+        match operand {
+            Operand::Attribute(name) => attribute(element, name)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            Operand::Text => vec![element.text()],
+            Operand::Child(name) => {
+                let (prefix, local) = match name.split_once(':') {
+                    Some((prefix, local)) => (Some(prefix), local),
+                    None => (None, name.as_str()),
+                };
+                element
+                    .elements()
+                    .filter(|child| {
+                        self.matches_name(child, &child_scope(child, scope), prefix, local)
+                    })
+                    .map(|child| child.text().trim().to_string())
+                    .collect()
+            }
+        }
+    }
+
+    fn keep(&self, item: &Item, position: usize, count: usize, predicate: &Predicate) -> bool {
+        match predicate {
+            Predicate::Position(wanted) => position == *wanted,
+            Predicate::Last => position == count,
+            Predicate::Exists(operand) | Predicate::Compare(operand, _, _) => {
+                let values = match item {
+                    Item::Element(element, scope, _) => self.operand(element, scope, operand),
+                    Item::Attribute(value) | Item::Text(value) => match operand {
+                        Operand::Text => vec![value.clone()],
+                        _ => vec![],
+                    },
+                    Item::Document(_) => vec![],
+                };
+                match predicate {
+                    Predicate::Compare(_, negate, literal) => {
+                        values.iter().any(|value| (value == literal) != *negate)
+                    }
+                    _ => !values.is_empty(),
+                }
+            }
+        }
+    }
+
+    /// what a single step yields from one context item
+    fn step<'a>(&self, item: &Item<'a>, test: &Test) -> Vec<Item<'a>> {
+        let (element, scope, ancestors) = match item {
+            Item::Document(root) => {
+                return match test {
+                    Test::Name(prefix, local) => {
+                        let scope = child_scope(root, &Rc::new(Vec::new()));
+                        match self.matches_name(root, &scope, prefix.as_deref(), local) {
+                            true => vec![Item::Element(root, scope, vec![])],
+                            false => vec![],
+                        }
+                    }
+                    Test::Current => vec![item.clone()],
+                    _ => vec![],
+                }
+            }
+            Item::Element(element, scope, ancestors) => (*element, scope, ancestors),
+            Item::Attribute(_) | Item::Text(_) => return vec![],
+        };
+        let mut path = ancestors.clone();
+        path.push(element);
+        match test {
+            Test::Current => vec![item.clone()],
+            Test::Parent => match ancestors.split_last() {
+                Some((parent, rest)) => {
+                    // the parent's own declarations are already in scope, close enough
+                    vec![Item::Element(parent, scope.clone(), rest.to_vec())]
+                }
+                None => vec![],
+            },
+            Test::Attribute(name) => element
+                .attributes
+                .iter()
+                .filter(|(key, _)| name == "*" || key == name || local(key) == name)
+                .filter(|(key, _)| !key.starts_with("xmlns"))
+                .map(|(_, value)| Item::Attribute(value.clone()))
+                .collect(),
+            Test::Text => element
+                .children
+                .iter()
+                .filter_map(|child| match child {
+                    Node::Text(text) if !text.trim().is_empty() => {
+                        Some(Item::Text(text.trim().to_string()))
+                    }
+                    _ => None,
+                })
+                .collect(),
+            Test::Name(prefix, local) => element
+                .elements()
+                .filter_map(|child| {
+                    let scope = child_scope(child, scope);
+                    self.matches_name(child, &scope, prefix.as_deref(), local)
+                        .then(|| Item::Element(child, scope, path.clone()))
+                })
+                .collect(),
+        }
+    }
+
+    /// every matched node as text: elements by their text, attributes by their value
+    pub fn evaluate(&self, root: &Element) -> Vec<String> {
+        let mut items = vec![Item::Document(root)];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for item in &items {
+                let mut contexts = vec![item.clone()];
+                if step.descendant {
+                    descendants(item, &mut contexts);
+                }
+                for context in &contexts {
+                    let mut found = self.step(context, &step.test);
+                    for predicate in &step.predicates {
+                        let count = found.len();
+                        found = found
+                            .into_iter()
+                            .enumerate()
+                            .filter(|(i, found)| self.keep(found, i + 1, count, predicate))
+                            .map(|(_, found)| found)
+                            .collect();
+                    }
+                    next.extend(found);
+                }
+            }
+            items = next;
+        }
+        items
+            .into_iter()
+            .map(|item| match item {
+                Item::Document(root) => root.text().trim().to_string(),
+                Item::Element(element, _, _) => element.text().trim().to_string(),
+                Item::Attribute(value) | Item::Text(value) => value,
+            })
+            .collect()
+    }
+}
+
+fn local(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn attribute<'a>(element: &'a Element, name: &str) -> Option<&'a str> {
+    element
+        .attributes
+        .iter()
+        .find(|(key, _)| key == name || local(key) == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// the scope inside `element`, with its own `xmlns` declarations added
+fn child_scope(element: &Element, parent: &Rc<Scope>) -> Rc<Scope> {
+    let declared: Vec<_> = element
+        .attributes
+        .iter()
+        .filter_map(|(key, uri)| match key.as_str() {
+            "xmlns" => Some((String::new(), uri.clone())),
+            key => key
+                .strip_prefix("xmlns:")
+                .map(|prefix| (prefix.to_string(), uri.clone())),
+        })
+        .collect();
+    if declared.is_empty() {
+        return parent.clone();
+    }
+    let mut scope = parent.as_ref().clone();
+    scope.extend(declared);
+    Rc::new(scope)
+}
+
+fn descendants<'a>(item: &Item<'a>, out: &mut Vec<Item<'a>>) {
+    let (element, scope, ancestors) = match item {
+        Item::Document(root) => {
+            let root = Item::Element(root, child_scope(root, &Rc::new(Vec::new())), vec![]);
+            out.push(root.clone());
+            return descendants(&root, out);
+        }
+        Item::Element(element, scope, ancestors) => (element, scope, ancestors),
+        _ => return,
+    };
+    let mut path = ancestors.clone();
+    path.push(element);
+    for child in element.elements() {
+        let child = Item::Element(child, child_scope(child, scope), path.clone());
+        out.push(child.clone());
+        descendants(&child, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml;
+
+    const FEED: &str = r#"<?xml version="1.0"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+  <url id="home"><loc>https://example.com/</loc><priority>1.0</priority></url>
+  <url><loc>https://example.com/a</loc><priority>0.5</priority>
+    <image:image><image:loc>https://example.com/a.png</image:loc></image:image>
+  </url>
+  <url lang="de"><loc>https://example.com/b</loc><priority>0.5</priority></url>
+</urlset>"#;
+
+    fn eval(path: &str) -> Vec<String> {
+        let root = xml::parse(FEED).unwrap();
+        XPath::new(path, vec![]).unwrap().evaluate(&root)
+    }
+
+    fn error(path: &str) -> String {
+        XPath::new(path, vec![]).unwrap_err()
+    }
+
+    #[test]
+    fn paths() {
+        let locs = [
+            "https://example.com/",
+            "https://example.com/a",
+            "https://example.com/b",
+        ];
+        assert_eq!(eval("/urlset/url/loc"), locs);
+        // unprefixed names match in any namespace
+        assert_eq!(eval("//loc").len(), 4);
+        assert_eq!(eval("//url/loc/text()"), locs);
+        assert_eq!(eval("//image:loc"), ["https://example.com/a.png"]);
+        assert_eq!(eval("//url/@id"), ["home"]);
+        assert_eq!(eval("//url/*/image:loc"), ["https://example.com/a.png"]);
+    }
+
+    #[test]
+    fn axes() {
+        assert_eq!(
+            eval("/urlset/child::url/child::loc"),
+            eval("/urlset/url/loc")
+        );
+        assert_eq!(
+            eval("/descendant::image:loc"),
+            ["https://example.com/a.png"]
+        );
+        assert_eq!(eval("//url/attribute::lang"), ["de"]);
+        assert_eq!(eval("//image:loc/../../loc"), ["https://example.com/a"]);
+        assert_eq!(
+            eval("//image:loc/parent::node()/parent::node()/self::node()/loc"),
+            ["https://example.com/a"]
+        );
+    }
+
+    #[test]
+    fn predicates() {
+        assert_eq!(eval("//url[2]/loc"), ["https://example.com/a"]);
+        assert_eq!(eval("//url[last()]/loc"), ["https://example.com/b"]);
+        assert_eq!(eval("//url[@lang]/loc"), ["https://example.com/b"]);
+        assert_eq!(eval("//url[@lang='de']/loc"), ["https://example.com/b"]);
+        assert_eq!(eval("//url[priority!='0.5']/loc"), ["https://example.com/"]);
+        assert_eq!(
+            eval("//url[priority=\"0.5\"][2]/loc"),
+            ["https://example.com/b"]
+        );
+        assert_eq!(
+            eval("//loc[.='https://example.com/a']"),
+            ["https://example.com/a"]
+        );
+    }
+
+    #[test]
+    fn namespace_bindings() {
+        let root = xml::parse(FEED).unwrap();
+        let namespaces = vec![(
+            "s".to_string(),
+            "http://www.sitemaps.org/schemas/sitemap/0.9".to_string(),
+        )];
+        let path = XPath::new("/s:urlset/s:url[1]/s:loc", namespaces).unwrap();
+        assert_eq!(path.evaluate(&root), ["https://example.com/"]);
+    }
+
+    #[test]
+    fn rejects_unsupported() {
+        assert_eq!(
+            error("count(//url)"),
+            "unsupported xpath function 'count()'"
+        );
+        assert_eq!(
+            error("string(//loc)"),
+            "unsupported xpath function 'string()'"
+        );
+        assert_eq!(
+            error("//url/loc | //url/priority"),
+            "unsupported xpath union '|'"
+        );
+        assert_eq!(
+            error("//loc/following-sibling::priority"),
+            "unsupported xpath axis 'following-sibling'"
+        );
+        assert_eq!(
+            error("//loc/parent::url"),
+            "only node() is supported on the parent axis, not 'url'"
+        );
+        assert_eq!(
+            error("//url[contains(loc, 'a')]"),
+            "unsupported xpath predicate 'contains(loc, 'a')'"
+        );
+        assert_eq!(error("//url[1"), "missing ']' in xpath");
+        assert_eq!(error("//url/"), "incomplete xpath '//url/'");
+    }
+}
@@ -0,0 +1,317 @@
+//! the block-style subset of yaml used by recipe files, read into [`Json`] values
+
+use crate::json::Json;
+
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+/// drops a `#` comment that isn't inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            ('#', None) if i == 0 || line[..i].ends_with(char::is_whitespace) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+pub fn parse(input: &str) -> Result<Json, String> {
+    let lines: Vec<_> = input
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, strip_comment(line).trim_end()))
+        .filter(|(_, line)| !line.trim().is_empty() && *line != "---")
+        .map(|(number, line)| {
+            let text = line.trim_start();
+            let indent = &line[..line.len() - text.len()];
+            if indent.contains('\t') {
+                return Err(format!("line {}: tabs can't indent yaml", number));
+            }
+            Ok(Line {
+                number,
+                indent: indent.len(),
+                text,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    if lines.is_empty() {
+        return Ok(Json::Null);
+    }
+    let mut parser = Parser { lines, at: 0 };
+    let indent = parser.lines[0].indent;
+    let value = parser.block(indent)?;
+    match parser.lines.get(parser.at) {
+        Some(line) => Err(format!("line {}: unexpected indentation", line.number)),
+        None => Ok(value),
+    }
+}
+
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    at: usize,
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// splits `key: value` outside of quotes
+fn split_key(text: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) if i == 0 => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (':', None) if text[i + 1..].is_empty() || text[i + 1..].starts_with(' ') => {
+                return Some((text[..i].trim(), text[i + 1..].trim()))
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+impl<'a> Parser<'a> {
+    fn block(&mut self, indent: usize) -> Result<Json, String> {
+        match self.lines.get(self.at) {
+            Some(line) if is_item(line.text) => self.sequence(indent),
+            Some(_) => self.mapping(indent),
+            None => Ok(Json::Null),
+        }
+    }
+
+    /// the value below a `key:` or `-` without inline value
+    fn nested(
+        &mut self,
+        parent_indent: usize,
+        allow_same_indent_items: bool,
+    ) -> Result<Json, String> {
+        match self.lines.get(self.at) {
+            Some(line) if line.indent > parent_indent => self.block(line.indent),
+            Some(line)
+                if allow_same_indent_items
+                    && line.indent == parent_indent
+                    && is_item(line.text) =>
+            {
+                self.sequence(parent_indent)
+            }
+            _ => Ok(Json::Null),
+        }
+    }
+
+    fn sequence(&mut self, indent: usize) -> Result<Json, String> {
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.get(self.at) {
+            if line.indent != indent || !is_item(line.text) {
+                break;
+            }
+            let rest = line.text[1..].trim_start();
+            let number = line.number;
+            if rest.is_empty() {
+                self.at += 1;
+                items.push(self.nested(indent, false)?);
+            } else if split_key(rest).is_some() {
+                // `- key: value` starts a mapping indented past the dash
+                let item_indent = indent + (line.text.len() - rest.len());
+                self.lines[self.at].indent = item_indent;
+                self.lines[self.at].text = rest;
+                items.push(self.mapping(item_indent)?);
+            } else {
+                self.at += 1;
+                items.push(scalar(rest).map_err(|e| format!("line {}: {}", number, e))?);
+            }
+        }
+        Ok(Json::Array(items))
+    }
+
+    fn mapping(&mut self, indent: usize) -> Result<Json, String> {
+        let mut fields = Vec::new();
+        while let Some(line) = self.lines.get(self.at) {
+            if line.indent != indent || is_item(line.text) {
+                break;
+            }
+            let error = |e: String| format!("line {}: {}", line.number, e);
+            let (key, value) =
+                split_key(line.text).ok_or_else(|| error("expected `key: value`".into()))?;
+            let key = match scalar(key).map_err(error)? {
+                Json::String(key) => key,
+                other => other.to_string(),
+            };
+            self.at += 1;
+            let value = if value.is_empty() {
+                self.nested(indent, true)?
+            } else {
+                scalar(value).map_err(error)?
+            };
+            fields.push((key, value));
+        }
+        Ok(Json::Object(fields))
+    }
+}
+
+fn unquote(text: &str) -> Option<String> {
+    if let Some(inner) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        return Some(inner.replace("''", "'"));
+    }
+    let inner = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some(other) => out.push(other),
+                None => {}
+            },
+            (c, false) => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+fn scalar(text: &str) -> Result<Json, String> {
+    let text = text.trim();
+    if let Some(unquoted) = unquote(text) {
+        return Ok(Json::String(unquoted));
+    }
+    if text.starts_with(['"', '\'']) {
+        return Err(format!("unterminated string {}", text));
+    }
+    if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+        return Ok(Json::Array(
+            inner
+                .split(',')
+                .filter(|item| !item.trim().is_empty())
+                .map(scalar)
+                .collect::<Result<_, _>>()?,
+        ));
+    }
+    Ok(match text {
+        "~" | "null" => Json::Null,
+        "true" => Json::Bool(true),
+        "false" => Json::Bool(false),
+        _ => match text.parse::<f64>() {
+            Ok(number) if text.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                Json::Number(number)
+            }
+            _ => Json::String(text.to_string()),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipe() {
+        let recipe = parse(
+            "---\n# products\nurls:\n  - https://example.com/a\n  - https://example.com/b # second\n\
+             paginate: page={1..N}\nuntil_empty: true\nfollow_next: 3\nfields:\n  title: h1\n  \
+             image:\n    selector: img.main\n    attribute: src\n    all: false\n",
+        )
+        .unwrap();
+        assert_eq!(
+            recipe,
+            Json::object(vec![
+                (
+                    "urls",
+                    vec!["https://example.com/a", "https://example.com/b"].into()
+                ),
+                ("paginate", "page={1..N}".into()),
+                ("until_empty", true.into()),
+                ("follow_next", 3.0.into()),
+                (
+                    "fields",
+                    Json::object(vec![
+                        ("title", "h1".into()),
+                        (
+                            "image",
+                            Json::object(vec![
+                                ("selector", "img.main".into()),
+                                ("attribute", "src".into()),
+                                ("all", false.into()),
+                            ])
+                        ),
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn quoting() {
+        let parsed = parse(
+            "single: 'it''s # not a comment'\ndouble: \"tab\\there\\n\"\n\"quoted: key\": x\n\
+             colon: a:b\nnumber: '42'\nnull: ~\n",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            Json::object(vec![
+                ("single", "it's # not a comment".into()),
+                ("double", "tab\there\n".into()),
+                ("quoted: key", "x".into()),
+                ("colon", "a:b".into()),
+                ("number", "42".into()),
+                ("null", Json::Null),
+            ])
+        );
+    }
+
+    #[test]
+    fn nested_sequences() {
+        let parsed = parse(
+            "items:\n- name: a\n  tags: [x, 'y']\n- name: b\n  tags:\n    - z\n-\n  - deep\n",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            Json::object(vec![(
+                "items",
+                Json::Array(vec![
+                    Json::object(vec![("name", "a".into()), ("tags", vec!["x", "y"].into())]),
+                    Json::object(vec![("name", "b".into()), ("tags", vec!["z"].into())]),
+                    Json::Array(vec!["deep".into()]),
+                ])
+            )])
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            parse("a: 1\nnot a mapping\n").unwrap_err(),
+            "line 2: expected `key: value`"
+        );
+        assert_eq!(
+            parse("a: 1\n\nb: \"open\n").unwrap_err(),
+            "line 3: unterminated string \"open"
+        );
+        assert_eq!(
+            parse("a:\n\t- x\n").unwrap_err(),
+            "line 2: tabs can't indent yaml"
+        );
+        assert_eq!(
+            parse("a:\n    b: 1\n  c: 2\n").unwrap_err(),
+            "line 3: unexpected indentation"
+        );
+        assert_eq!(
+            parse("- x\n- 'y\n").unwrap_err(),
+            "line 2: unterminated string 'y"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(parse("# nothing\n---\n").unwrap(), Json::Null);
+    }
+}